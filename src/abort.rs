@@ -0,0 +1,132 @@
+use crate::AbortError;
+use aws_sdk_s3::config::AsyncSleep;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::Client;
+use futures::{StreamExt, TryStreamExt};
+use std::num::NonZeroUsize;
+use std::time::{Duration, SystemTime};
+
+const ATTEMPTS: usize = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Aborts a multipart upload and checks with `ListParts` that it is gone.
+///
+/// Parts still in flight may survive an abort, so both steps are retried with exponential
+/// backoff. The backoff sleeps with the `sleep_impl` of the client, or with tokio under the
+/// `tokio` feature; without either, only one attempt is made rather than retrying at once.
+pub async fn abort_verified(
+    client: &Client,
+    abort: AbortMultipartUploadFluentBuilder,
+) -> Result<(), AbortError> {
+    let input = abort.as_input().clone();
+    let sleep_impl = crate::sleep_impl(client);
+    let attempts = if sleep_impl.is_some() { ATTEMPTS } else { 1 };
+    let mut backoff = INITIAL_BACKOFF;
+    let mut error = AbortError::NotAborted;
+    for attempt in 0..attempts {
+        if let (true, Some(sleep_impl)) = (attempt > 0, &sleep_impl) {
+            sleep_impl.sleep(backoff).await;
+            backoff *= 2;
+        }
+
+        match instrument!(
+            abort.clone().send(),
+            "abort_multipart_upload",
+            bucket = input.get_bucket().as_deref(),
+            key = input.get_key().as_deref(),
+            upload_id = input.get_upload_id().as_deref(),
+            attempt,
+        )
+        .await
+        {
+            Err(err) if err.code() != Some("NoSuchUpload") => {
+                error = AbortError::Abort(err);
+                continue;
+            }
+            _ => (),
+        }
+        match client
+            .list_parts()
+            .set_bucket(input.get_bucket().clone())
+            .set_key(input.get_key().clone())
+            .set_upload_id(input.get_upload_id().clone())
+            .set_expected_bucket_owner(input.get_expected_bucket_owner().clone())
+            .set_request_payer(input.get_request_payer().clone())
+            .max_parts(1)
+            .send()
+            .await
+        {
+            Ok(_) => error = AbortError::NotAborted,
+            Err(err) if err.code() == Some("NoSuchUpload") => return Ok(()),
+            Err(err) => error = AbortError::ListParts(err),
+        }
+    }
+    Err(error)
+}
+
+/// Aborts the multipart uploads in `bucket` under `prefix` that were initiated more than
+/// `older_than` ago, with at most `concurrency_limit` aborts in flight. Returns the key and
+/// upload ID of every aborted upload.
+pub async fn abort_incomplete_uploads(
+    client: &Client,
+    bucket: &str,
+    older_than: Duration,
+    prefix: Option<&str>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<Vec<(String, String)>, AbortError> {
+    let now = client
+        .config()
+        .time_source()
+        .map_or_else(SystemTime::now, |time_source| time_source.now());
+    let threshold = DateTime::from(now - older_than);
+
+    let mut uploads = Vec::new();
+    let (mut key_marker, mut upload_id_marker) = (None, None);
+    loop {
+        let output = client
+            .list_multipart_uploads()
+            .bucket(bucket)
+            .set_prefix(prefix.map(ToOwned::to_owned))
+            .set_key_marker(key_marker)
+            .set_upload_id_marker(upload_id_marker)
+            .send()
+            .await
+            .map_err(AbortError::ListMultipartUploads)?;
+        uploads.extend(
+            output
+                .uploads
+                .into_iter()
+                .flatten()
+                .filter(|upload| {
+                    upload
+                        .initiated
+                        .is_some_and(|initiated| initiated < threshold)
+                })
+                .filter_map(|upload| Some((upload.key?, upload.upload_id?))),
+        );
+        if output.is_truncated != Some(true) {
+            break;
+        }
+        key_marker = output.next_key_marker;
+        upload_id_marker = output.next_upload_id_marker;
+    }
+
+    futures::stream::iter(uploads)
+        .map(|(key, upload_id)| async move {
+            abort_verified(
+                client,
+                client
+                    .abort_multipart_upload()
+                    .bucket(bucket)
+                    .key(&key)
+                    .upload_id(&upload_id),
+            )
+            .await?;
+            Ok((key, upload_id))
+        })
+        .buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+        .try_collect()
+        .await
+}
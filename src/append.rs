@@ -0,0 +1,92 @@
+use crate::into_byte_stream::{from_stream, into_byte_stream};
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput, RequestIds};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::put_object::{PutObjectError, PutObjectOutput};
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use futures::StreamExt;
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+
+/// The result of [`MultipartUpload::append_to`](crate::MultipartUpload::append_to).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
+pub enum AppendOutput {
+    /// The body was appended in place to an object in a directory bucket. Holds the output of
+    /// each part-sized append, none for an empty body.
+    Appended(Vec<PutObjectOutput>),
+    /// The object was written again from the existing object and the body.
+    Rewritten(MultipartUploadOutput),
+}
+
+/// Whether `bucket` is an S3 Express One Zone directory bucket, which supports appends.
+pub(crate) fn is_express(bucket: &str) -> bool {
+    bucket.ends_with("--x-s3")
+}
+
+/// Appends `body` at `offset` with a `PutObject` per part, in order.
+pub(crate) async fn append_express<E>(
+    upload: &MultipartUpload,
+    body: ByteStream,
+    offset: u64,
+    part_size: RangeInclusive<u64>,
+) -> Result<Vec<PutObjectOutput>, MultipartUploadError<E>>
+where
+    E: From<SdkError<PutObjectError>> + From<ByteStreamError>,
+{
+    let mut outputs = Vec::new();
+    let mut parts = std::pin::pin!(crate::split::split(stream(body), part_size, ()));
+    while let Some(part) = parts.next().await {
+        let part = part.map_err(MultipartUploadError::new)?;
+        let output = upload
+            .client
+            .put_object()
+            .set_bucket(upload.create.get_bucket().clone())
+            .set_key(upload.create.get_key().clone())
+            .set_expected_bucket_owner(upload.create.get_expected_bucket_owner().clone())
+            .set_request_payer(upload.create.get_request_payer().clone())
+            .write_offset_bytes((offset + part.offset) as _)
+            .content_length(part.content_length as _)
+            .body(into_byte_stream(part.body))
+            .send()
+            .await
+            .map_err(|err| {
+                let request_ids = RequestIds::new(&err);
+                MultipartUploadError::new(err).request_ids(request_ids)
+            })?;
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// Streams `first`, then `second`.
+pub(crate) fn chain(first: ByteStream, second: ByteStream) -> ByteStream {
+    from_stream(stream(first).chain(stream(second)))
+}
+
+fn stream(
+    mut body: ByteStream,
+) -> impl futures::Stream<Item = Result<bytes::Bytes, ByteStreamError>> + Send {
+    futures::stream::poll_fn(move |cx| Pin::new(&mut body).poll_next(cx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chain, is_express};
+    use aws_sdk_s3::primitives::ByteStream;
+
+    #[test]
+    fn test_is_express() {
+        assert!(is_express("bucket--usw2-az1--x-s3"));
+        assert!(!is_express("bucket"));
+    }
+
+    #[tokio::test]
+    async fn test_chain() {
+        let body = chain(
+            ByteStream::from_static(b"foo"),
+            ByteStream::from_static(b"bar"),
+        );
+        assert_eq!(&body.collect().await.unwrap().into_bytes()[..], b"foobar");
+    }
+}
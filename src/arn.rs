@@ -0,0 +1,139 @@
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_smithy_types::error::operation::BuildError;
+
+pub(crate) fn is_arn(bucket: &str) -> bool {
+    bucket.starts_with("arn:")
+}
+
+/// Checks the bucket of `request` if it is an access point ARN, and replaces a multi-region
+/// access point alias with its ARN in the `aws` partition, which requests through it require.
+/// The account of the alias is taken from the expected bucket owner.
+pub(crate) fn resolve(
+    request: CreateMultipartUploadFluentBuilder,
+) -> Result<CreateMultipartUploadFluentBuilder, BuildError> {
+    let Some(bucket) = request.get_bucket() else {
+        return Ok(request);
+    };
+    if is_arn(bucket) {
+        validate(bucket)?;
+        return Ok(request);
+    }
+    if !bucket.ends_with(".mrap") {
+        return Ok(request);
+    }
+    let Some(account) = request.get_expected_bucket_owner() else {
+        return Err(BuildError::invalid_field(
+            "bucket",
+            format!(
+                "{bucket} is a multi-region access point alias; set expected_bucket_owner or use \
+                 the ARN of the access point"
+            ),
+        ));
+    };
+    let arn = format!("arn:aws:s3::{account}:accesspoint/{bucket}");
+    validate(&arn)?;
+    Ok(request.bucket(arn))
+}
+
+/// Checks that `arn` names an access point that accepts multipart uploads.
+pub(crate) fn validate(arn: &str) -> Result<(), BuildError> {
+    let invalid = |reason: &str| {
+        BuildError::invalid_field(
+            "bucket",
+            format!("{arn} is not an access point ARN: {reason}"),
+        )
+    };
+    let [_, partition, service, region, account, resource] =
+        arn.splitn(6, ':').collect::<Vec<_>>()[..]
+    else {
+        return Err(invalid(
+            "expected arn:<partition>:<service>:<region>:<account>:<resource>",
+        ));
+    };
+    if partition.is_empty() {
+        return Err(invalid("the partition is empty"));
+    }
+    if account.len() != 12 || !account.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid("the account is not a 12-digit account ID"));
+    }
+    let name = match service {
+        "s3" => resource
+            .strip_prefix("accesspoint/")
+            .or_else(|| resource.strip_prefix("accesspoint:")),
+        "s3-outposts" => resource
+            .strip_prefix("outpost/")
+            .and_then(|resource| resource.split_once('/'))
+            .filter(|(outpost, _)| !outpost.is_empty())
+            .and_then(|(_, resource)| resource.strip_prefix("accesspoint/")),
+        "s3-object-lambda" => {
+            return Err(invalid(
+                "Object Lambda access points do not accept multipart uploads",
+            ))
+        }
+        _ => return Err(invalid("the service is neither s3 nor s3-outposts")),
+    };
+    let Some(name) = name.filter(|name| !name.is_empty() && !name.contains(['/', ':'])) else {
+        return Err(invalid(match service {
+            "s3" => "expected accesspoint/<name> as the resource",
+            _ => "expected outpost/<outpost-id>/accesspoint/<name> as the resource",
+        }));
+    };
+    // only multi-region access points are global
+    if region.is_empty() && !(service == "s3" && name.ends_with(".mrap")) {
+        return Err(invalid("the region is empty"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve, validate};
+    use aws_sdk_s3::config::{BehaviorVersion, Region};
+    use aws_sdk_s3::{Client, Config};
+
+    #[test]
+    fn test_validate() {
+        for arn in [
+            "arn:aws:s3:us-west-2:123456789012:accesspoint/my-access-point",
+            "arn:aws:s3:us-west-2:123456789012:accesspoint:my-access-point",
+            "arn:aws:s3::123456789012:accesspoint/mfzwi23gnjvgw.mrap",
+            "arn:aws:s3-outposts:us-west-2:123456789012:outpost/op-01ac5d28a6a232904/accesspoint/ap",
+        ] {
+            validate(arn).unwrap();
+        }
+        for arn in [
+            "arn:aws:s3:us-west-2:123456789012",
+            "arn:aws:s3:us-west-2:1234:accesspoint/my-access-point",
+            "arn:aws:s3:us-west-2:123456789012:bucket/my-bucket",
+            "arn:aws:s3::123456789012:accesspoint/my-access-point",
+            "arn:aws:s3-outposts:us-west-2:123456789012:outpost//accesspoint/ap",
+            "arn:aws:s3-object-lambda:us-west-2:123456789012:accesspoint/olap",
+            "arn:aws:sqs:us-west-2:123456789012:queue",
+        ] {
+            validate(arn).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let client = Client::from_conf(
+            Config::builder()
+                .behavior_version(BehaviorVersion::latest())
+                .region(Region::from_static("us-east-1"))
+                .build(),
+        );
+        let request = client
+            .create_multipart_upload()
+            .bucket("mfzwi23gnjvgw.mrap");
+        resolve(request.clone()).unwrap_err();
+        let request = resolve(request.expected_bucket_owner("123456789012")).unwrap();
+        assert_eq!(
+            request.get_bucket().as_deref(),
+            Some("arn:aws:s3::123456789012:accesspoint/mfzwi23gnjvgw.mrap"),
+        );
+
+        let request = client.create_multipart_upload().bucket("bucket");
+        let request = resolve(request).unwrap();
+        assert_eq!(request.get_bucket().as_deref(), Some("bucket"));
+    }
+}
@@ -0,0 +1,138 @@
+use aws_sdk_s3::config::interceptors::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_sdk_s3::operation::upload_part::UploadPartInput;
+use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyInput;
+use aws_sdk_s3::Client;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Counts the attempts of the `UploadPart` and `UploadPartCopy` requests of an upload, since
+/// the retries of the client happen within a single `send`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Attempts(Arc<Mutex<HashMap<Key, usize>>>);
+
+type Key = (Option<String>, i32);
+
+#[derive(Clone, Debug)]
+struct Current(Key);
+
+impl Storable for Current {
+    type Storer = StoreReplace<Self>;
+}
+
+/// The upload ID and part number of an `UploadPart` or `UploadPartCopy` request.
+pub(crate) fn part_of<'a>(
+    context: &'a BeforeSerializationInterceptorContextRef<'_>,
+) -> Option<(Option<&'a str>, i32)> {
+    let input = context.input();
+    if let Some(input) = input.downcast_ref::<UploadPartInput>() {
+        return Some((input.upload_id(), input.part_number()?));
+    }
+    let input = input.downcast_ref::<UploadPartCopyInput>()?;
+    Some((input.upload_id(), input.part_number()?))
+}
+
+impl Attempts {
+    /// Returns `client` counting its attempts here.
+    pub(crate) fn install(&self, client: &Client) -> Client {
+        Client::from_conf(
+            client
+                .config()
+                .to_builder()
+                .interceptor(self.clone())
+                .build(),
+        )
+    }
+
+    /// The attempts at part `part_number` of `upload_id` since its request was last sent, or 1
+    /// when the request did not go through the client, e.g. with a custom
+    /// [`MpuClient`](crate::MpuClient).
+    pub(crate) fn take(&self, upload_id: Option<&str>, part_number: usize) -> usize {
+        let key = (upload_id.map(str::to_owned), part_number as _);
+        match self.0.lock().unwrap().remove(&key) {
+            Some(attempts) if attempts > 0 => attempts,
+            _ => 1,
+        }
+    }
+}
+
+impl Intercept for Attempts {
+    fn name(&self) -> &'static str {
+        "Attempts"
+    }
+
+    fn read_before_execution(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some((upload_id, part_number)) = part_of(context) {
+            let key = (upload_id.map(str::to_owned), part_number);
+            self.0.lock().unwrap().insert(key.clone(), 0);
+            cfg.interceptor_state().store_put(Current(key));
+        }
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _: &BeforeTransmitInterceptorContextRef<'_>,
+        _: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(Current(key)) = cfg.load::<Current>() {
+            *self.0.lock().unwrap().entry(key.clone()).or_default() += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use crate::fake::Flaky;
+    use crate::{MultipartUpload, PartError};
+    use aws_sdk_s3::error::SdkError;
+    use aws_sdk_s3::operation::upload_part::UploadPartError;
+    use aws_sdk_s3::primitives::ByteStream;
+
+    #[tokio::test]
+    async fn test_attempts() {
+        let output = MultipartUpload::new(&Flaky::new(1).client(3))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        let attempts = output
+            .parts
+            .iter()
+            .map(|uploaded_part| uploaded_part.attempts)
+            .collect::<Vec<_>>();
+        assert_eq!(attempts, [2, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_attempts_failure() {
+        let err = MultipartUpload::new(&Flaky::new(2).client(2))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        let err = err
+            .error
+            .downcast_ref::<PartError<SdkError<UploadPartError>>>()
+            .unwrap();
+        assert_eq!((err.part_number, err.attempts), (1, 2));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::checksum::Hasher;
+use crate::split::PartHasher;
+use crate::{Checksum, ETagHasher, READER_CAPACITY};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::get_object_attributes::GetObjectAttributesError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::primitives::ByteStreamError;
+use aws_sdk_s3::types::{ObjectAttributes, ObjectPart};
+use aws_sdk_s3::Client;
+use md5::{Digest, Md5};
+use std::io::SeekFrom;
+use std::num::NonZeroU64;
+use std::ops::Range;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Compares the file at `path` with an object and returns the byte ranges that differ.
+///
+/// Objects uploaded with additional checksums are compared part by part using
+/// `GetObjectAttributes`. Others are compared by ETag as a whole, assuming parts of equal size,
+/// and report the whole file on a mismatch.
+pub async fn audit<E, P>(
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    path: P,
+) -> Result<Vec<Range<u64>>, E>
+where
+    E: From<SdkError<GetObjectAttributesError>>
+        + From<SdkError<HeadObjectError>>
+        + From<ByteStreamError>,
+    P: AsRef<Path>,
+{
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(ByteStreamError::from)?;
+    let len = file.metadata().await.map_err(ByteStreamError::from)?.len();
+
+    let mut parts = Vec::new();
+    let mut part_number_marker = None;
+    let (e_tag, object_size) = loop {
+        let output = client
+            .get_object_attributes()
+            .bucket(bucket)
+            .key(key)
+            .object_attributes(ObjectAttributes::Etag)
+            .object_attributes(ObjectAttributes::ObjectParts)
+            .object_attributes(ObjectAttributes::ObjectSize)
+            .set_part_number_marker(part_number_marker)
+            .send()
+            .await?;
+        let object_parts = output.object_parts;
+        let is_truncated = object_parts
+            .as_ref()
+            .and_then(|object_parts| object_parts.is_truncated);
+        part_number_marker = object_parts
+            .as_ref()
+            .and_then(|object_parts| object_parts.next_part_number_marker.clone());
+        parts.extend(
+            object_parts
+                .and_then(|object_parts| object_parts.parts)
+                .into_iter()
+                .flatten(),
+        );
+        if is_truncated != Some(true) {
+            break (output.e_tag, output.object_size.unwrap_or_default() as u64);
+        }
+    };
+
+    if parts.is_empty()
+        || parts
+            .iter()
+            .any(|part| Checksum::from_object_part(part).is_none())
+    {
+        let local = match parts_count(&e_tag) {
+            Some(_) => {
+                let part_size = client
+                    .head_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .part_number(1)
+                    .send()
+                    .await?
+                    .content_length
+                    .unwrap_or_default() as u64;
+                let mut hasher =
+                    ETagHasher::new(NonZeroU64::new(part_size).unwrap_or(NonZeroU64::MIN));
+                read(&mut file, 0..len, |data| hasher.update(data))
+                    .await
+                    .map_err(ByteStreamError::from)?;
+                hasher.finalize()
+            }
+            None => {
+                let mut hasher = Md5::new();
+                read(&mut file, 0..len, |data| Digest::update(&mut hasher, data))
+                    .await
+                    .map_err(ByteStreamError::from)?;
+                format!("{:x}", hasher.finalize())
+            }
+        };
+        // GetObjectAttributes returns the ETag without quotes
+        let mut ranges = Vec::new();
+        if e_tag.as_deref().map(|e_tag| e_tag.trim_matches('"')) != Some(local.trim_matches('"')) {
+            ranges.push(0..len.max(object_size));
+        }
+        return Ok(ranges);
+    }
+
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for part in &parts {
+        let size = part.size.unwrap_or_default() as u64;
+        if !matches_part(&mut file, offset..offset + size, part)
+            .await
+            .map_err(ByteStreamError::from)?
+        {
+            ranges.push(offset..offset + size);
+        }
+        offset += size;
+    }
+    if len > object_size {
+        ranges.push(object_size..len);
+    }
+    Ok(ranges)
+}
+
+// The number of parts in a multipart ETag.
+fn parts_count(e_tag: &Option<String>) -> Option<usize> {
+    e_tag
+        .as_deref()?
+        .trim_matches('"')
+        .split_once('-')?
+        .1
+        .parse()
+        .ok()
+}
+
+async fn matches_part(
+    file: &mut tokio::fs::File,
+    range: Range<u64>,
+    part: &ObjectPart,
+) -> std::io::Result<bool> {
+    let Some(expected) = Checksum::from_object_part(part) else {
+        return Ok(false);
+    };
+    let Some(mut hasher) = Hasher::new(&expected.algorithm()) else {
+        return Ok(false);
+    };
+    let len = range.end - range.start;
+    let read_len = read(file, range, |data| hasher.update(data)).await?;
+    Ok(read_len == len && hasher.finalize_reset() == expected)
+}
+
+// Feeds `range` of `file` to `f`, stopping early at the end of the file.
+async fn read<F>(file: &mut tokio::fs::File, range: Range<u64>, mut f: F) -> std::io::Result<u64>
+where
+    F: FnMut(&[u8]),
+{
+    file.seek(SeekFrom::Start(range.start)).await?;
+    let mut buf = vec![0; READER_CAPACITY];
+    let mut remaining = range.end - range.start;
+    while remaining > 0 {
+        let limit = buf.len().min(remaining as usize);
+        let n = file.read(&mut buf[..limit]).await?;
+        if n == 0 {
+            break;
+        }
+        f(&buf[..n]);
+        remaining -= n as u64;
+    }
+    Ok(range.end - range.start - remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parts_count;
+
+    #[test]
+    fn test_parts_count() {
+        assert_eq!(
+            parts_count(&Some("\"66cc959da476913bd064a0ee9ecd5dff-2\"".to_owned())),
+            Some(2)
+        );
+        assert_eq!(
+            parts_count(&Some("66cc959da476913bd064a0ee9ecd5dff-12".to_owned())),
+            Some(12)
+        );
+        assert_eq!(
+            parts_count(&Some("\"66cc959da476913bd064a0ee9ecd5dff\"".to_owned())),
+            None
+        );
+        assert_eq!(parts_count(&None), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{AzureError, PartSink};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http_body_util::Full;
+use hyper::header::{HeaderValue, ETAG};
+use hyper::{Method, Request, StatusCode};
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::client::legacy::Client;
+use md5::digest::Output;
+use md5::Md5;
+
+const VERSION: &str = "2021-08-06";
+
+/// A [`PartSink`] that uploads an Azure block blob with `Put Block` and `Put Block List`,
+/// authorized by the SAS token in the URL of the blob.
+///
+/// Each block is sent with `Content-MD5`, which Azure verifies. [`PartSink::commit`] returns
+/// the ETag of the blob.
+#[derive(Clone, Debug)]
+pub struct AzureBlockBlob<C> {
+    client: Client<C, Full<Bytes>>,
+    url: String,
+}
+
+impl<C> AzureBlockBlob<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// `url` is the URL of the blob with a SAS token that allows writing it.
+    pub fn new<S>(client: &Client<C, Full<Bytes>>, url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client: client.clone(),
+            url: url.into(),
+        }
+    }
+
+    fn url(&self, query: &str) -> String {
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        format!("{}{separator}{query}", self.url)
+    }
+
+    async fn put(
+        &self,
+        url: String,
+        body: Bytes,
+        content_md5: Option<String>,
+    ) -> Result<Option<String>, AzureError> {
+        let mut request = Request::builder()
+            .method(Method::PUT)
+            .uri(url)
+            .header("x-ms-version", VERSION);
+        if let Some(content_md5) = content_md5 {
+            request = request.header("content-md5", content_md5);
+        }
+        let request = request.body(Full::new(body)).map_err(AzureError::Request)?;
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(AzureError::Http)?;
+        match response.status() {
+            StatusCode::CREATED => Ok(response
+                .headers()
+                .get(ETAG)
+                .and_then(|value| HeaderValue::to_str(value).ok())
+                .map(ToOwned::to_owned)),
+            status => Err(AzureError::Status(status)),
+        }
+    }
+}
+
+impl<C> PartSink for AzureBlockBlob<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    type Receipt = String;
+    type Output = Option<String>;
+    type Error = AzureError;
+
+    fn put_part(
+        &self,
+        part_number: usize,
+        body: Vec<Bytes>,
+        content_md5: Output<Md5>,
+    ) -> BoxFuture<'_, Result<Self::Receipt, Self::Error>> {
+        Box::pin(async move {
+            let block_id = block_id(part_number);
+            let url = self.url(&format!("comp=block&blockid={}", encode(&block_id)));
+            self.put(url, body.concat().into(), Some(base64::encode(content_md5)))
+                .await?;
+            Ok(block_id)
+        })
+    }
+
+    fn commit(
+        &self,
+        receipts: Vec<Self::Receipt>,
+    ) -> BoxFuture<'_, Result<Self::Output, Self::Error>> {
+        Box::pin(async move {
+            self.put(
+                self.url("comp=blocklist"),
+                block_list(&receipts).into(),
+                None,
+            )
+            .await
+        })
+    }
+}
+
+// Block IDs of a blob must all have the same length.
+fn block_id(part_number: usize) -> String {
+    base64::encode(format!("{part_number:06}"))
+}
+
+fn block_list(block_ids: &[String]) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?><BlockList>"#);
+    for block_id in block_ids {
+        xml.push_str("<Latest>");
+        xml.push_str(block_id);
+        xml.push_str("</Latest>");
+    }
+    xml.push_str("</BlockList>");
+    xml
+}
+
+// percent-encodes the characters of base64 that are reserved in a query
+fn encode(block_id: &str) -> String {
+    block_id
+        .replace('+', "%2B")
+        .replace('/', "%2F")
+        .replace('=', "%3D")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_id, block_list, encode};
+
+    #[test]
+    fn test_block_id() {
+        assert_eq!(block_id(1), "MDAwMDAx");
+        assert_eq!(block_id(10000).len(), block_id(1).len());
+        assert_eq!(encode("a+b/c=="), "a%2Bb%2Fc%3D%3D");
+    }
+
+    #[test]
+    fn test_block_list() {
+        assert_eq!(
+            block_list(&[block_id(1), block_id(2)]),
+            r#"<?xml version="1.0" encoding="utf-8"?><BlockList><Latest>MDAwMDAx</Latest><Latest>MDAwMDAy</Latest></BlockList>"#,
+        );
+    }
+}
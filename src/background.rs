@@ -0,0 +1,102 @@
+use crate::into_byte_stream::from_stream;
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput};
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use futures::{future, stream, Future, StreamExt};
+use std::convert::Infallible;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use tokio::task::JoinHandle;
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type Upload = JoinHandle<Result<MultipartUploadOutput, MultipartUploadError<BoxError>>>;
+
+/// A multipart upload driven by a spawned task, for the adapters whose methods are called one
+/// chunk at a time.
+#[derive(Debug)]
+pub(crate) struct BackgroundUpload {
+    tx: Option<mpsc::UnboundedSender<(Vec<Bytes>, oneshot::Sender<()>)>>,
+    upload: Option<Upload>,
+    abort: Option<AbortMultipartUploadFluentBuilder>,
+}
+
+impl BackgroundUpload {
+    /// Creates the multipart upload and starts uploading the parts within `part_size`.
+    pub(crate) async fn start(
+        upload: MultipartUpload,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<Self, BoxError> {
+        upload.validate_part_size(&part_size)?;
+        let mut initiated = upload
+            .initiate::<BoxError>()
+            .await
+            .map_err(|err| err.error)?;
+        let abort = initiated.abort();
+
+        let (tx, rx) = mpsc::unbounded::<(Vec<Bytes>, oneshot::Sender<()>)>();
+        // chunks are acknowledged once the splitter has read past them
+        let body = rx.flat_map(|(chunks, ack)| {
+            stream::iter(chunks.into_iter().map(Ok::<_, Infallible>)).chain(
+                stream::once(async move {
+                    let _ = ack.send(());
+                })
+                .filter_map(|()| future::ready(None)),
+            )
+        });
+        let upload = tokio::spawn(async move {
+            initiated
+                .upload_parts(from_stream(body), part_size, concurrency_limit)
+                .await?;
+            initiated.complete().await
+        });
+        Ok(Self {
+            tx: Some(tx),
+            upload: Some(upload),
+            abort: Some(abort),
+        })
+    }
+
+    /// Appends `chunks` to the body. The future resolves once they have been taken into parts.
+    pub(crate) fn put(
+        &self,
+        chunks: Vec<Bytes>,
+    ) -> impl Future<Output = Result<(), BoxError>> + Send + 'static {
+        let (ack, acked) = oneshot::channel();
+        if let Some(tx) = &self.tx {
+            // the receiver lives as long as the upload, whose failure is reported by `complete`
+            let _ = tx.unbounded_send((chunks, ack));
+        }
+        async move {
+            acked
+                .await
+                .map_err(|_| "the multipart upload has finished".into())
+        }
+    }
+
+    /// Ends the body and waits for the upload.
+    pub(crate) async fn complete(&mut self) -> Result<MultipartUploadOutput, BoxError> {
+        self.tx = None;
+        let upload = self
+            .upload
+            .take()
+            .ok_or("the multipart upload has finished")?;
+        let output = upload.await?.map_err(|err| err.error)?;
+        self.abort = None;
+        Ok(output)
+    }
+
+    /// Stops the upload and aborts the multipart upload unless it has been completed.
+    pub(crate) async fn abort(&mut self) -> Result<(), BoxError> {
+        self.tx = None;
+        if let Some(upload) = self.upload.take() {
+            upload.abort();
+            let _ = upload.await;
+        }
+        if let Some(abort) = self.abort.take() {
+            abort.send().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput, UploadErrorKind};
+use aws_sdk_s3::primitives::ByteStream;
+use futures::{Stream, StreamExt};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The body of an entry of [`upload_batch`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Source {
+    Path(PathBuf),
+    Body(ByteStream),
+}
+
+impl From<PathBuf> for Source {
+    fn from(value: PathBuf) -> Self {
+        Self::Path(value)
+    }
+}
+
+impl From<ByteStream> for Source {
+    fn from(value: ByteStream) -> Self {
+        Self::Body(value)
+    }
+}
+
+/// Uploads each `(source, key)` pair and yields the key and result of every upload as it
+/// finishes.
+///
+/// `upload` returns the request for a key, e.g. with the bucket and key set. `concurrency_limit`
+/// bounds both the uploads and the parts in flight across all of them, unless the request
+/// already has a [`MultipartUpload::semaphore`]. A failed upload does not stop the others.
+pub fn upload_batch<E, I, S, F>(
+    sources: I,
+    mut upload: F,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> impl Stream<
+    Item = (
+        String,
+        Result<MultipartUploadOutput, MultipartUploadError<E>>,
+    ),
+>
+where
+    E: UploadErrorKind,
+    I: IntoIterator<Item = (S, String)>,
+    S: Into<Source>,
+    F: FnMut(&str) -> MultipartUpload,
+{
+    let semaphore = concurrency_limit
+        .map(|concurrency_limit| Arc::new(Semaphore::new(concurrency_limit.get())));
+    futures::stream::iter(sources)
+        .map(move |(source, key)| {
+            let mut upload = upload(&key);
+            if upload.semaphore.is_none() {
+                upload.semaphore = semaphore.clone();
+            }
+            upload = match source.into() {
+                Source::Path(path) => upload.body_path(path),
+                Source::Body(body) => upload.body(body),
+            };
+            let part_size = part_size.clone();
+            async move { (key, upload.send(part_size, None).await) }
+        })
+        .buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+}
@@ -0,0 +1,695 @@
+use anyhow::{bail, Context};
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::Client;
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::{StreamExt, TryStreamExt};
+use indicatif::ProgressBar;
+use md5::{Digest, Md5};
+use s3_mpu::{
+    abort_incomplete_uploads, abort_verified, predict_e_tag, JsonObject, MultipartCopy,
+    MultipartDownload, MultipartUpload, MultipartUploadError, Progress, UploadEvent, PART_SIZE,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Multipart uploads, downloads and copies of S3 objects.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+    /// The smallest part size, e.g. `8MiB`. Larger files use larger parts to stay within the
+    /// part limit.
+    #[arg(long, global = true, default_value = "8MiB", value_parser = parse_size)]
+    part_size: u64,
+    /// The number of parts in flight.
+    #[arg(long, global = true, default_value = "8")]
+    concurrency: NonZeroUsize,
+    /// Hides the progress bar.
+    #[arg(long, short, global = true)]
+    quiet: bool,
+    /// The format of the results on the standard output.
+    #[arg(long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// One line per result.
+    Text,
+    /// One JSON object per command, with the ETag, version ID, checksum, timings and parts of
+    /// uploads and copies.
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Uploads a file, or the standard input for `-`, to s3://bucket/key.
+    Upload {
+        path: PathBuf,
+        url: S3Url,
+        /// The expected length of the standard input, which sizes the parts so that it fits
+        /// within the part limit.
+        #[arg(long, value_parser = parse_size)]
+        expected_size: Option<u64>,
+    },
+    /// Uploads the standard input to s3://bucket/key, recording each part S3 acknowledges in a
+    /// checkpoint file. Run again with the same input after a failure, it skips the recorded
+    /// parts of the input instead of uploading them again.
+    Put {
+        url: S3Url,
+        /// The checkpoint file, removed once the upload is complete.
+        #[arg(long)]
+        checkpoint: PathBuf,
+    },
+    /// Downloads s3://bucket/key to a file.
+    Download { url: S3Url, path: PathBuf },
+    /// Copies an object on the server.
+    Copy { source: S3Url, destination: S3Url },
+    /// Aborts the incomplete uploads under s3://bucket/prefix.
+    AbortStale {
+        url: S3Url,
+        /// The age of the uploads to abort, e.g. `7d`, `12h` or `30m`.
+        #[arg(long, default_value = "1d", value_parser = parse_duration)]
+        older_than: Duration,
+    },
+    /// Checks that s3://bucket/key has the content of a file, by its ETag.
+    Verify { path: PathBuf, url: S3Url },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct S3Url {
+    bucket: String,
+    key: String,
+}
+
+impl FromStr for S3Url {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (bucket, key) = s
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("{s} does not start with s3://"))?
+            .split_once('/')
+            .unwrap_or((&s[5..], ""));
+        if bucket.is_empty() {
+            return Err(format!("{s} has no bucket"));
+        }
+        Ok(Self {
+            bucket: bucket.to_owned(),
+            key: key.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for S3Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+impl S3Url {
+    fn object(&self) -> anyhow::Result<(&str, &str)> {
+        if self.key.is_empty() {
+            bail!("{self} has no key");
+        }
+        Ok((&self.bucket, &self.key))
+    }
+}
+
+/// The upload of `put`, kept in its checkpoint file as a line `<upload ID> <URL>` followed by
+/// a line `<number> <offset> <len> <Content-MD5>` per part acknowledged by S3.
+#[derive(Debug, PartialEq)]
+struct Checkpoint {
+    upload_id: String,
+    url: String,
+    parts: BTreeMap<i32, CheckpointPart>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct CheckpointPart {
+    offset: u64,
+    len: u64,
+    content_md5: String,
+}
+
+impl FromStr for Checkpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // a line cut short by a crash is left out
+        let s = &s[..s.rfind('\n').map_or(0, |i| i + 1)];
+        let mut lines = s.lines();
+        let (upload_id, url) = lines
+            .next()
+            .and_then(|line| line.split_once(' '))
+            .ok_or("the checkpoint has no upload")?;
+        let mut parts = BTreeMap::new();
+        for line in lines {
+            let (number, part) =
+                CheckpointPart::parse(line).ok_or_else(|| format!("{line} is not a part"))?;
+            // a part uploaded again replaces the earlier one
+            parts.insert(number, part);
+        }
+        Ok(Self {
+            upload_id: upload_id.to_owned(),
+            url: url.to_owned(),
+            parts,
+        })
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", self.upload_id, self.url)?;
+        for (number, part) in &self.parts {
+            write!(f, "{}", part.line(*number))?;
+        }
+        Ok(())
+    }
+}
+
+impl CheckpointPart {
+    fn parse(line: &str) -> Option<(i32, Self)> {
+        let fields = line.split(' ').collect::<Vec<_>>();
+        let [number, offset, len, content_md5] = fields[..] else {
+            return None;
+        };
+        let part = Self {
+            offset: offset.parse().ok()?,
+            len: len.parse().ok()?,
+            content_md5: content_md5.to_owned(),
+        };
+        Some((number.parse().ok()?, part))
+    }
+
+    fn line(&self, number: i32) -> String {
+        format!(
+            "{number} {} {} {}\n",
+            self.offset, self.len, self.content_md5
+        )
+    }
+}
+
+impl Checkpoint {
+    /// The number of parts from 1 that S3 has in `listed` as `(number, len)` and that the
+    /// checkpoint records back to back with the same lengths. Parts shorter than `min_len`
+    /// may have ended an earlier input, so they and the parts after them are uploaded again.
+    fn resumable<I>(&self, listed: I, min_len: u64) -> usize
+    where
+        I: IntoIterator<Item = (i32, u64)>,
+    {
+        let mut offset = 0;
+        listed
+            .into_iter()
+            .zip(1..)
+            .take_while(|((number, len), expected)| {
+                let resumable = *number == *expected
+                    && *len >= min_len
+                    && self
+                        .parts
+                        .get(number)
+                        .is_some_and(|part| part.offset == offset && part.len == *len);
+                offset += len;
+                resumable
+            })
+            .count()
+    }
+}
+
+// reads a checkpointed part from `stdin` and checks that it has not changed
+async fn skip_part(
+    stdin: &mut tokio::io::Stdin,
+    number: i32,
+    part: &CheckpointPart,
+) -> anyhow::Result<()> {
+    let mut reader = stdin.take(part.len);
+    let mut md5 = Md5::new();
+    let mut buf = vec![0; 64 << 10];
+    let mut len = 0;
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        md5.update(&buf[..n]);
+        len += n as u64;
+    }
+    if len != part.len || base64::encode(md5.finalize()) != part.content_md5 {
+        bail!("part {number} of the standard input differs from the checkpoint");
+    }
+    Ok(())
+}
+
+fn parse_size(s: &str) -> Result<u64, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("{s} is not a size"))?;
+    let shift = match unit {
+        "" | "B" => 0,
+        "K" | "KiB" => 10,
+        "M" | "MiB" => 20,
+        "G" | "GiB" => 30,
+        _ => return Err(format!("{s} has an unknown unit; use KiB, MiB or GiB")),
+    };
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("{s} is too large"))
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, secs) = [("s", 1), ("m", 60), ("h", 60 * 60), ("d", 24 * 60 * 60)]
+        .into_iter()
+        .find_map(|(unit, secs)| Some((s.strip_suffix(unit)?, secs)))
+        .ok_or_else(|| format!("{s} has an unknown unit; use s, m, h or d"))?;
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("{s} is not a duration"))?;
+    value
+        .checked_mul(secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("{s} is too long"))
+}
+
+fn progress_bar(quiet: bool, len: Option<u64>) -> ProgressBar {
+    match (quiet, len) {
+        (true, _) => ProgressBar::hidden(),
+        (false, Some(len)) => ProgressBar::new(len),
+        (false, None) => ProgressBar::no_length(),
+    }
+}
+
+// aborts a failed upload so that its parts are not left behind
+async fn finish<T>(
+    client: &Client,
+    result: Result<T, MultipartUploadError<anyhow::Error>>,
+) -> anyhow::Result<T> {
+    match result {
+        Ok(output) => Ok(output),
+        Err(err) => {
+            if let Some(abort) = err.abort {
+                if let Err(abort_err) = abort_verified(client, abort).await {
+                    eprintln!("failed to abort the upload: {abort_err}");
+                }
+            }
+            Err(err.error)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let client = Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
+    let part_size = args.part_size..=*PART_SIZE.end();
+    let concurrency_limit = Some(args.concurrency);
+
+    match args.command {
+        Command::Upload {
+            path,
+            url,
+            expected_size,
+        } => {
+            let (bucket, key) = url.object()?;
+            let stdin = path.as_os_str() == "-";
+            let len = if stdin {
+                expected_size
+            } else {
+                let metadata = tokio::fs::metadata(&path)
+                    .await
+                    .with_context(|| path.display().to_string())?;
+                Some(metadata.len())
+            };
+            let progress = Arc::new(Progress::new(client.clone(), progress_bar(args.quiet, len)));
+            let upload = MultipartUpload::new(&client)
+                .mpu_client(progress)
+                .bucket(bucket)
+                .key(key);
+            let upload = match (stdin, expected_size) {
+                (true, Some(expected_size)) => upload.body_stdin().size_hint(expected_size),
+                (true, None) => upload.body_stdin(),
+                (false, _) => upload.body_path(&path),
+            };
+            let output = upload.send(part_size, concurrency_limit).await;
+            let output = finish(&client, output).await?;
+            if args.output == Output::Json {
+                println!("{}", output.to_json(bucket, key));
+            } else {
+                println!(
+                    "uploaded {url} ({} bytes, ETag {})",
+                    output.content_length,
+                    output.output.e_tag.as_deref().unwrap_or_default(),
+                );
+            }
+        }
+        Command::Put { url, checkpoint } => {
+            let (bucket, key) = url.object()?;
+            let progress = Arc::new(Progress::new(
+                client.clone(),
+                progress_bar(args.quiet, None),
+            ));
+            let upload = || {
+                MultipartUpload::new(&client)
+                    .mpu_client(progress.clone())
+                    .bucket(bucket)
+                    .key(key)
+                    .content_md5(true)
+            };
+            let mut stdin = tokio::io::stdin();
+            let (state, resume_parts) = match tokio::fs::read_to_string(&checkpoint).await {
+                Ok(state) => {
+                    let mut state = state
+                        .parse::<Checkpoint>()
+                        .map_err(anyhow::Error::msg)
+                        .with_context(|| checkpoint.display().to_string())?;
+                    if state.url != url.to_string() {
+                        bail!(
+                            "{} is the checkpoint of {}",
+                            checkpoint.display(),
+                            state.url
+                        );
+                    }
+                    let mut initiated = upload()
+                        .upload_id(&state.upload_id)
+                        .initiate::<anyhow::Error>()
+                        .await
+                        .map_err(|err| err.error)?;
+                    initiated
+                        .list_parts::<anyhow::Error>()
+                        .await
+                        .map_err(|err| err.error)?;
+                    let resumable = state.resumable(
+                        initiated.parts().iter().map(|uploaded_part| {
+                            (uploaded_part.info.number, uploaded_part.info.len)
+                        }),
+                        *PART_SIZE.start(),
+                    );
+                    state
+                        .parts
+                        .retain(|number, _| *number as usize <= resumable);
+                    for (number, part) in &state.parts {
+                        skip_part(&mut stdin, *number, part).await?;
+                    }
+                    (state, initiated.parts()[..resumable].to_vec())
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    let initiated = upload()
+                        .initiate::<anyhow::Error>()
+                        .await
+                        .map_err(|err| err.error)?;
+                    let state = Checkpoint {
+                        upload_id: initiated.upload_id().context("no upload ID")?.to_owned(),
+                        url: url.to_string(),
+                        parts: BTreeMap::new(),
+                    };
+                    (state, Vec::new())
+                }
+                Err(err) => return Err(err).with_context(|| checkpoint.display().to_string()),
+            };
+            // the checkpoint is replaced at once so that a crash cannot lose the upload ID
+            let tmp = checkpoint.with_extension("tmp");
+            tokio::fs::write(&tmp, state.to_string()).await?;
+            tokio::fs::rename(&tmp, &checkpoint).await?;
+            let mut file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&checkpoint)
+                .await?;
+
+            let mut events = std::pin::pin!(upload()
+                .upload_id(&state.upload_id)
+                .resume_parts(resume_parts)
+                .body_reader(stdin)
+                .send_streaming::<anyhow::Error>(part_size, concurrency_limit));
+            let output = loop {
+                match events
+                    .next()
+                    .await
+                    .context("the upload ended without output")?
+                {
+                    Ok(UploadEvent::PartCompleted(uploaded_part)) => {
+                        let info = &uploaded_part.info;
+                        let part = CheckpointPart {
+                            offset: info.range.start,
+                            len: info.len,
+                            content_md5: info.content_md5.clone().unwrap_or_default(),
+                        };
+                        file.write_all(part.line(info.number).as_bytes()).await?;
+                        file.sync_data().await?;
+                    }
+                    Ok(UploadEvent::Completed(output)) => break output,
+                    Ok(_) => {}
+                    // the upload is kept to be resumed
+                    Err(err) => {
+                        return Err(err
+                            .error
+                            .context(format!("resume with --checkpoint {}", checkpoint.display())))
+                    }
+                }
+            };
+            drop(file);
+            tokio::fs::remove_file(&checkpoint).await?;
+            if args.output == Output::Json {
+                println!("{}", output.to_json(bucket, key));
+            } else {
+                println!(
+                    "uploaded {url} ({} bytes, ETag {})",
+                    output.content_length,
+                    output.output.e_tag.as_deref().unwrap_or_default(),
+                );
+            }
+        }
+        Command::Download { url, path } => {
+            let (bucket, key) = url.object()?;
+            let mut output = MultipartDownload::new(&client)
+                .bucket(bucket)
+                .key(key)
+                .verify(true)
+                .send::<anyhow::Error>(
+                    NonZeroU64::new(args.part_size).context("invalid part size")?,
+                    concurrency_limit,
+                )
+                .await?;
+            let bar = progress_bar(args.quiet, output.head.content_length.map(|len| len as _));
+            let mut file = tokio::fs::File::create(&path)
+                .await
+                .with_context(|| path.display().to_string())?;
+            while let Some(data) = output.body.try_next().await? {
+                file.write_all(&data).await?;
+                bar.inc(data.len() as _);
+            }
+            file.flush().await?;
+            bar.finish();
+            if args.output == Output::Json {
+                let json = JsonObject::new()
+                    .field("bucket", bucket)
+                    .field("key", key)
+                    .field("path", &*path.to_string_lossy())
+                    .field(
+                        "content_length",
+                        output.head.content_length.unwrap_or_default(),
+                    )
+                    .field("e_tag", &output.head.e_tag)
+                    .field("version_id", &output.head.version_id);
+                println!("{json}");
+            } else {
+                println!("downloaded {url} to {}", path.display());
+            }
+        }
+        Command::Copy {
+            source,
+            destination,
+        } => {
+            let (source_bucket, source_key) = source.object()?;
+            let (bucket, key) = destination.object()?;
+            let output = MultipartCopy::new(MultipartUpload::new(&client).bucket(bucket).key(key))
+                .source_bucket(source_bucket)
+                .source_key(source_key)
+                .send(part_size, concurrency_limit)
+                .await;
+            let output = finish(&client, output).await?;
+            if args.output == Output::Json {
+                println!("{}", output.to_json(bucket, key));
+            } else {
+                println!(
+                    "copied {source} to {destination} ({} bytes)",
+                    output.content_length,
+                );
+            }
+        }
+        Command::AbortStale { url, older_than } => {
+            let prefix = (!url.key.is_empty()).then_some(url.key.as_str());
+            let aborted = abort_incomplete_uploads(
+                &client,
+                &url.bucket,
+                older_than,
+                prefix,
+                concurrency_limit,
+            )
+            .await?;
+            if args.output == Output::Json {
+                let aborted = aborted
+                    .iter()
+                    .map(|(key, upload_id)| {
+                        JsonObject::new()
+                            .field("bucket", &url.bucket)
+                            .field("key", key)
+                            .field("upload_id", upload_id)
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", JsonObject::new().field("aborted", aborted));
+            } else {
+                for (key, upload_id) in &aborted {
+                    println!("aborted {upload_id} of s3://{}/{key}", url.bucket);
+                }
+            }
+        }
+        Command::Verify { path, url } => {
+            let (bucket, key) = url.object()?;
+            let head = client
+                .head_object()
+                .bucket(bucket)
+                .key(key)
+                .part_number(1)
+                .send()
+                .await?;
+            let Some(e_tag) = head.e_tag else {
+                bail!("{url} has no ETag");
+            };
+            let file = std::fs::File::open(&path).with_context(|| path.display().to_string())?;
+            // the first part gives the part size the object was uploaded with
+            let expected = if head.parts_count.is_some() {
+                let part_size = head.content_length.unwrap_or_default() as u64;
+                let part_size = NonZeroU64::new(part_size).context("the first part is empty")?;
+                tokio::task::spawn_blocking(move || predict_e_tag(file, part_size)).await??
+            } else {
+                tokio::task::spawn_blocking(move || {
+                    let mut file = file;
+                    let mut md5 = Md5::new();
+                    std::io::copy(&mut file, &mut md5)?;
+                    anyhow::Ok(format!("\"{:x}\"", md5.finalize()))
+                })
+                .await??
+            };
+            if expected != e_tag {
+                bail!(
+                    "{} does not match {url}: ETag {expected} != {e_tag} (objects encrypted with \
+                     SSE-KMS or SSE-C cannot be verified)",
+                    path.display(),
+                );
+            }
+            if args.output == Output::Json {
+                let json = JsonObject::new()
+                    .field("bucket", bucket)
+                    .field("key", key)
+                    .field("path", &*path.to_string_lossy())
+                    .field("e_tag", &e_tag);
+                println!("{json}");
+            } else {
+                println!("{} matches {url}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_duration, parse_size, Checkpoint, CheckpointPart, S3Url};
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_s3_url() {
+        assert_eq!(
+            "s3://bucket/a/b".parse::<S3Url>(),
+            Ok(S3Url {
+                bucket: "bucket".to_owned(),
+                key: "a/b".to_owned(),
+            }),
+        );
+        assert_eq!("s3://bucket".parse::<S3Url>().unwrap().key, "");
+        assert!("bucket/key".parse::<S3Url>().is_err());
+        assert!("s3:///key".parse::<S3Url>().is_err());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let part = |offset, len| CheckpointPart {
+            offset,
+            len,
+            content_md5: "md5".to_owned(),
+        };
+        let checkpoint = Checkpoint {
+            upload_id: "id".to_owned(),
+            url: "s3://bucket/a b".to_owned(),
+            parts: BTreeMap::from([(1, part(0, 10)), (2, part(10, 10)), (4, part(30, 10))]),
+        };
+        let s = checkpoint.to_string();
+        assert_eq!(s.parse(), Ok(checkpoint));
+        // the last line was cut short and a part was uploaded again
+        let checkpoint = format!("{s}2 10 5 md5\n3 1").parse::<Checkpoint>().unwrap();
+        assert_eq!(checkpoint.parts[&2], part(10, 5));
+        assert_eq!(checkpoint.parts.len(), 3);
+        assert!("id s3://bucket/key\n1 0\n".parse::<Checkpoint>().is_err());
+        assert!("".parse::<Checkpoint>().is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_resumable() {
+        let part = |offset, len| CheckpointPart {
+            offset,
+            len,
+            content_md5: "md5".to_owned(),
+        };
+        let checkpoint = Checkpoint {
+            upload_id: "id".to_owned(),
+            url: "s3://bucket/key".to_owned(),
+            parts: BTreeMap::from([
+                (1, part(0, 10)),
+                (2, part(10, 10)),
+                (3, part(20, 10)),
+                (5, part(40, 10)),
+            ]),
+        };
+        assert_eq!(checkpoint.resumable([(1, 10), (2, 10), (3, 10)], 10), 3);
+        // part 4 is missing
+        assert_eq!(
+            checkpoint.resumable([(1, 10), (2, 10), (3, 10), (5, 10)], 10),
+            3
+        );
+        // S3 has a part 2 that was not acknowledged
+        assert_eq!(checkpoint.resumable([(1, 10), (2, 9), (3, 10)], 5), 1);
+        // a short part may have ended the input
+        assert_eq!(checkpoint.resumable([(1, 10), (2, 10), (3, 10)], 11), 0);
+        assert_eq!(checkpoint.resumable([(2, 10)], 10), 0);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("8MiB"), Ok(8 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert!(parse_size("8MB").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            parse_duration("7d"),
+            Ok(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("7é").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration(&format!("{}d", u64::MAX)).is_err());
+    }
+}
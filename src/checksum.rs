@@ -0,0 +1,321 @@
+use crate::split::PartHasher;
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::types::builders::CompletedPartBuilder;
+use aws_sdk_s3::types::{ChecksumAlgorithm, CopyPartResult};
+use crc_fast::CrcAlgorithm;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// A base64-encoded additional checksum, as sent in the `x-amz-checksum-*` headers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Checksum {
+    Crc32(String),
+    Crc32c(String),
+    Crc64Nvme(String),
+    Sha1(String),
+    Sha256(String),
+}
+
+impl Checksum {
+    // https://docs.aws.amazon.com/AmazonS3/latest/userguide/checking-object-integrity.html#full-object-checksums
+    /// Combines the CRCs of consecutive parts, given with [`Self::crc`], into the CRC of the
+    /// whole object. `None` for an algorithm other than CRC32, CRC32C and CRC64NVME.
+    pub(crate) fn combine<I>(algorithm: &ChecksumAlgorithm, parts: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let (crc_algorithm, wrap): (_, fn(String) -> Self) = match algorithm {
+            ChecksumAlgorithm::Crc32 => (CrcAlgorithm::Crc32IsoHdlc, Self::Crc32),
+            ChecksumAlgorithm::Crc32C => (CrcAlgorithm::Crc32Iscsi, Self::Crc32c),
+            ChecksumAlgorithm::Crc64Nvme => (CrcAlgorithm::Crc64Nvme, Self::Crc64Nvme),
+            _ => return None,
+        };
+        let crc = parts.into_iter().fold(0, |crc, (part_crc, len)| {
+            crc_fast::checksum_combine(crc_algorithm, crc, part_crc, len)
+        });
+        Some(wrap(base64::encode(match algorithm {
+            ChecksumAlgorithm::Crc64Nvme => crc.to_be_bytes().to_vec(),
+            _ => (crc as u32).to_be_bytes().to_vec(),
+        })))
+    }
+
+    /// The CRC for [`Self::combine`], or `None` if the checksum is not a valid CRC of
+    /// `algorithm`.
+    pub(crate) fn crc(&self, algorithm: &ChecksumAlgorithm) -> Option<u64> {
+        let width = match algorithm {
+            ChecksumAlgorithm::Crc32 | ChecksumAlgorithm::Crc32C => 4,
+            ChecksumAlgorithm::Crc64Nvme => 8,
+            _ => return None,
+        };
+        if self.algorithm() != *algorithm {
+            return None;
+        }
+        let value = base64::decode(self.value()).ok()?;
+        if value.len() != width {
+            return None;
+        }
+        let mut bytes = [0; 8];
+        bytes[8 - width..].copy_from_slice(&value);
+        Some(u64::from_be_bytes(bytes))
+    }
+
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        match self {
+            Self::Crc32(_) => ChecksumAlgorithm::Crc32,
+            Self::Crc32c(_) => ChecksumAlgorithm::Crc32C,
+            Self::Crc64Nvme(_) => ChecksumAlgorithm::Crc64Nvme,
+            Self::Sha1(_) => ChecksumAlgorithm::Sha1,
+            Self::Sha256(_) => ChecksumAlgorithm::Sha256,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Crc32(value)
+            | Self::Crc32c(value)
+            | Self::Crc64Nvme(value)
+            | Self::Sha1(value)
+            | Self::Sha256(value) => value,
+        }
+    }
+
+    pub(crate) fn from_copy_part_result(
+        algorithm: &ChecksumAlgorithm,
+        result: &CopyPartResult,
+    ) -> Option<Self> {
+        match algorithm {
+            ChecksumAlgorithm::Crc32 => result.checksum_crc32.clone().map(Self::Crc32),
+            ChecksumAlgorithm::Crc32C => result.checksum_crc32_c.clone().map(Self::Crc32c),
+            ChecksumAlgorithm::Crc64Nvme => result.checksum_crc64_nvme.clone().map(Self::Crc64Nvme),
+            ChecksumAlgorithm::Sha1 => result.checksum_sha1.clone().map(Self::Sha1),
+            ChecksumAlgorithm::Sha256 => result.checksum_sha256.clone().map(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    #[cfg(any(feature = "download", feature = "tokio"))]
+    pub(crate) fn from_head_object(
+        output: &aws_sdk_s3::operation::head_object::HeadObjectOutput,
+    ) -> Option<Self> {
+        output
+            .checksum_crc32
+            .clone()
+            .map(Self::Crc32)
+            .or_else(|| output.checksum_crc32_c.clone().map(Self::Crc32c))
+            .or_else(|| output.checksum_crc64_nvme.clone().map(Self::Crc64Nvme))
+            .or_else(|| output.checksum_sha1.clone().map(Self::Sha1))
+            .or_else(|| output.checksum_sha256.clone().map(Self::Sha256))
+    }
+
+    pub(crate) fn from_complete_multipart_upload(
+        output: &aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadOutput,
+    ) -> Option<Self> {
+        output
+            .checksum_crc32
+            .clone()
+            .map(Self::Crc32)
+            .or_else(|| output.checksum_crc32_c.clone().map(Self::Crc32c))
+            .or_else(|| output.checksum_crc64_nvme.clone().map(Self::Crc64Nvme))
+            .or_else(|| output.checksum_sha1.clone().map(Self::Sha1))
+            .or_else(|| output.checksum_sha256.clone().map(Self::Sha256))
+    }
+
+    pub(crate) fn from_part(part: &aws_sdk_s3::types::Part) -> Option<Self> {
+        part.checksum_crc32
+            .clone()
+            .map(Self::Crc32)
+            .or_else(|| part.checksum_crc32_c.clone().map(Self::Crc32c))
+            .or_else(|| part.checksum_crc64_nvme.clone().map(Self::Crc64Nvme))
+            .or_else(|| part.checksum_sha1.clone().map(Self::Sha1))
+            .or_else(|| part.checksum_sha256.clone().map(Self::Sha256))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn from_object_part(part: &aws_sdk_s3::types::ObjectPart) -> Option<Self> {
+        part.checksum_crc32
+            .clone()
+            .map(Self::Crc32)
+            .or_else(|| part.checksum_crc32_c.clone().map(Self::Crc32c))
+            .or_else(|| part.checksum_crc64_nvme.clone().map(Self::Crc64Nvme))
+            .or_else(|| part.checksum_sha1.clone().map(Self::Sha1))
+            .or_else(|| part.checksum_sha256.clone().map(Self::Sha256))
+    }
+
+    pub(crate) fn set_upload_part(
+        &self,
+        builder: UploadPartFluentBuilder,
+    ) -> UploadPartFluentBuilder {
+        let builder = builder.checksum_algorithm(self.algorithm());
+        match self {
+            Self::Crc32(value) => builder.checksum_crc32(value),
+            Self::Crc32c(value) => builder.checksum_crc32_c(value),
+            Self::Crc64Nvme(value) => builder.checksum_crc64_nvme(value),
+            Self::Sha1(value) => builder.checksum_sha1(value),
+            Self::Sha256(value) => builder.checksum_sha256(value),
+        }
+    }
+
+    pub(crate) fn set_completed_part(&self, builder: CompletedPartBuilder) -> CompletedPartBuilder {
+        match self {
+            Self::Crc32(value) => builder.checksum_crc32(value),
+            Self::Crc32c(value) => builder.checksum_crc32_c(value),
+            Self::Crc64Nvme(value) => builder.checksum_crc64_nvme(value),
+            Self::Sha1(value) => builder.checksum_sha1(value),
+            Self::Sha256(value) => builder.checksum_sha256(value),
+        }
+    }
+
+    pub(crate) fn set_complete(
+        &self,
+        builder: CompleteMultipartUploadFluentBuilder,
+    ) -> CompleteMultipartUploadFluentBuilder {
+        match self {
+            Self::Crc32(value) => builder.checksum_crc32(value),
+            Self::Crc32c(value) => builder.checksum_crc32_c(value),
+            Self::Crc64Nvme(value) => builder.checksum_crc64_nvme(value),
+            Self::Sha1(value) => builder.checksum_sha1(value),
+            Self::Sha256(value) => builder.checksum_sha256(value),
+        }
+    }
+}
+
+pub(crate) enum Hasher {
+    Crc32(crc_fast::Digest),
+    Crc32c(crc_fast::Digest),
+    Crc64Nvme(crc_fast::Digest),
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: &ChecksumAlgorithm) -> Option<Self> {
+        match algorithm {
+            ChecksumAlgorithm::Crc32 => Some(Self::Crc32(crc_fast::Digest::new(
+                CrcAlgorithm::Crc32IsoHdlc,
+            ))),
+            ChecksumAlgorithm::Crc32C => Some(Self::Crc32c(crc_fast::Digest::new(
+                CrcAlgorithm::Crc32Iscsi,
+            ))),
+            ChecksumAlgorithm::Crc64Nvme => Some(Self::Crc64Nvme(crc_fast::Digest::new(
+                CrcAlgorithm::Crc64Nvme,
+            ))),
+            ChecksumAlgorithm::Sha1 => Some(Self::Sha1(Sha1::new())),
+            ChecksumAlgorithm::Sha256 => Some(Self::Sha256(Sha256::new())),
+            _ => None,
+        }
+    }
+}
+
+impl PartHasher for Hasher {
+    type Output = Checksum;
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(digest) | Self::Crc32c(digest) | Self::Crc64Nvme(digest) => {
+                digest.update(data)
+            }
+            Self::Sha1(digest) => PartHasher::update(digest, data),
+            Self::Sha256(digest) => PartHasher::update(digest, data),
+        }
+    }
+
+    fn finalize_reset(&mut self) -> Self::Output {
+        match self {
+            Self::Crc32(digest) => Checksum::Crc32(base64::encode(
+                (digest.finalize_reset() as u32).to_be_bytes(),
+            )),
+            Self::Crc32c(digest) => Checksum::Crc32c(base64::encode(
+                (digest.finalize_reset() as u32).to_be_bytes(),
+            )),
+            Self::Crc64Nvme(digest) => {
+                Checksum::Crc64Nvme(base64::encode(digest.finalize_reset().to_be_bytes()))
+            }
+            Self::Sha1(digest) => {
+                Checksum::Sha1(base64::encode(PartHasher::finalize_reset(digest)))
+            }
+            Self::Sha256(digest) => {
+                Checksum::Sha256(base64::encode(PartHasher::finalize_reset(digest)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Checksum, Hasher};
+    use crate::split::PartHasher;
+    use aws_sdk_s3::types::ChecksumAlgorithm;
+
+    #[test]
+    fn test_hasher() {
+        for (algorithm, checksum) in [
+            (
+                ChecksumAlgorithm::Crc32,
+                Checksum::Crc32("y/Q5Jg==".to_owned()),
+            ),
+            (
+                ChecksumAlgorithm::Crc32C,
+                Checksum::Crc32c("4waSgw==".to_owned()),
+            ),
+            (
+                ChecksumAlgorithm::Sha1,
+                Checksum::Sha1("98O8HYCOBHMq32eZZczDTKeuNEE=".to_owned()),
+            ),
+            (
+                ChecksumAlgorithm::Sha256,
+                Checksum::Sha256("FeKw08M4keuw8e9gnsQZQgwg4yDOlMZfvIwzEkSOsiU=".to_owned()),
+            ),
+        ] {
+            let mut hasher = Hasher::new(&algorithm).unwrap();
+            hasher.update(b"1234");
+            hasher.update(b"56789");
+            assert_eq!(hasher.finalize_reset(), checksum);
+            hasher.update(b"123456789");
+            assert_eq!(hasher.finalize_reset(), checksum);
+            assert_eq!(checksum.algorithm(), algorithm);
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        for algorithm in [
+            ChecksumAlgorithm::Crc32,
+            ChecksumAlgorithm::Crc32C,
+            ChecksumAlgorithm::Crc64Nvme,
+        ] {
+            let mut hasher = Hasher::new(&algorithm).unwrap();
+            hasher.update(b"1234");
+            let a = hasher.finalize_reset();
+            hasher.update(b"56789");
+            let b = hasher.finalize_reset();
+            hasher.update(b"123456789");
+            let crcs = [
+                (a.crc(&algorithm).unwrap(), 4),
+                (b.crc(&algorithm).unwrap(), 5),
+            ];
+            assert_eq!(
+                Checksum::combine(&algorithm, crcs),
+                Some(hasher.finalize_reset())
+            );
+        }
+        assert_eq!(Checksum::combine(&ChecksumAlgorithm::Sha256, []), None);
+    }
+
+    #[test]
+    fn test_crc() {
+        let crc32 = ChecksumAlgorithm::Crc32;
+        assert_eq!(
+            Checksum::Crc32(base64::encode([0, 0, 1, 2])).crc(&crc32),
+            Some(0x102)
+        );
+        // another algorithm, invalid base64 and a value longer than 8 bytes
+        assert_eq!(Checksum::Crc32c(base64::encode([0; 4])).crc(&crc32), None);
+        assert_eq!(Checksum::Crc32("!".to_owned()).crc(&crc32), None);
+        assert_eq!(
+            Checksum::Crc64Nvme(base64::encode([0; 9])).crc(&ChecksumAlgorithm::Crc64Nvme),
+            None
+        );
+    }
+}
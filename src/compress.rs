@@ -0,0 +1,99 @@
+use crate::into_byte_stream::from_stream;
+use crate::READER_CAPACITY;
+use async_compression::tokio::bufread::{GzipEncoder, ZstdEncoder};
+use async_compression::Level;
+use aws_sdk_s3::primitives::ByteStream;
+use std::io;
+use std::pin::Pin;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// A compression format for [`MultipartUpload::compress`](crate::MultipartUpload::compress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    pub(crate) fn content_encoding(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn encode(self, level: i32, mut body: ByteStream) -> ByteStream {
+        let body = StreamReader::new(futures::stream::poll_fn(move |cx| {
+            Pin::new(&mut body)
+                .poll_next(cx)
+                .map(|chunk| chunk.map(|chunk| chunk.map_err(io::Error::from)))
+        }));
+        let level = Level::Precise(level);
+        match self {
+            Self::Gzip => from_stream(ReaderStream::with_capacity(
+                GzipEncoder::with_quality(body, level),
+                READER_CAPACITY,
+            )),
+            Self::Zstd => from_stream(ReaderStream::with_capacity(
+                ZstdEncoder::with_quality(body, level),
+                READER_CAPACITY,
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::Codec;
+    use crate::{FakeS3, MultipartUpload};
+    use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+    use aws_sdk_s3::primitives::ByteStream;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncRead, AsyncReadExt};
+
+    async fn read_to_end<R>(mut reader: R) -> Vec<u8>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_compress() {
+        let body = (0..1000).map(|i| (i % 7) as u8).collect::<Vec<u8>>();
+        for codec in [Codec::Gzip, Codec::Zstd] {
+            let fake = FakeS3::new();
+            let content_encoding = Arc::new(Mutex::new(None));
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .body(ByteStream::from(body.clone()))
+                .compress(codec, 3)
+                .customize_create({
+                    let content_encoding = content_encoding.clone();
+                    move |create| {
+                        *content_encoding.lock().unwrap() = create.get_content_encoding().clone();
+                        create
+                    }
+                })
+                .part_size_limits(10..=10)
+                .send::<anyhow::Error>(10..=10, None)
+                .await
+                .unwrap();
+            assert_eq!(
+                content_encoding.lock().unwrap().as_deref(),
+                Some(codec.content_encoding()),
+            );
+            let object = fake.object("bucket", "key").unwrap();
+            assert!(object.len() < body.len());
+            let decoded = match codec {
+                Codec::Gzip => read_to_end(GzipDecoder::new(&object[..])).await,
+                Codec::Zstd => read_to_end(ZstdDecoder::new(&object[..])).await,
+            };
+            assert_eq!(decoded, body);
+        }
+    }
+}
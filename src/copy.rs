@@ -0,0 +1,132 @@
+use crate::arn;
+use crate::copy_source::copy_source;
+use crate::{
+    CircuitOpen, IntegrityError, InvalidPartSize, MultipartUpload, MultipartUploadError,
+    MultipartUploadOutput, PartError, PreconditionFailed, RequestIds,
+};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError;
+use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyError;
+use aws_smithy_types::error::operation::BuildError;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+
+/// Copies an object with ranged `UploadPartCopy` requests, e.g. one larger than the 5 GiB limit
+/// of `CopyObject`.
+///
+/// The destination and its settings are taken from a [`MultipartUpload`], whose body is
+/// ignored.
+pub struct MultipartCopy {
+    upload: MultipartUpload,
+    source_bucket: Option<String>,
+    source_key: Option<String>,
+    source_version_id: Option<String>,
+}
+
+impl MultipartCopy {
+    pub fn new(upload: MultipartUpload) -> Self {
+        Self {
+            upload,
+            source_bucket: None,
+            source_key: None,
+            source_version_id: None,
+        }
+    }
+
+    pub fn source_bucket<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source_bucket = Some(inp.into());
+        self
+    }
+
+    pub fn source_key<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source_key = Some(inp.into());
+        self
+    }
+
+    pub fn source_version_id<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source_version_id = Some(inp.into());
+        self
+    }
+
+    pub async fn send<E>(
+        self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
+    where
+        E: From<SdkError<HeadObjectError>>
+            + From<SdkError<CreateMultipartUploadError>>
+            + From<PartError<SdkError<UploadPartCopyError>>>
+            + From<SdkError<CompleteMultipartUploadError>>
+            + From<SdkError<PutObjectError>>
+            + From<PreconditionFailed>
+            + From<IntegrityError>
+            + From<BuildError>
+            + From<InvalidPartSize>
+            + From<CircuitOpen>,
+    {
+        let Self {
+            mut upload,
+            source_bucket,
+            source_key,
+            source_version_id,
+        } = self;
+        let (Some(source_bucket), Some(source_key)) = (source_bucket, source_key) else {
+            return Err(MultipartUploadError::new(BuildError::missing_field(
+                "source_bucket",
+                "source_bucket and source_key are required",
+            )));
+        };
+        // parts are copied on the server, so there is no Content-MD5 to compare against
+        if upload.verify_e_tag {
+            return Err(MultipartUploadError::new(BuildError::invalid_field(
+                "verify_e_tag",
+                "is not supported for copies",
+            )));
+        }
+        if arn::is_arn(&source_bucket) {
+            arn::validate(&source_bucket).map_err(MultipartUploadError::new)?;
+        }
+        upload.content_md5 = false;
+        upload
+            .validate_part_size(&part_size)
+            .map_err(MultipartUploadError::new)?;
+
+        let head = upload
+            .client
+            .head_object()
+            .bucket(&source_bucket)
+            .key(&source_key)
+            .set_version_id(source_version_id.clone())
+            .send()
+            .await
+            .map_err(|err| {
+                let request_ids = RequestIds::new(&err);
+                MultipartUploadError::new(err).request_ids(request_ids)
+            })?;
+        let copy_source = copy_source(&source_bucket, &source_key, source_version_id.as_deref());
+
+        let mut initiated = upload.initiate().await?;
+        initiated
+            .copy_parts(
+                &copy_source,
+                head.content_length.unwrap_or_default() as u64,
+                part_size,
+                concurrency_limit,
+            )
+            .await?;
+        initiated.complete().await
+    }
+}
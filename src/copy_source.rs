@@ -0,0 +1,56 @@
+use crate::arn;
+use std::fmt::Write;
+
+/// The `x-amz-copy-source` of an object, whose bucket may be an access point ARN.
+pub(crate) fn copy_source(bucket: &str, key: &str, version_id: Option<&str>) -> String {
+    let mut copy_source = if arn::is_arn(bucket) {
+        format!("{}/object/{}", encode(bucket), encode(key))
+    } else {
+        format!("{bucket}/{}", encode(key))
+    };
+    if let Some(version_id) = version_id {
+        write!(copy_source, "?versionId={}", encode(version_id)).unwrap();
+    }
+    copy_source
+}
+
+// URL-encodes `value` as the `x-amz-copy-source` header expects, keeping `/` as is.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'/') {
+            encoded.push(b as char);
+        } else {
+            write!(encoded, "%{b:02X}").unwrap();
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_source, encode};
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("dir/a b+c.txt"), "dir/a%20b%2Bc.txt");
+        assert_eq!(encode("日"), "%E6%97%A5");
+    }
+
+    #[test]
+    fn test_copy_source() {
+        assert_eq!(copy_source("bucket", "a b", None), "bucket/a%20b");
+        assert_eq!(
+            copy_source("bucket", "key", Some("v+1")),
+            "bucket/key?versionId=v%2B1",
+        );
+        assert_eq!(
+            copy_source(
+                "arn:aws:s3:us-west-2:123456789012:accesspoint/ap",
+                "key",
+                None,
+            ),
+            "arn%3Aaws%3As3%3Aus-west-2%3A123456789012%3Aaccesspoint/ap/object/key",
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use crate::plan::part_size_for;
+use crate::{
+    ETagHasher, MultipartUpload, MultipartUploadError, MultipartUploadOutput, RequestIds,
+    UploadErrorKind, MAX_PARTS, READER_CAPACITY,
+};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::delete_objects::DeleteObjectsError;
+use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error;
+use aws_sdk_s3::operation::put_object::PutObjectOutput;
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use aws_sdk_s3::Client;
+use futures::{StreamExt, TryStreamExt};
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+use std::io;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ObjectOutput {
+    Put(PutObjectOutput),
+    MultipartUpload(MultipartUploadOutput),
+}
+
+/// Uploads every file under `path` to `bucket`, keyed by `prefix` followed by the file's
+/// relative path with `/` separators.
+///
+/// Files smaller than `part_size.start()` are sent with `PutObject`, the others as multipart
+/// uploads. `concurrency_limit` bounds the requests in flight across all files. Returns the key
+/// and output of every uploaded file.
+///
+/// A failed file does not stop the others: every file is uploaded, then the multipart uploads
+/// that failed are aborted and the first error is returned with the others as
+/// `additional_errors`.
+pub async fn upload_dir<E, P>(
+    client: &Client,
+    path: P,
+    bucket: &str,
+    prefix: &str,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<Vec<(String, ObjectOutput)>, MultipartUploadError<E>>
+where
+    E: UploadErrorKind,
+    P: AsRef<Path>,
+{
+    let files = walk(path.as_ref())
+        .await
+        .map_err(|err| MultipartUploadError::new(ByteStreamError::from(err)))?;
+    upload_files(client, files, bucket, prefix, part_size, concurrency_limit).await
+}
+
+async fn upload_files<E>(
+    client: &Client,
+    files: Vec<(PathBuf, String, u64)>,
+    bucket: &str,
+    prefix: &str,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<Vec<(String, ObjectOutput)>, MultipartUploadError<E>>
+where
+    E: UploadErrorKind,
+{
+    let semaphore = concurrency_limit
+        .map(|concurrency_limit| Arc::new(Semaphore::new(concurrency_limit.get())));
+    let results = futures::stream::iter(files)
+        .map(|(path, key, len)| {
+            let key = format!("{prefix}{key}");
+            let semaphore = semaphore.clone();
+            let part_size = part_size.clone();
+            async move {
+                let output = if len < *part_size.start() {
+                    let _permit = match &semaphore {
+                        Some(semaphore) => semaphore.acquire().await.ok(),
+                        None => None,
+                    };
+                    let body = ByteStream::from_path(&path)
+                        .await
+                        .map_err(MultipartUploadError::new)?;
+                    let put_object = client.put_object().bucket(bucket).key(&key).body(body);
+                    #[cfg(feature = "mime_guess")]
+                    let put_object = put_object.set_content_type(crate::guess_content_type(&key));
+                    let output = put_object.send().await.map_err(|err| {
+                        let request_ids = RequestIds::new(&err);
+                        MultipartUploadError::new(err).request_ids(request_ids)
+                    })?;
+                    ObjectOutput::Put(output)
+                } else {
+                    let mut upload = MultipartUpload::new(client)
+                        .bucket(bucket)
+                        .key(&key)
+                        .body_path(path);
+                    if let Some(semaphore) = semaphore {
+                        upload = upload.semaphore(semaphore);
+                    }
+                    ObjectOutput::MultipartUpload(upload.send(part_size, None).await?)
+                };
+                Ok((key, output))
+            }
+        })
+        .buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut outputs = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(output) => outputs.push(output),
+            Err(err) => errors.push(err),
+        }
+    }
+    match MultipartUploadError::abort_all(errors).await {
+        Some(err) => Err(err),
+        None => Ok(outputs),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct SyncOutput {
+    pub uploaded: Vec<(String, ObjectOutput)>,
+    /// Keys whose objects already matched the local files.
+    pub skipped: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Uploads the files under `path` like [`upload_dir`], skipping those whose object under
+/// `prefix` has the same size and ETag. With `delete`, objects under `prefix` without a local
+/// file are deleted.
+///
+/// Expected ETags assume the object was uploaded with the same `part_size`, so objects written
+/// by other tools or encrypted with SSE-KMS are uploaded again.
+///
+/// Failed uploads are handled as in [`upload_dir`], and nothing is deleted once an upload
+/// failed.
+pub async fn sync<E, P>(
+    client: &Client,
+    path: P,
+    bucket: &str,
+    prefix: &str,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+    delete: bool,
+) -> Result<SyncOutput, MultipartUploadError<E>>
+where
+    E: UploadErrorKind + From<SdkError<ListObjectsV2Error>> + From<SdkError<DeleteObjectsError>>,
+    P: AsRef<Path>,
+{
+    let files = walk(path.as_ref())
+        .await
+        .map_err(|err| MultipartUploadError::new(ByteStreamError::from(err)))?;
+    let mut remote = list(client, bucket, prefix).await?;
+
+    let checks = files
+        .into_iter()
+        .map(|file| {
+            let remote = remote.remove(&file.1);
+            let part_size = &part_size;
+            async move {
+                let changed = match remote {
+                    Some((size, e_tag)) if size == file.2 as i64 => {
+                        e_tag.as_deref() != Some(&local_e_tag(&file.0, file.2, part_size).await?)
+                    }
+                    _ => true,
+                };
+                Ok::<_, io::Error>((file, changed))
+            }
+        })
+        .collect::<Vec<_>>();
+    let checked = futures::stream::iter(checks)
+        .buffered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|err| MultipartUploadError::new(ByteStreamError::from(err)))?;
+
+    let mut output = SyncOutput::default();
+    let mut files = Vec::new();
+    for (file, changed) in checked {
+        if changed {
+            files.push(file);
+        } else {
+            output.skipped.push(format!("{prefix}{}", file.1));
+        }
+    }
+    output.uploaded =
+        upload_files(client, files, bucket, prefix, part_size, concurrency_limit).await?;
+
+    if delete {
+        let mut extras = remote
+            .into_keys()
+            .map(|key| format!("{prefix}{key}"))
+            .collect::<Vec<_>>();
+        extras.sort();
+        // DeleteObjects accepts at most 1000 keys.
+        for keys in extras.chunks(1000) {
+            let objects = keys
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<_, _>>()
+                .map_err(MultipartUploadError::new)?;
+            let delete = Delete::builder()
+                .set_objects(Some(objects))
+                .quiet(false)
+                .build()
+                .map_err(MultipartUploadError::new)?;
+            let deleted = client
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(|err| {
+                    let request_ids = RequestIds::new(&err);
+                    MultipartUploadError::new(err).request_ids(request_ids)
+                })?;
+            output.deleted.extend(
+                deleted
+                    .deleted
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|deleted| deleted.key),
+            );
+        }
+    }
+    Ok(output)
+}
+
+/// Lists the objects under `prefix` as relative key to size and ETag.
+async fn list<E>(
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+) -> Result<HashMap<String, (i64, Option<String>)>, MultipartUploadError<E>>
+where
+    E: From<SdkError<ListObjectsV2Error>>,
+{
+    let mut objects = HashMap::new();
+    let mut continuation_token = None;
+    loop {
+        let output = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|err| {
+                let request_ids = RequestIds::new(&err);
+                MultipartUploadError::new(err).request_ids(request_ids)
+            })?;
+        for object in output.contents.into_iter().flatten() {
+            if let Some(key) = object
+                .key
+                .as_deref()
+                .and_then(|key| key.strip_prefix(prefix))
+            {
+                objects.insert(
+                    key.to_owned(),
+                    (object.size.unwrap_or_default(), object.e_tag),
+                );
+            }
+        }
+        match output.next_continuation_token {
+            Some(token) if output.is_truncated == Some(true) => continuation_token = Some(token),
+            _ => break,
+        }
+    }
+    Ok(objects)
+}
+
+/// The ETag of the file at `path` as uploaded by [`upload_dir`].
+async fn local_e_tag(path: &Path, len: u64, part_size: &RangeInclusive<u64>) -> io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut buf = vec![0; READER_CAPACITY];
+    if len < *part_size.start() {
+        let mut hasher = Md5::new();
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(format!("\"{:x}\"", hasher.finalize()));
+            }
+            hasher.update(&buf[..n]);
+        }
+    }
+
+    let size = part_size_for(len, part_size, MAX_PARTS);
+    let mut hasher = ETagHasher::new(NonZeroU64::new(size).unwrap());
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+/// Lists the regular files under `root` with their relative keys and lengths. Symbolic links to
+/// files are followed, those to directories are not.
+async fn walk(root: &Path) -> io::Result<Vec<(PathBuf, String, u64)>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, key)) = dirs.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().into_string().map_err(|name| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("non UTF-8 file name {name:?}"),
+                )
+            })?;
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                dirs.push((path, format!("{key}{name}/")));
+            } else {
+                let metadata = tokio::fs::metadata(&path).await?;
+                if metadata.is_file() {
+                    files.push((path, format!("{key}{name}"), metadata.len()));
+                }
+            }
+        }
+    }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{local_e_tag, walk};
+    use crate::e_tag;
+    use md5::{Digest, Md5};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_walk() {
+        let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        tokio::fs::create_dir_all(root.join("a/b")).await.unwrap();
+        tokio::fs::create_dir_all(root.join("c")).await.unwrap();
+        tokio::fs::write(root.join("foo"), b"foo").await.unwrap();
+        tokio::fs::write(root.join("a/b/bar"), b"bar!")
+            .await
+            .unwrap();
+
+        let files = walk(&root).await.unwrap();
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|(path, key, len)| (path.strip_prefix(&root).unwrap(), &key[..], *len))
+                .collect::<Vec<_>>(),
+            [
+                (std::path::Path::new("a/b/bar"), "a/b/bar", 4),
+                (std::path::Path::new("foo"), "foo", 3),
+            ]
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_upload_dir_failed_part() {
+        let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        tokio::fs::create_dir_all(&root).await.unwrap();
+        let body = vec![0; *crate::PART_SIZE.start() as usize + 1];
+        tokio::fs::write(root.join("foo"), &body).await.unwrap();
+        tokio::fs::write(root.join("bar"), &body).await.unwrap();
+
+        // the first part fails without a retry
+        let flaky = crate::fake::Flaky::new(1);
+        let result = super::upload_dir::<anyhow::Error, _>(
+            &flaky.client(1),
+            &root,
+            "bucket",
+            "",
+            crate::PART_SIZE,
+            None,
+        )
+        .await;
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+        let err = result.unwrap_err();
+        assert!(err.abort.is_none());
+        assert!(flaky.fake.uploads().is_empty());
+        let uploaded = ["foo", "bar"]
+            .into_iter()
+            .filter(|key| flaky.fake.object("bucket", key).is_some())
+            .count();
+        assert_eq!(uploaded, 1);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_sync_failed_part() {
+        let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        tokio::fs::create_dir_all(&root).await.unwrap();
+        let body = vec![0; *crate::PART_SIZE.start() as usize + 1];
+        tokio::fs::write(root.join("foo"), &body).await.unwrap();
+        tokio::fs::write(root.join("bar"), &body).await.unwrap();
+
+        let flaky = crate::fake::Flaky::new(1);
+        let client = flaky.client(1);
+        client
+            .put_object()
+            .bucket("bucket")
+            .key("prefix/stale")
+            .send()
+            .await
+            .unwrap();
+        let result = super::sync::<anyhow::Error, _>(
+            &client,
+            &root,
+            "bucket",
+            "prefix/",
+            crate::PART_SIZE,
+            None,
+            true,
+        )
+        .await;
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+        assert!(result.unwrap_err().abort.is_none());
+        assert!(flaky.fake.uploads().is_empty());
+        assert!(flaky.fake.object("bucket", "prefix/stale").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_local_e_tag() {
+        let path = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let data = (0..10).collect::<Vec<u8>>();
+        tokio::fs::write(&path, &data).await.unwrap();
+
+        let single = local_e_tag(&path, 10, &(16..=16)).await.unwrap();
+        let multi = local_e_tag(&path, 10, &(4..=4)).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(single, format!("\"{:x}\"", Md5::digest(&data)));
+        assert_eq!(multi, e_tag::composite(data.chunks(4).map(Md5::digest)));
+    }
+}
@@ -0,0 +1,630 @@
+use crate::checksum::Hasher;
+use crate::split::PartHasher;
+use crate::{e_tag, Checksum, IntegrityError};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::get_object::builders::GetObjectFluentBuilder;
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::head_object::builders::HeadObjectFluentBuilder;
+use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
+use aws_sdk_s3::primitives::ByteStreamError;
+use aws_sdk_s3::types::{ChecksumMode, ChecksumType, ServerSideEncryption};
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use md5::digest::Output;
+use md5::{Digest, Md5};
+use std::fmt;
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::ops::Range;
+use std::pin::Pin;
+
+/// Downloads an object with concurrent ranged `GetObject` requests.
+pub struct MultipartDownload {
+    client: Client,
+    bucket: Option<String>,
+    key: Option<String>,
+    version_id: Option<String>,
+    verify: bool,
+    positional: bool,
+}
+
+#[non_exhaustive]
+pub struct MultipartDownloadOutput<E> {
+    pub head: HeadObjectOutput,
+    /// The object in order. Dropping it cancels the requests in flight.
+    pub body: Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>,
+}
+
+impl<E> MultipartDownloadOutput<E> {
+    /// Reads the body as an `AsyncRead`, e.g. to pipe the object into a decoder.
+    #[cfg(feature = "tokio")]
+    pub fn into_async_read(self) -> impl tokio::io::AsyncRead + Send
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        tokio_util::io::StreamReader::new(self.body.map_err(std::io::Error::other))
+    }
+}
+
+impl<E> fmt::Debug for MultipartDownloadOutput<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultipartDownloadOutput")
+            .field("head", &self.head)
+            .finish_non_exhaustive()
+    }
+}
+
+// A range fetched by one request. With `verify`, ranges follow the parts of the object.
+#[derive(Clone, Debug)]
+struct Segment {
+    range: Range<u64>,
+    part_number: Option<i32>,
+    checksum: Option<Checksum>,
+}
+
+impl MultipartDownload {
+    pub fn new(client: &Client) -> Self {
+        Self {
+            client: client.clone(),
+            bucket: None,
+            key: None,
+            version_id: None,
+            verify: false,
+            positional: false,
+        }
+    }
+
+    pub fn bucket<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.bucket = Some(inp.into());
+        self
+    }
+
+    pub fn key<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.key = Some(inp.into());
+        self
+    }
+
+    pub fn version_id<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.version_id = Some(inp.into());
+        self
+    }
+
+    /// Fetches the object part by part as it was uploaded, checking each part against its
+    /// additional checksum and the whole object against its ETag, which fails with an
+    /// [`IntegrityError`]. `part_size` is ignored.
+    ///
+    /// The ETag is not checked for objects encrypted with SSE-KMS or SSE-C, and part checksums
+    /// are not checked for multipart objects with a full-object checksum.
+    pub fn verify(mut self, inp: bool) -> Self {
+        self.verify = inp;
+        self
+    }
+
+    /// Makes [`Self::send_to_path`] write each range at its offset as it arrives, so that no
+    /// range waits in memory for the ones before it. The file is created anew instead of
+    /// resuming a previous download.
+    pub fn positional(mut self, inp: bool) -> Self {
+        self.positional = inp;
+        self
+    }
+
+    /// Sends `HeadObject` and returns the body, fetched in ranges of `part_size` bytes with at
+    /// most `concurrency_limit` requests in flight.
+    ///
+    /// The body yields the ranges in order while the following ones are fetched ahead of the
+    /// consumer, holding at most `concurrency_limit` ranges in memory.
+    ///
+    /// Ranges are requested with the ETag from `HeadObject`, so an object overwritten during the
+    /// download fails instead of mixing versions.
+    pub async fn send<E>(
+        self,
+        part_size: NonZeroU64,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<MultipartDownloadOutput<E>, E>
+    where
+        E: From<SdkError<HeadObjectError>>
+            + From<SdkError<GetObjectError>>
+            + From<ByteStreamError>
+            + From<IntegrityError>
+            + Send
+            + 'static,
+    {
+        let (head, segments) = self.plan::<E>(part_size, concurrency_limit).await?;
+        let body = self.fetch::<E>(&head, segments, Vec::new(), concurrency_limit);
+        Ok(MultipartDownloadOutput { head, body })
+    }
+
+    /// Downloads the object into the file at `path`, resuming after the complete ranges already
+    /// in the file. With [`verify`](Self::verify), the kept ranges are checked as well.
+    ///
+    /// The file is assumed to hold a previous download of the same object, e.g. one pinned with
+    /// [`version_id`](Self::version_id). With [`positional`](Self::positional), the ranges are
+    /// written at their offsets instead and nothing is resumed.
+    #[cfg(feature = "tokio")]
+    pub async fn send_to_path<E, P>(
+        self,
+        path: P,
+        part_size: NonZeroU64,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<HeadObjectOutput, E>
+    where
+        E: From<SdkError<HeadObjectError>>
+            + From<SdkError<GetObjectError>>
+            + From<ByteStreamError>
+            + From<IntegrityError>
+            + Send
+            + 'static,
+        P: AsRef<std::path::Path>,
+    {
+        use std::io::SeekFrom;
+        use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+        if self.positional {
+            return self
+                .send_to_path_positional(path.as_ref(), part_size, concurrency_limit)
+                .await;
+        }
+        let io_err = |err| E::from(ByteStreamError::from(err));
+        let (head, segments) = self.plan::<E>(part_size, concurrency_limit).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await
+            .map_err(io_err)?;
+        let written = file.metadata().await.map_err(io_err)?.len();
+        let skipped = segments
+            .iter()
+            .take_while(|segment| segment.range.end <= written)
+            .count();
+        let offset = segments[..skipped]
+            .last()
+            .map_or(0, |segment| segment.range.end);
+        file.set_len(offset).await.map_err(io_err)?;
+
+        let mut md5s = Vec::new();
+        if self.verify {
+            file.seek(SeekFrom::Start(0)).await.map_err(io_err)?;
+            for segment in &segments[..skipped] {
+                let mut data = vec![0; (segment.range.end - segment.range.start) as usize];
+                file.read_exact(&mut data).await.map_err(io_err)?;
+                md5s.push(check(segment, &data)?);
+            }
+        }
+        file.seek(SeekFrom::Start(offset)).await.map_err(io_err)?;
+
+        let mut body =
+            self.fetch::<E>(&head, segments[skipped..].to_vec(), md5s, concurrency_limit);
+        while let Some(data) = body.try_next().await? {
+            file.write_all(&data).await.map_err(io_err)?;
+        }
+        file.flush().await.map_err(io_err)?;
+        Ok(head)
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn send_to_path_positional<E>(
+        self,
+        path: &std::path::Path,
+        part_size: NonZeroU64,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<HeadObjectOutput, E>
+    where
+        E: From<SdkError<HeadObjectError>>
+            + From<SdkError<GetObjectError>>
+            + From<ByteStreamError>
+            + From<IntegrityError>,
+    {
+        use std::io::SeekFrom;
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        let (head, segments) = self.plan::<E>(part_size, concurrency_limit).await?;
+        let file = tokio::fs::File::create(path)
+            .await
+            .map_err(ByteStreamError::from)?;
+        file.set_len(head.content_length.unwrap_or_default() as _)
+            .await
+            .map_err(ByteStreamError::from)?;
+
+        let get_object = self.get_object().set_if_match(head.e_tag.clone());
+        let mut md5s = futures::stream::iter(segments.into_iter().enumerate())
+            .map(|(i, segment)| {
+                let get_object = request(&get_object, &segment);
+                async move {
+                    let output = get_object.send().await?;
+                    // a handle of its own keeps the offset independent of other segments
+                    let mut file = tokio::fs::OpenOptions::new()
+                        .write(true)
+                        .open(path)
+                        .await
+                        .map_err(ByteStreamError::from)?;
+                    file.seek(SeekFrom::Start(segment.range.start))
+                        .await
+                        .map_err(ByteStreamError::from)?;
+                    let md5 = write_segment::<E, _>(output.body, &mut file, &segment, self.verify)
+                        .await?;
+                    file.flush().await.map_err(ByteStreamError::from)?;
+                    Ok::<_, E>((i, md5))
+                }
+            })
+            .buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+            .try_collect::<Vec<_>>()
+            .await?;
+        md5s.sort_by_key(|(i, _)| *i);
+        let md5s = md5s
+            .into_iter()
+            .filter_map(|(_, md5)| md5)
+            .collect::<Vec<_>>();
+        check_e_tag(self.e_tag(&head), &md5s)?;
+        Ok(head)
+    }
+
+    /// Downloads the object into `writer` in order. Responses are awaited concurrently but
+    /// their bodies are read one at a time, so no range is held in memory.
+    #[cfg(feature = "tokio")]
+    pub async fn to_writer<E, W>(
+        self,
+        mut writer: W,
+        part_size: NonZeroU64,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<HeadObjectOutput, E>
+    where
+        E: From<SdkError<HeadObjectError>>
+            + From<SdkError<GetObjectError>>
+            + From<ByteStreamError>
+            + From<IntegrityError>,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let (head, segments) = self.plan::<E>(part_size, concurrency_limit).await?;
+        let get_object = self.get_object().set_if_match(head.e_tag.clone());
+        let mut responses = futures::stream::iter(segments)
+            .map(|segment| {
+                let get_object = request(&get_object, &segment);
+                async move { Ok::<_, E>((get_object.send().await?, segment)) }
+            })
+            .buffered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get));
+        let mut md5s = Vec::new();
+        while let Some((output, segment)) = responses.try_next().await? {
+            md5s.extend(
+                write_segment::<E, _>(output.body, &mut writer, &segment, self.verify).await?,
+            );
+        }
+        writer.flush().await.map_err(ByteStreamError::from)?;
+        check_e_tag(self.e_tag(&head), &md5s)?;
+        Ok(head)
+    }
+
+    fn head_object(&self) -> HeadObjectFluentBuilder {
+        self.client
+            .head_object()
+            .set_bucket(self.bucket.clone())
+            .set_key(self.key.clone())
+            .set_version_id(self.version_id.clone())
+    }
+
+    fn get_object(&self) -> GetObjectFluentBuilder {
+        self.client
+            .get_object()
+            .set_bucket(self.bucket.clone())
+            .set_key(self.key.clone())
+            .set_version_id(self.version_id.clone())
+    }
+
+    async fn plan<E>(
+        &self,
+        part_size: NonZeroU64,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<(HeadObjectOutput, Vec<Segment>), E>
+    where
+        E: From<SdkError<HeadObjectError>>,
+    {
+        let head = self.head_object().send().await?;
+        let len = head.content_length.unwrap_or_default() as u64;
+        if !self.verify {
+            let segments = (0..len.div_ceil(part_size.get()))
+                .map(|i| i * part_size.get())
+                .map(|offset| Segment {
+                    range: offset..(offset + part_size.get()).min(len),
+                    part_number: None,
+                    checksum: None,
+                })
+                .collect();
+            return Ok((head, segments));
+        }
+
+        let head_part = |part_number| {
+            self.head_object()
+                .set_if_match(head.e_tag.clone())
+                .part_number(part_number)
+                .checksum_mode(ChecksumMode::Enabled)
+                .send()
+        };
+        let first = head_part(1).await?;
+        let parts_count = first.parts_count.unwrap_or(1);
+        let rest = futures::stream::iter(2..=parts_count)
+            .map(head_part)
+            .buffered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+            .try_collect::<Vec<_>>()
+            .await?;
+        // a part of a full-object checksum only carries the checksum of the whole object
+        let part_checksums =
+            parts_count == 1 || first.checksum_type != Some(ChecksumType::FullObject);
+        let mut offset = 0;
+        let segments = std::iter::once(first)
+            .chain(rest)
+            .zip(1..)
+            .map(|(output, part_number)| {
+                let len = output.content_length.unwrap_or_default() as u64;
+                let segment = Segment {
+                    range: offset..offset + len,
+                    part_number: Some(part_number),
+                    checksum: part_checksums
+                        .then(|| Checksum::from_head_object(&output))
+                        .flatten(),
+                };
+                offset += len;
+                segment
+            })
+            .collect();
+        Ok((head, segments))
+    }
+
+    fn fetch<E>(
+        &self,
+        head: &HeadObjectOutput,
+        segments: Vec<Segment>,
+        md5s: Vec<Output<Md5>>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send>>
+    where
+        E: From<SdkError<GetObjectError>>
+            + From<ByteStreamError>
+            + From<IntegrityError>
+            + Send
+            + 'static,
+    {
+        let verify = self.verify;
+        let get_object = self.get_object().set_if_match(head.e_tag.clone());
+        let parts = futures::stream::iter(segments)
+            .map(move |segment| {
+                let get_object = request(&get_object, &segment);
+                async move {
+                    let output = get_object.send().await?;
+                    let data = output.body.collect().await?.into_bytes();
+                    let md5 = if verify {
+                        Some(check(&segment, &data)?)
+                    } else {
+                        None
+                    };
+                    Ok::<_, E>((data, md5))
+                }
+            })
+            .buffered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get));
+
+        let e_tag = self.e_tag(head);
+        Box::pin(futures::stream::unfold(
+            Some((Box::pin(parts), md5s)),
+            move |state| {
+                let e_tag = e_tag.clone();
+                async move {
+                    let (mut parts, mut md5s) = state?;
+                    match parts.next().await {
+                        Some(Ok((data, md5))) => {
+                            md5s.extend(md5);
+                            Some((Ok(data), Some((parts, md5s))))
+                        }
+                        Some(Err(err)) => Some((Err(err), None)),
+                        None => check_e_tag(e_tag, &md5s)
+                            .err()
+                            .map(|err| (Err(err.into()), None)),
+                    }
+                }
+            },
+        ))
+    }
+
+    // The ETag to check the download against, if any.
+    fn e_tag(&self, head: &HeadObjectOutput) -> Option<String> {
+        let encrypted = matches!(
+            head.server_side_encryption,
+            Some(ServerSideEncryption::AwsKms | ServerSideEncryption::AwsKmsDsse)
+        ) || head.sse_customer_algorithm.is_some();
+        head.e_tag.clone().filter(|_| self.verify && !encrypted)
+    }
+}
+
+fn request(get_object: &GetObjectFluentBuilder, segment: &Segment) -> GetObjectFluentBuilder {
+    match segment.part_number {
+        Some(part_number) => get_object
+            .clone()
+            .part_number(part_number)
+            .checksum_mode(ChecksumMode::Enabled),
+        None => get_object.clone().range(format!(
+            "bytes={}-{}",
+            segment.range.start,
+            segment.range.end - 1
+        )),
+    }
+}
+
+// Streams the body of a segment into `writer`, checking it with `verify`.
+#[cfg(feature = "tokio")]
+async fn write_segment<E, W>(
+    mut body: aws_sdk_s3::primitives::ByteStream,
+    writer: &mut W,
+    segment: &Segment,
+    verify: bool,
+) -> Result<Option<Output<Md5>>, E>
+where
+    E: From<ByteStreamError> + From<IntegrityError>,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+
+    let mut check = verify.then(|| Check::new(segment));
+    while let Some(chunk) = body.try_next().await? {
+        if let Some(check) = &mut check {
+            check.update(&chunk);
+        }
+        writer
+            .write_all(&chunk)
+            .await
+            .map_err(ByteStreamError::from)?;
+    }
+    Ok(check.map(|check| check.finish(segment)).transpose()?)
+}
+
+// Hashes a segment for its part checksum and the ETag.
+struct Check {
+    md5: Md5,
+    hasher: Option<Hasher>,
+}
+
+impl Check {
+    fn new(segment: &Segment) -> Self {
+        Self {
+            md5: Md5::new(),
+            hasher: segment
+                .checksum
+                .as_ref()
+                .and_then(|checksum| Hasher::new(&checksum.algorithm())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.md5, data);
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(data);
+        }
+    }
+
+    // Checks the part checksum and returns the MD5 for the ETag.
+    fn finish(self, segment: &Segment) -> Result<Output<Md5>, IntegrityError> {
+        if let (Some(expected), Some(mut hasher)) = (&segment.checksum, self.hasher) {
+            let actual = hasher.finalize_reset();
+            if actual != *expected {
+                return Err(IntegrityError::ChecksumMismatch {
+                    part_number: segment.part_number.unwrap_or(1),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(self.md5.finalize())
+    }
+}
+
+fn check(segment: &Segment, data: &[u8]) -> Result<Output<Md5>, IntegrityError> {
+    let mut check = Check::new(segment);
+    check.update(data);
+    check.finish(segment)
+}
+
+fn check_e_tag(e_tag: Option<String>, md5s: &[Output<Md5>]) -> Result<(), IntegrityError> {
+    let Some(actual) = e_tag else {
+        return Ok(());
+    };
+    let expected = if actual.contains('-') {
+        e_tag::composite(md5s)
+    } else {
+        format!(
+            "\"{:x}\"",
+            md5s.first().copied().unwrap_or_else(|| Md5::digest([]))
+        )
+    };
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(IntegrityError::ETagMismatch {
+            expected,
+            actual: Some(actual),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, check_e_tag, Segment};
+    use crate::{e_tag, Checksum, IntegrityError};
+    use md5::{Digest, Md5};
+
+    #[test]
+    fn test_check() {
+        let mut segment = Segment {
+            range: 0..3,
+            part_number: Some(2),
+            checksum: Some(Checksum::Crc32("jHNlIQ==".to_owned())),
+        };
+        assert_eq!(check(&segment, b"foo").unwrap(), Md5::digest(b"foo"));
+        segment.checksum = Some(Checksum::Crc32("AAAAAA==".to_owned()));
+        assert!(matches!(
+            check(&segment, b"foo"),
+            Err(IntegrityError::ChecksumMismatch { part_number: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_e_tag() {
+        let md5s = [Md5::digest(b"foo"), Md5::digest(b"bar")];
+        assert!(check_e_tag(None, &md5s).is_ok());
+        assert!(check_e_tag(Some(e_tag::composite(md5s)), &md5s).is_ok());
+        assert!(check_e_tag(Some(format!("\"{:x}\"", md5s[0])), &md5s[..1]).is_ok());
+        assert!(check_e_tag(Some(format!("\"{:x}\"", Md5::digest([]))), &[]).is_ok());
+        assert!(matches!(
+            check_e_tag(Some(e_tag::composite(md5s)), &md5s[..1]),
+            Err(IntegrityError::ETagMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_into_async_read() {
+        use super::MultipartDownloadOutput;
+        use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+        use bytes::Bytes;
+        use std::io;
+        use tokio::io::AsyncReadExt;
+
+        let output = MultipartDownloadOutput {
+            head: HeadObjectOutput::builder().build(),
+            body: Box::pin(futures::stream::iter([
+                Ok::<_, io::Error>(Bytes::from_static(b"foo")),
+                Ok(Bytes::from_static(b"bar")),
+            ])),
+        };
+        let mut data = Vec::new();
+        output
+            .into_async_read()
+            .read_to_end(&mut data)
+            .await
+            .unwrap();
+        assert_eq!(data, b"foobar");
+
+        let output = MultipartDownloadOutput {
+            head: HeadObjectOutput::builder().build(),
+            body: Box::pin(futures::stream::iter([
+                Ok(Bytes::from_static(b"foo")),
+                Err(io::Error::other("error")),
+            ])),
+        };
+        let mut data = Vec::new();
+        assert!(output
+            .into_async_read()
+            .read_to_end(&mut data)
+            .await
+            .is_err());
+    }
+}
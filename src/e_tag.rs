@@ -0,0 +1,130 @@
+use md5::digest::Output;
+use md5::{Digest, Md5};
+use std::io::{self, Read};
+use std::num::NonZeroU64;
+
+// https://docs.aws.amazon.com/AmazonS3/latest/userguide/checking-object-integrity.html#large-object-checksums
+pub(crate) fn composite<I, T>(content_md5s: I) -> String
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut hasher = Md5::new();
+    let mut count = 0;
+    for content_md5 in content_md5s {
+        hasher.update(content_md5);
+        count += 1;
+    }
+    format!("\"{:x}-{count}\"", hasher.finalize())
+}
+
+/// Computes the ETag S3 gives a multipart upload whose parts, except the last one, have
+/// `part_size` bytes, as with `part_size` of `n..=n` or [`MultipartUpload::body_path`].
+///
+/// [`MultipartUpload::body_path`]: crate::MultipartUpload::body_path
+#[derive(Clone, Debug)]
+pub struct ETagHasher {
+    part_size: u64,
+    hasher: Md5,
+    hashed: u64,
+    content_md5s: Vec<Output<Md5>>,
+}
+
+impl ETagHasher {
+    pub fn new(part_size: NonZeroU64) -> Self {
+        Self {
+            part_size: part_size.get(),
+            hasher: Md5::new(),
+            hashed: 0,
+            content_md5s: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let (head, tail) =
+                data.split_at((data.len() as u64).min(self.part_size - self.hashed) as _);
+            self.hasher.update(head);
+            self.hashed += head.len() as u64;
+            if self.hashed == self.part_size {
+                self.content_md5s.push(self.hasher.finalize_reset());
+                self.hashed = 0;
+            }
+            data = tail;
+        }
+    }
+
+    /// Returns the quoted ETag, e.g. `"66cc959da476913bd064a0ee9ecd5dff-2"`, or the plain MD5
+    /// of an empty object without any data.
+    pub fn finalize(mut self) -> String {
+        if self.hashed > 0 {
+            self.content_md5s.push(self.hasher.finalize());
+        }
+        if self.content_md5s.is_empty() {
+            return format!("\"{:x}\"", Md5::digest([]));
+        }
+        composite(self.content_md5s)
+    }
+}
+
+/// Reads `reader` to the end and returns its [`ETagHasher`] ETag, e.g. to check an existing
+/// object without downloading it.
+pub fn predict_e_tag<R>(mut reader: R, part_size: NonZeroU64) -> io::Result<String>
+where
+    R: Read,
+{
+    let mut hasher = ETagHasher::new(part_size);
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break Ok(hasher.finalize()),
+            Ok(len) => hasher.update(&buf[..len]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => break Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{composite, predict_e_tag, ETagHasher};
+    use md5::{Digest, Md5};
+    use std::io;
+
+    #[test]
+    fn test_composite() {
+        assert_eq!(
+            composite([Md5::digest([0, 1, 2]), Md5::digest([3, 4])]),
+            "\"66cc959da476913bd064a0ee9ecd5dff-2\""
+        );
+    }
+
+    #[test]
+    fn test_e_tag_hasher() {
+        let mut hasher = ETagHasher::new(3.try_into().unwrap());
+        hasher.update(&[0]);
+        hasher.update(&[1, 2, 3, 4]);
+        assert_eq!(
+            hasher.finalize(),
+            composite([Md5::digest([0, 1, 2]), Md5::digest([3, 4])])
+        );
+
+        let mut hasher = ETagHasher::new(2.try_into().unwrap());
+        hasher.update(&[0, 1, 2, 3]);
+        assert_eq!(
+            hasher.finalize(),
+            composite([Md5::digest([0, 1]), Md5::digest([2, 3])])
+        );
+
+        let hasher = ETagHasher::new(2.try_into().unwrap());
+        assert_eq!(hasher.finalize(), "\"d41d8cd98f00b204e9800998ecf8427e\"");
+    }
+
+    #[test]
+    fn test_predict_e_tag() {
+        assert_eq!(
+            predict_e_tag(io::Cursor::new([0, 1, 2, 3, 4]), 3.try_into().unwrap()).unwrap(),
+            "\"66cc959da476913bd064a0ee9ecd5dff-2\""
+        );
+    }
+}
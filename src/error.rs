@@ -0,0 +1,454 @@
+use crate::{Checksum, UploadedPart};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::AbortMultipartUploadError;
+use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError;
+use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
+use aws_sdk_s3::operation::list_multipart_uploads::ListMultipartUploadsError;
+use aws_sdk_s3::operation::list_parts::ListPartsError;
+use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::operation::upload_part::UploadPartError;
+use aws_sdk_s3::operation::{RequestId, RequestIdExt};
+use aws_sdk_s3::primitives::ByteStreamError;
+use aws_smithy_types::error::operation::BuildError;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+
+/// The errors an upload of a body may fail with, as a single bound on the error type of
+/// [`MultipartUpload::send`](crate::MultipartUpload::send) and the other methods that upload a
+/// body. Every type that converts from all of them implements it, e.g. `anyhow::Error`.
+pub trait UploadErrorKind:
+    From<SdkError<CreateMultipartUploadError>>
+    + From<PartError<SdkError<UploadPartError>>>
+    + From<SdkError<CompleteMultipartUploadError>>
+    + From<SdkError<PutObjectError>>
+    + From<PreconditionFailed>
+    + From<IntegrityError>
+    + From<BuildError>
+    + From<InvalidPartSize>
+    + From<ObjectTooLarge>
+    + From<TooManyParts>
+    + From<CircuitOpen>
+    + From<ByteStreamError>
+{
+}
+
+impl<E> UploadErrorKind for E where
+    E: From<SdkError<CreateMultipartUploadError>>
+        + From<PartError<SdkError<UploadPartError>>>
+        + From<SdkError<CompleteMultipartUploadError>>
+        + From<SdkError<PutObjectError>>
+        + From<PreconditionFailed>
+        + From<IntegrityError>
+        + From<BuildError>
+        + From<InvalidPartSize>
+        + From<ObjectTooLarge>
+        + From<TooManyParts>
+        + From<CircuitOpen>
+        + From<ByteStreamError>
+{
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct MultipartUploadError<E> {
+    pub error: E,
+    /// Set once the multipart upload has been created.
+    pub upload_id: Option<String>,
+    /// Set while the multipart upload may still hold uploaded parts.
+    pub abort: Option<AbortMultipartUploadFluentBuilder>,
+    pub request_ids: RequestIds,
+    /// Further part failures collected when `fail_fast` is disabled.
+    pub additional_errors: Vec<E>,
+    /// Parts uploaded before the upload failed.
+    pub uploaded_parts: Vec<UploadedPart>,
+}
+
+impl<E> MultipartUploadError<E> {
+    pub(crate) fn new<T>(error: T) -> Self
+    where
+        T: Into<E>,
+    {
+        Self {
+            error: error.into(),
+            upload_id: None,
+            abort: None,
+            request_ids: RequestIds::default(),
+            additional_errors: Vec::new(),
+            uploaded_parts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn request_ids(mut self, request_ids: RequestIds) -> Self {
+        self.request_ids = request_ids;
+        self
+    }
+
+    pub(crate) fn abort(
+        mut self,
+        upload_id: &Option<String>,
+        abort: AbortMultipartUploadFluentBuilder,
+    ) -> Self {
+        self.upload_id.clone_from(upload_id);
+        self.abort = Some(abort);
+        self
+    }
+
+    // Aborts the multipart uploads of `errors` and returns the first error, with the others
+    // as its `additional_errors`. An abort that fails is left on its error.
+    #[cfg(feature = "sync")]
+    pub(crate) async fn abort_all(errors: Vec<Self>) -> Option<Self> {
+        let mut errors = futures::future::join_all(errors.into_iter().map(|mut err| async {
+            if let Some(abort) = err.abort.take() {
+                if abort.clone().send().await.is_err() {
+                    err.abort = Some(abort);
+                }
+            }
+            err
+        }))
+        .await
+        .into_iter();
+        let mut first = errors.next()?;
+        for err in errors {
+            first.additional_errors.push(err.error);
+            first.additional_errors.extend(err.additional_errors);
+        }
+        Some(first)
+    }
+}
+
+impl<E> fmt::Display for MultipartUploadError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.upload_id {
+            Some(upload_id) => write!(f, "multipart upload {upload_id} failed")?,
+            None => write!(f, "multipart upload failed")?,
+        }
+        if let Some(request_id) = &self.request_ids.request_id {
+            write!(f, " (request id {request_id})")?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl<E> Error for MultipartUploadError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The `x-amz-request-id` and `x-amz-id-2` of a failed request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestIds {
+    pub request_id: Option<String>,
+    pub extended_request_id: Option<String>,
+}
+
+impl RequestIds {
+    pub(crate) fn new<T>(err: &T) -> Self
+    where
+        T: RequestId + RequestIdExt,
+    {
+        Self {
+            request_id: err.request_id().map(ToOwned::to_owned),
+            extended_request_id: err.extended_request_id().map(ToOwned::to_owned),
+        }
+    }
+}
+
+/// A `part_size` outside of the limits, which default to [`PART_SIZE`].
+///
+/// [`PART_SIZE`]: crate::PART_SIZE
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct InvalidPartSize {
+    pub part_size: RangeInclusive<u64>,
+    pub limits: RangeInclusive<u64>,
+}
+
+impl fmt::Display for InvalidPartSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "part size {:?} is not within {:?}",
+            self.part_size, self.limits
+        )
+    }
+}
+
+impl Error for InvalidPartSize {}
+
+/// A body larger than the object size limit, which defaults to [`MAX_OBJECT_SIZE`].
+///
+/// [`MAX_OBJECT_SIZE`]: crate::MAX_OBJECT_SIZE
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ObjectTooLarge {
+    pub max_object_size: u64,
+}
+
+impl fmt::Display for ObjectTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "body exceeds {} bytes", self.max_object_size)
+    }
+}
+
+impl Error for ObjectTooLarge {}
+
+/// A body of unknown length that needs more than `max_parts` parts at its part size. Setting
+/// [`MultipartUpload::size_hint`](crate::MultipartUpload::size_hint) picks a part size that
+/// fits it.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TooManyParts {
+    pub max_parts: usize,
+}
+
+impl fmt::Display for TooManyParts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "body needs more than {} parts", self.max_parts)
+    }
+}
+
+impl Error for TooManyParts {}
+
+/// The parts in flight were dropped after `consecutive_failures` parts in a row failed. The
+/// errors of the failed parts are in [`MultipartUploadError::additional_errors`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CircuitOpen {
+    pub consecutive_failures: usize,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} parts in a row failed", self.consecutive_failures)
+    }
+}
+
+impl Error for CircuitOpen {}
+
+#[derive(Debug)]
+pub struct PreconditionFailed(pub SdkError<CompleteMultipartUploadError>);
+
+impl fmt::Display for PreconditionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "precondition failed on completion")
+    }
+}
+
+impl Error for PreconditionFailed {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum IntegrityError {
+    ETagMismatch {
+        expected: String,
+        actual: Option<String>,
+    },
+    ChecksumMismatch {
+        part_number: i32,
+        expected: Checksum,
+        actual: Checksum,
+    },
+    /// The callback of
+    /// [`MultipartUpload::verify_part`](crate::MultipartUpload::verify_part) rejected a part.
+    PartRejected {
+        part_number: i32,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// A part has no valid CRC of the `FULL_OBJECT` checksum algorithm to combine, e.g. a part
+    /// listed by [`Initiated::list_parts`](crate::Initiated::list_parts) or resumed from
+    /// elsewhere.
+    InvalidPartChecksum { part_number: i32 },
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ETagMismatch { expected, actual } => write!(
+                f,
+                "ETag mismatch (expected {expected}, actual {})",
+                actual.as_deref().unwrap_or("none")
+            ),
+            Self::ChecksumMismatch {
+                part_number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch in part {part_number} (expected {}, actual {})",
+                expected.value(),
+                actual.value()
+            ),
+            Self::PartRejected { part_number, .. } => write!(f, "part {part_number} was rejected"),
+            Self::InvalidPartChecksum { part_number } => {
+                write!(f, "part {part_number} has no valid checksum to combine")
+            }
+        }
+    }
+}
+
+impl Error for IntegrityError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::PartRejected { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PartError<E> {
+    pub part_number: i32,
+    /// Byte range of the part within the object.
+    pub range: Range<u64>,
+    pub attempts: usize,
+    pub request_ids: RequestIds,
+    pub source: E,
+}
+
+impl<E> fmt::Display for PartError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "part {} (bytes {}..{}) failed after {} attempt(s)",
+            self.part_number, self.range.start, self.range.end, self.attempts
+        )
+    }
+}
+
+impl<E> Error for PartError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AbortError {
+    Abort(SdkError<AbortMultipartUploadError>),
+    ListParts(SdkError<ListPartsError>),
+    ListMultipartUploads(SdkError<ListMultipartUploadsError>),
+    /// The upload was still listed after the last attempt.
+    NotAborted,
+}
+
+impl fmt::Display for AbortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Abort(_) => write!(f, "failed to abort multipart upload"),
+            Self::ListParts(_) => write!(f, "failed to list parts of aborted multipart upload"),
+            Self::ListMultipartUploads(_) => write!(f, "failed to list multipart uploads"),
+            Self::NotAborted => write!(f, "multipart upload still exists after abort"),
+        }
+    }
+}
+
+impl Error for AbortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Abort(err) => Some(err),
+            Self::ListParts(err) => Some(err),
+            Self::ListMultipartUploads(err) => Some(err),
+            Self::NotAborted => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TarError {
+    InvalidHeader,
+    UnexpectedEof,
+}
+
+impl fmt::Display for TarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid tar header"),
+            Self::UnexpectedEof => write!(f, "unexpected end of tar archive"),
+        }
+    }
+}
+
+impl Error for TarError {}
+
+#[cfg(feature = "hyper")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PresignedError {
+    Http(hyper_util::client::legacy::Error),
+    Status(hyper::StatusCode),
+    /// The body does not line up with the byte ranges of the presigned parts.
+    Range,
+}
+
+#[cfg(feature = "hyper")]
+impl fmt::Display for PresignedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(_) => write!(f, "failed to send presigned request"),
+            Self::Status(status) => write!(f, "presigned request failed with {status}"),
+            Self::Range => write!(f, "body does not match the presigned parts"),
+        }
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl Error for PresignedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Http(err) => Some(err),
+            Self::Status(_) | Self::Range => None,
+        }
+    }
+}
+
+/// The error of a request of [`AzureBlockBlob`].
+///
+/// [`AzureBlockBlob`]: crate::AzureBlockBlob
+#[cfg(feature = "azure")]
+#[derive(Debug)]
+pub enum AzureError {
+    Request(hyper::http::Error),
+    Http(hyper_util::client::legacy::Error),
+    Status(hyper::StatusCode),
+}
+
+#[cfg(feature = "azure")]
+impl fmt::Display for AzureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(_) => write!(f, "failed to build Azure request"),
+            Self::Http(_) => write!(f, "failed to send Azure request"),
+            Self::Status(status) => write!(f, "Azure request failed with {status}"),
+        }
+    }
+}
+
+#[cfg(feature = "azure")]
+impl Error for AzureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Request(err) => Some(err),
+            Self::Http(err) => Some(err),
+            Self::Status(_) => None,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
+use aws_sdk_s3::Client;
+
+/// Whether `CreateMultipartUpload` failed in a way that another region may not, i.e. the
+/// request did not reach S3 or S3 answered with a server error.
+pub(crate) fn is_region_failure(err: &SdkError<CreateMultipartUploadError>) -> bool {
+    match err {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            true
+        }
+        SdkError::ServiceError(err) => err.raw().status().is_server_error(),
+        _ => false,
+    }
+}
+
+/// Builds `request` again with `client`, since a fluent builder cannot change its client.
+///
+/// Every field of the input is copied; `test_rebuild` fails once the SDK adds one that is not.
+/// A fluent builder has no config override to keep, since only the SDK can set one.
+pub(crate) fn rebuild(
+    client: &Client,
+    request: &CreateMultipartUploadFluentBuilder,
+) -> CreateMultipartUploadFluentBuilder {
+    client
+        .create_multipart_upload()
+        .set_acl(request.get_acl().clone())
+        .set_bucket(request.get_bucket().clone())
+        .set_bucket_key_enabled(*request.get_bucket_key_enabled())
+        .set_cache_control(request.get_cache_control().clone())
+        .set_checksum_algorithm(request.get_checksum_algorithm().clone())
+        .set_checksum_type(request.get_checksum_type().clone())
+        .set_content_disposition(request.get_content_disposition().clone())
+        .set_content_encoding(request.get_content_encoding().clone())
+        .set_content_language(request.get_content_language().clone())
+        .set_content_type(request.get_content_type().clone())
+        .set_expected_bucket_owner(request.get_expected_bucket_owner().clone())
+        .set_expires(*request.get_expires())
+        .set_grant_full_control(request.get_grant_full_control().clone())
+        .set_grant_read(request.get_grant_read().clone())
+        .set_grant_read_acp(request.get_grant_read_acp().clone())
+        .set_grant_write_acp(request.get_grant_write_acp().clone())
+        .set_key(request.get_key().clone())
+        .set_metadata(request.get_metadata().clone())
+        .set_object_lock_event_hold(request.get_object_lock_event_hold().clone())
+        .set_object_lock_event_hold_duration_days(
+            *request.get_object_lock_event_hold_duration_days(),
+        )
+        .set_object_lock_event_hold_duration_years(
+            *request.get_object_lock_event_hold_duration_years(),
+        )
+        .set_object_lock_legal_hold_status(request.get_object_lock_legal_hold_status().clone())
+        .set_object_lock_mode(request.get_object_lock_mode().clone())
+        .set_object_lock_retain_until_date(*request.get_object_lock_retain_until_date())
+        .set_request_payer(request.get_request_payer().clone())
+        .set_server_side_encryption(request.get_server_side_encryption().clone())
+        .set_sse_customer_algorithm(request.get_sse_customer_algorithm().clone())
+        .set_sse_customer_key(request.get_sse_customer_key().clone())
+        .set_sse_customer_key_md5(request.get_sse_customer_key_md5().clone())
+        .set_ssekms_encryption_context(request.get_ssekms_encryption_context().clone())
+        .set_ssekms_key_id(request.get_ssekms_key_id().clone())
+        .set_storage_class(request.get_storage_class().clone())
+        .set_tagging(request.get_tagging().clone())
+        .set_website_redirect_location(request.get_website_redirect_location().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rebuild;
+    use aws_sdk_s3::config::Region;
+    use aws_sdk_s3::primitives::DateTime;
+    use aws_sdk_s3::types::{
+        ChecksumAlgorithm, ChecksumType, ObjectCannedAcl, ObjectLockEventHold,
+        ObjectLockLegalHoldStatus, ObjectLockMode, RequestPayer, ServerSideEncryption,
+        StorageClass,
+    };
+    use aws_sdk_s3::{Client, Config};
+
+    #[test]
+    fn test_rebuild() {
+        let client = |region| {
+            Client::from_conf(
+                Config::builder()
+                    .behavior_version_latest()
+                    .region(Region::from_static(region))
+                    .build(),
+            )
+        };
+        let request = client("us-east-1")
+            .create_multipart_upload()
+            .acl(ObjectCannedAcl::Private)
+            .bucket("bucket")
+            .bucket_key_enabled(true)
+            .cache_control("no-cache")
+            .checksum_algorithm(ChecksumAlgorithm::Crc32)
+            .checksum_type(ChecksumType::Composite)
+            .content_disposition("inline")
+            .content_encoding("gzip")
+            .content_language("en")
+            .content_type("text/plain")
+            .expected_bucket_owner("owner")
+            .expires(DateTime::from_secs(1))
+            .grant_full_control("id=a")
+            .grant_read("id=b")
+            .grant_read_acp("id=c")
+            .grant_write_acp("id=d")
+            .key("key")
+            .metadata("foo", "bar")
+            .object_lock_event_hold(ObjectLockEventHold::On)
+            .object_lock_event_hold_duration_days(1)
+            .object_lock_event_hold_duration_years(2)
+            .object_lock_legal_hold_status(ObjectLockLegalHoldStatus::On)
+            .object_lock_mode(ObjectLockMode::Governance)
+            .object_lock_retain_until_date(DateTime::from_secs(2))
+            .request_payer(RequestPayer::Requester)
+            .server_side_encryption(ServerSideEncryption::AwsKms)
+            .sse_customer_algorithm("AES256")
+            .sse_customer_key("key")
+            .sse_customer_key_md5("md5")
+            .ssekms_encryption_context("context")
+            .ssekms_key_id("key-id")
+            .storage_class(StorageClass::StandardIa)
+            .tagging("foo=bar")
+            .website_redirect_location("/");
+        // every field the SDK knows of is set above
+        assert!(!format!("{:?}", request.as_input()).contains("None"));
+        assert_eq!(
+            rebuild(&client("eu-west-1"), &request).as_input(),
+            request.as_input()
+        );
+    }
+}
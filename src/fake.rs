@@ -0,0 +1,1377 @@
+use crate::e_tag;
+use aws_sdk_s3::config::http::{HttpRequest, HttpResponse};
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::timeout::TimeoutConfig;
+use aws_sdk_s3::config::{
+    Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation, RuntimeComponents,
+    StalledStreamProtectionConfig,
+};
+use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use aws_sdk_s3::{Client, Config};
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use bytes::Bytes;
+use md5::digest::Output;
+use md5::{Digest, Md5};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, PoisonError};
+
+const ENDPOINT: &str = "http://s3.fake";
+
+/// An in-memory S3 that serves `CreateMultipartUpload`, `UploadPart`, `UploadPartCopy`,
+/// `CompleteMultipartUpload`, `AbortMultipartUpload`, `ListParts`, `PutObject`, `GetObject`,
+/// `HeadObject` and unpaginated `ListObjectsV2`, for tests of code that uploads with this crate.
+///
+/// Unlike S3, parts may be smaller than 5 MiB. Checksums other than `Content-MD5` are neither
+/// verified nor returned.
+#[derive(Clone, Debug, Default)]
+pub struct FakeS3 {
+    state: Arc<Mutex<State>>,
+}
+
+/// A request received by [`FakeS3`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RecordedRequest {
+    pub method: String,
+    pub bucket: String,
+    pub key: String,
+    /// The query string, e.g. `partNumber=1&uploadId=...`.
+    pub query: String,
+    /// The headers in arrival order, with lowercase names.
+    pub headers: Vec<(String, String)>,
+}
+
+impl RecordedRequest {
+    /// `CreateMultipartUpload`, `UploadPart`, etc. as named in the S3 API.
+    pub fn operation(&self) -> &'static str {
+        let has = |name: &str| {
+            self.query
+                .split('&')
+                .any(|param| param.split('=').next() == Some(name))
+        };
+        match (self.method.as_str(), has("uploadId")) {
+            ("POST", false) if has("uploads") => "CreateMultipartUpload",
+            ("PUT", true) => "UploadPart",
+            ("POST", true) => "CompleteMultipartUpload",
+            ("DELETE", true) => "AbortMultipartUpload",
+            ("GET", true) => "ListParts",
+            ("PUT", false) => "PutObject",
+            ("GET", false) if has("list-type") => "ListObjectsV2",
+            ("GET", false) => "GetObject",
+            ("HEAD", false) => "HeadObject",
+            _ => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_upload_id: usize,
+    uploads: HashMap<String, Upload>,
+    objects: HashMap<(String, String), Object>,
+    requests: Vec<RecordedRequest>,
+}
+
+#[derive(Debug)]
+struct Upload {
+    bucket: String,
+    key: String,
+    parts: BTreeMap<i32, (Bytes, Output<Md5>)>,
+}
+
+#[derive(Clone, Debug)]
+struct Object {
+    body: Bytes,
+    e_tag: String,
+}
+
+impl FakeS3 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a client that sends its requests to this fake.
+    pub fn client(&self) -> Client {
+        Client::from_conf(
+            Config::builder()
+                .behavior_version_latest()
+                .credentials_provider(Credentials::new("fake", "fake", None, None, "fake"))
+                .endpoint_url(ENDPOINT)
+                .force_path_style(true)
+                .http_client(self.clone())
+                .region(Region::from_static("us-east-1"))
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+                .retry_config(RetryConfig::disabled())
+                .stalled_stream_protection(StalledStreamProtectionConfig::disabled())
+                .timeout_config(TimeoutConfig::disabled())
+                .build(),
+        )
+    }
+
+    /// The body of a completed object.
+    pub fn object(&self, bucket: &str, key: &str) -> Option<Bytes> {
+        self.lock()
+            .objects
+            .get(&(bucket.to_owned(), key.to_owned()))
+            .map(|object| object.body.clone())
+    }
+
+    /// The IDs of the multipart uploads that are neither completed nor aborted.
+    pub fn uploads(&self) -> Vec<String> {
+        let mut upload_ids = self.lock().uploads.keys().cloned().collect::<Vec<_>>();
+        upload_ids.sort();
+        upload_ids
+    }
+
+    /// The requests received so far, in arrival order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn handle(&self, request: HttpRequest) -> Result<HttpResponse, ConnectorError> {
+        let method = request.method().to_owned();
+        let uri = request.uri().strip_prefix(ENDPOINT).unwrap_or_default();
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let (bucket, key) = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .unwrap_or((path.trim_start_matches('/'), ""));
+        let (bucket, key, query) = (decode(bucket), decode(key), query.to_owned());
+        let params = query
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                (name.to_owned(), decode(value))
+            })
+            .collect::<HashMap<_, _>>();
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.to_owned()))
+            .collect::<Vec<_>>();
+        let header = |name| request.headers().get(name).map(ToOwned::to_owned);
+        let range = header("range");
+        let copy_source = header("x-amz-copy-source");
+        let copy_source_range = header("x-amz-copy-source-range");
+        let if_match = header("if-match");
+        let aws_chunked = request
+            .headers()
+            .get("content-encoding")
+            .is_some_and(|content_encoding| content_encoding.contains("aws-chunked"));
+        let body = ByteStream::new(request.into_body())
+            .collect()
+            .await
+            .map_err(|err| ConnectorError::other(err.into(), None))?
+            .into_bytes();
+        let body = if aws_chunked {
+            decode_aws_chunked(&body)
+        } else {
+            body
+        };
+
+        let mut state = self.lock();
+        state.requests.push(RecordedRequest {
+            method: method.clone(),
+            bucket: bucket.clone(),
+            key: key.clone(),
+            query,
+            headers,
+        });
+        let upload_id = params.get("uploadId");
+        Ok(match (method.as_str(), upload_id) {
+            ("POST", None) if params.contains_key("uploads") => {
+                state.next_upload_id += 1;
+                let upload_id = format!("upload-{}", state.next_upload_id);
+                let body = format!(
+                    "<InitiateMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key>\
+                     <UploadId>{upload_id}</UploadId></InitiateMultipartUploadResult>",
+                    escape(&bucket),
+                    escape(&key),
+                );
+                state.uploads.insert(
+                    upload_id,
+                    Upload {
+                        bucket,
+                        key,
+                        parts: BTreeMap::new(),
+                    },
+                );
+                response(200, body)
+            }
+            ("PUT", Some(upload_id)) => {
+                if !state.uploads.contains_key(upload_id) {
+                    return Ok(error(404, "NoSuchUpload"));
+                }
+                let Some(part_number) = params
+                    .get("partNumber")
+                    .and_then(|part_number| part_number.parse().ok())
+                    .filter(|part_number| (1..=10000).contains(part_number))
+                else {
+                    return Ok(error(400, "InvalidArgument"));
+                };
+                let body = match &copy_source {
+                    Some(copy_source) => {
+                        let Some(body) =
+                            copied(&state.objects, copy_source, copy_source_range.as_deref())
+                        else {
+                            return Ok(error(404, "NoSuchKey"));
+                        };
+                        body
+                    }
+                    None => body,
+                };
+                let upload = state.uploads.get_mut(upload_id).unwrap();
+                let content_md5 = Md5::digest(&body);
+                let e_tag = format!("\"{content_md5:x}\"");
+                upload.parts.insert(part_number, (body, content_md5));
+                if copy_source.is_some() {
+                    return Ok(response(
+                        200,
+                        format!(
+                            "<CopyPartResult><ETag>{}</ETag></CopyPartResult>",
+                            escape(&e_tag)
+                        ),
+                    ));
+                }
+                let mut response = response(200, String::new());
+                response.headers_mut().insert("etag", e_tag);
+                response
+            }
+            ("POST", Some(upload_id)) => {
+                let Some(upload) = state.uploads.get(upload_id) else {
+                    return Ok(error(404, "NoSuchUpload"));
+                };
+                if let Some(if_match) = &if_match {
+                    let object = state
+                        .objects
+                        .get(&(upload.bucket.clone(), upload.key.clone()));
+                    if object.is_none_or(|object| object.e_tag != *if_match) {
+                        return Ok(error(412, "PreconditionFailed"));
+                    }
+                }
+                let body = String::from_utf8_lossy(&body);
+                let part_numbers = elements(&body, "PartNumber")
+                    .into_iter()
+                    .map(|part_number| part_number.parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>();
+                let e_tags = elements(&body, "ETag")
+                    .into_iter()
+                    .map(|e_tag| e_tag.replace("&quot;", "\"").replace("&#34;", "\""))
+                    .collect::<Vec<_>>();
+                let Ok(part_numbers) = part_numbers else {
+                    return Ok(error(400, "MalformedXML"));
+                };
+                if part_numbers.is_empty() || !part_numbers.is_sorted_by(|a, b| a < b) {
+                    return Ok(error(400, "InvalidPartOrder"));
+                }
+                let mut object = Vec::new();
+                let mut content_md5s = Vec::new();
+                for (i, part_number) in part_numbers.iter().enumerate() {
+                    let Some((part, content_md5)) = upload.parts.get(part_number) else {
+                        return Ok(error(400, "InvalidPart"));
+                    };
+                    if e_tags
+                        .get(i)
+                        .is_some_and(|e_tag| *e_tag != format!("\"{content_md5:x}\""))
+                    {
+                        return Ok(error(400, "InvalidPart"));
+                    }
+                    object.extend_from_slice(part);
+                    content_md5s.push(*content_md5);
+                }
+                let e_tag = e_tag::composite(content_md5s);
+                let upload = state.uploads.remove(upload_id).unwrap();
+                let body = format!(
+                    "<CompleteMultipartUploadResult><Bucket>{}</Bucket><Key>{}</Key>\
+                     <ETag>{}</ETag></CompleteMultipartUploadResult>",
+                    escape(&upload.bucket),
+                    escape(&upload.key),
+                    escape(&e_tag),
+                );
+                state.objects.insert(
+                    (upload.bucket, upload.key),
+                    Object {
+                        body: object.into(),
+                        e_tag,
+                    },
+                );
+                response(200, body)
+            }
+            ("GET", Some(upload_id)) => {
+                let Some(upload) = state.uploads.get(upload_id) else {
+                    return Ok(error(404, "NoSuchUpload"));
+                };
+                let parts = upload
+                    .parts
+                    .iter()
+                    .map(|(part_number, (part, content_md5))| {
+                        format!(
+                            "<Part><PartNumber>{part_number}</PartNumber><ETag>{}</ETag>\
+                             <Size>{}</Size></Part>",
+                            escape(&format!("\"{content_md5:x}\"")),
+                            part.len(),
+                        )
+                    })
+                    .collect::<String>();
+                let body = format!(
+                    "<ListPartsResult><Bucket>{}</Bucket><Key>{}</Key>\
+                     <UploadId>{}</UploadId><IsTruncated>false</IsTruncated>{parts}\
+                     </ListPartsResult>",
+                    escape(&upload.bucket),
+                    escape(&upload.key),
+                    escape(upload_id),
+                );
+                response(200, body)
+            }
+            ("DELETE", Some(upload_id)) => match state.uploads.remove(upload_id) {
+                Some(_) => response(204, String::new()),
+                None => error(404, "NoSuchUpload"),
+            },
+            ("PUT", None) => {
+                let e_tag = format!("\"{:x}\"", Md5::digest(&body));
+                state.objects.insert(
+                    (bucket, key),
+                    Object {
+                        body,
+                        e_tag: e_tag.clone(),
+                    },
+                );
+                let mut response = response(200, String::new());
+                response.headers_mut().insert("etag", e_tag);
+                response
+            }
+            ("GET", None) if key.is_empty() && params.contains_key("list-type") => {
+                let prefix = params.get("prefix").map_or("", String::as_str);
+                let mut objects = state
+                    .objects
+                    .iter()
+                    .filter(|((b, k), _)| *b == bucket && k.starts_with(prefix))
+                    .collect::<Vec<_>>();
+                objects.sort_by(|a, b| a.0.cmp(b.0));
+                let contents = objects
+                    .into_iter()
+                    .map(|((_, key), object)| {
+                        format!(
+                            "<Contents><Key>{}</Key><Size>{}</Size><ETag>{}</ETag></Contents>",
+                            escape(key),
+                            object.body.len(),
+                            escape(&object.e_tag),
+                        )
+                    })
+                    .collect::<String>();
+                let body = format!(
+                    "<ListBucketResult><Name>{}</Name><IsTruncated>false</IsTruncated>\
+                     {contents}</ListBucketResult>",
+                    escape(&bucket),
+                );
+                response(200, body)
+            }
+            ("GET" | "HEAD", None) => {
+                let Some(object) = state.objects.get(&(bucket, key)).cloned() else {
+                    // as S3 does, HEAD responds without an error body
+                    return Ok(if method == "HEAD" {
+                        HttpResponse::new(404.try_into().unwrap(), SdkBody::empty())
+                    } else {
+                        error(404, "NoSuchKey")
+                    });
+                };
+                let len = object.body.len();
+                let (status, body, content_range) = match range.as_deref().and_then(|range| {
+                    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+                    let start = start.parse::<usize>().ok()?;
+                    let end = end.parse::<usize>().map_or(len, |end| (end + 1).min(len));
+                    (start < end).then_some(start..end)
+                }) {
+                    Some(range) => (
+                        206,
+                        object.body.slice(range.clone()),
+                        Some(format!("bytes {}-{}/{len}", range.start, range.end - 1)),
+                    ),
+                    None => (200, object.body, None),
+                };
+                let mut response = HttpResponse::new(
+                    status.try_into().unwrap(),
+                    if method == "HEAD" {
+                        SdkBody::empty()
+                    } else {
+                        SdkBody::from(body.clone())
+                    },
+                );
+                let headers = response.headers_mut();
+                headers.insert("content-length", body.len().to_string());
+                headers.insert("etag", object.e_tag);
+                if let Some(content_range) = content_range {
+                    headers.insert("content-range", content_range);
+                }
+                response
+            }
+            _ => error(501, "NotImplemented"),
+        })
+    }
+}
+
+// The range of an object given by the headers of `UploadPartCopy`.
+fn copied(
+    objects: &HashMap<(String, String), Object>,
+    copy_source: &str,
+    range: Option<&str>,
+) -> Option<Bytes> {
+    let copy_source = copy_source.split('?').next()?.trim_start_matches('/');
+    let (bucket, key) = copy_source.split_once('/')?;
+    let body = &objects.get(&(decode(bucket), decode(key)))?.body;
+    match range {
+        Some(range) => {
+            let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+            let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+            (start <= end && end < body.len()).then(|| body.slice(start..=end))
+        }
+        None => Some(body.clone()),
+    }
+}
+
+impl HttpConnector for FakeS3 {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let this = self.clone();
+        HttpConnectorFuture::new(async move { this.handle(request).await })
+    }
+}
+
+impl HttpClient for FakeS3 {
+    fn http_connector(
+        &self,
+        _: &HttpConnectorSettings,
+        _: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
+
+/// Fails the first `failures` part requests sent to a [`FakeS3`] with 500 Internal Error, for
+/// tests of the retries of the client.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub(crate) struct Flaky {
+    pub(crate) fake: FakeS3,
+    failures: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[cfg(test)]
+impl Flaky {
+    pub(crate) fn new(failures: usize) -> Self {
+        Self {
+            fake: FakeS3::new(),
+            failures: Arc::new(failures.into()),
+        }
+    }
+
+    /// Returns a client that makes up to `max_attempts` attempts at each request, 1 ms apart.
+    pub(crate) fn client(&self, max_attempts: u32) -> Client {
+        Client::from_conf(
+            self.fake
+                .client()
+                .config()
+                .to_builder()
+                .http_client(self.clone())
+                .retry_config(
+                    RetryConfig::standard()
+                        .with_max_attempts(max_attempts)
+                        .with_initial_backoff(std::time::Duration::from_millis(1)),
+                )
+                .build(),
+        )
+    }
+}
+
+#[cfg(test)]
+impl HttpConnector for Flaky {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        use std::sync::atomic::Ordering;
+
+        if request.uri().contains("partNumber=")
+            && self
+                .failures
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+                .is_ok()
+        {
+            return HttpConnectorFuture::ready(Ok(error(500, "InternalError")));
+        }
+        self.fake.call(request)
+    }
+}
+
+#[cfg(test)]
+impl HttpClient for Flaky {
+    fn http_connector(
+        &self,
+        _: &HttpConnectorSettings,
+        _: &RuntimeComponents,
+    ) -> SharedHttpConnector {
+        SharedHttpConnector::new(self.clone())
+    }
+}
+
+fn response(status: u16, body: String) -> HttpResponse {
+    HttpResponse::new(status.try_into().unwrap(), SdkBody::from(body))
+}
+
+fn error(status: u16, code: &str) -> HttpResponse {
+    response(
+        status,
+        format!("<Error><Code>{code}</Code><Message>{code}</Message></Error>"),
+    )
+}
+
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let (start, end) = (format!("<{name}>"), format!("</{name}>"));
+    xml.match_indices(&start)
+        .filter_map(|(i, _)| {
+            let element = &xml[i + start.len()..];
+            Some(&element[..element.find(&end)?])
+        })
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = [iter.next().unwrap_or(b'0'), iter.next().unwrap_or(b'0')];
+                let hex = std::str::from_utf8(&hex).unwrap_or("00");
+                bytes.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+            }
+            b'+' => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// <hex size>[;chunk-signature=...]\r\n<data>\r\n ... 0\r\n<trailers>\r\n\r\n
+fn decode_aws_chunked(body: &[u8]) -> Bytes {
+    let mut decoded = Vec::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(i) = rest.windows(2).position(|w| w == b"\r\n") {
+        let size = std::str::from_utf8(&rest[..i])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?, 16).ok())
+            .unwrap_or_default();
+        if size == 0 {
+            break;
+        }
+        let data = &rest[i + 2..];
+        let size = size.min(data.len());
+        decoded.extend_from_slice(&data[..size]);
+        rest = data[size..].strip_prefix(b"\r\n").unwrap_or(&data[size..]);
+    }
+    decoded.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decode_aws_chunked, FakeS3};
+    use crate::{
+        Fault, FaultInjector, IntegrityError, MultipartUpload, ProviderLimits, TarError,
+        TooManyParts,
+    };
+    use aws_sdk_s3::primitives::ByteStream;
+    use aws_smithy_types::error::operation::BuildError;
+    use bytes::Bytes;
+    use std::convert::Infallible;
+    use std::num::NonZeroUsize;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_fake_s3() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let body = (0..25).collect::<Vec<u8>>();
+        let output = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("a b/c")
+            .verify_e_tag(true)
+            .body(ByteStream::from(body.clone()))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.parts.len(), 3);
+        assert_eq!(fake.object("bucket", "a b/c").unwrap(), body);
+        assert!(fake.uploads().is_empty());
+
+        let mut operations = fake
+            .requests()
+            .iter()
+            .map(|request| request.operation())
+            .collect::<Vec<_>>();
+        operations[1..4].sort();
+        assert_eq!(
+            operations,
+            [
+                "CreateMultipartUpload",
+                "UploadPart",
+                "UploadPart",
+                "UploadPart",
+                "CompleteMultipartUpload",
+            ],
+        );
+
+        let output = client
+            .get_object()
+            .bucket("bucket")
+            .key("a b/c")
+            .range("bytes=5-14")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            output.body.collect().await.unwrap().into_bytes(),
+            body[5..15],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_abort() {
+        let fake = FakeS3::new();
+        let initiated = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .initiate::<anyhow::Error>()
+            .await
+            .unwrap();
+        assert_eq!(fake.uploads(), [initiated.upload_id().unwrap()]);
+        initiated.abort().send().await.unwrap();
+        assert!(fake.uploads().is_empty());
+        assert!(initiated.abort().send().await.is_err());
+        assert!(fake.object("bucket", "key").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_abort_verified() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let err = MultipartUpload::new(&client)
+            .mpu_client(Arc::new(
+                FaultInjector::new(client.clone()).part(2, Fault::error(500, "InternalError")),
+            ))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        let abort = err.abort.unwrap();
+        crate::abort_verified(&client, abort.clone()).await.unwrap();
+        assert!(fake.uploads().is_empty());
+        // an upload that is already gone counts as aborted
+        crate::abort_verified(&client, abort).await.unwrap();
+        assert_eq!(
+            fake.requests()
+                .iter()
+                .filter(|request| request.operation() == "ListParts")
+                .count(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_missing_key() {
+        let fake = FakeS3::new();
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<BuildError>());
+        assert!(err.upload_id.is_none());
+        assert!(fake.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_provider_limits() {
+        let fake = FakeS3::new();
+        let mut limits = ProviderLimits::MINIO;
+        limits.part_size = 10..=10;
+        limits.max_parts = 2;
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .provider_limits(limits)
+            .starting_part_number(3)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<BuildError>());
+        assert!(fake.requests().is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_fake_s3_body_path_too_many_parts() {
+        let fake = FakeS3::new();
+        let mut limits = ProviderLimits::MINIO;
+        limits.part_size = 5..=100;
+        limits.max_parts = 3;
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::write(&path, [0; 25]).unwrap();
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body_path(&path)
+            .provider_limits(limits.clone())
+            .send::<anyhow::Error>(5..=5, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<TooManyParts>());
+        assert!(fake.requests().is_empty());
+
+        let mut initiated = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .provider_limits(limits)
+            .initiate::<anyhow::Error>()
+            .await
+            .unwrap();
+        let err = initiated
+            .upload_path::<anyhow::Error, _>(&path, 5..=5, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<TooManyParts>());
+        assert_eq!(fake.requests().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_size_hint() {
+        let fake = FakeS3::new();
+        let mut limits = ProviderLimits::MINIO;
+        limits.part_size = 5..=100;
+        limits.max_parts = 3;
+        let upload = || {
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .body_stream(futures::stream::iter(
+                    (0..5).map(|i| Ok::<_, Infallible>(Bytes::from(vec![i; 5]))),
+                ))
+                .provider_limits(limits.clone())
+        };
+        let err = upload()
+            .send::<anyhow::Error>(5..=100, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<TooManyParts>());
+
+        upload()
+            .size_hint(25)
+            .send::<anyhow::Error>(5..=100, None)
+            .await
+            .unwrap();
+        assert_eq!(fake.object("bucket", "key").unwrap().len(), 25);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_verify_part() {
+        let fake = FakeS3::new();
+        let e_tags = Arc::new(Mutex::new(Vec::new()));
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .verify_part({
+                let e_tags = e_tags.clone();
+                move |part_info, output| {
+                    e_tags
+                        .lock()
+                        .unwrap()
+                        .push((part_info.number, output.e_tag.clone().unwrap()));
+                    async { Ok(()) }
+                }
+            })
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        let mut e_tags = e_tags.lock().unwrap().clone();
+        e_tags.sort();
+        assert_eq!(
+            e_tags.iter().map(|(number, _)| *number).collect::<Vec<_>>(),
+            [1, 2, 3],
+        );
+
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("rejected")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .verify_part(|part_info, _| {
+                let number = part_info.number;
+                async move {
+                    if number == 2 {
+                        Err("not in the audit log".into())
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.error.downcast_ref(),
+            Some(IntegrityError::PartRejected { part_number: 2, .. }),
+        ));
+        assert!(err.abort.is_some());
+        assert!(fake.object("bucket", "rejected").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_gcs_compat() {
+        use aws_sdk_s3::config::RequestChecksumCalculation;
+        use aws_sdk_s3::Client;
+
+        let fake = FakeS3::new();
+        // the SDK would add checksums to every request that supports them
+        let client = Client::from_conf(
+            fake.client()
+                .config()
+                .to_builder()
+                .request_checksum_calculation(RequestChecksumCalculation::WhenSupported)
+                .build(),
+        );
+        let body = (0..25).collect::<Vec<u8>>();
+        MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body.clone()))
+            .gcs_compat(true)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(fake.object("bucket", "key").unwrap(), body);
+        let requests = fake.requests();
+        assert_eq!(requests[0].operation(), "CreateMultipartUpload");
+        for request in &requests {
+            assert!(
+                request.headers.iter().all(|(name, _)| {
+                    !name.starts_with("x-amz-checksum-")
+                        && name != "x-amz-sdk-checksum-algorithm"
+                        && name != "x-amz-trailer"
+                }),
+                "{request:?}",
+            );
+        }
+
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body))
+            .gcs_compat(true)
+            .verify_e_tag(true)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<BuildError>());
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_object_digest() {
+        use crate::Checksum;
+        use aws_sdk_s3::types::ChecksumAlgorithm;
+        use sha2::{Digest, Sha256};
+
+        let fake = FakeS3::new();
+        let body = (0..25).collect::<Vec<u8>>();
+        let expected = Checksum::Sha256(base64::encode(Sha256::digest(&body)));
+        let output = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body.clone()))
+            .object_digest(ChecksumAlgorithm::Sha256)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.object_digest, Some(expected.clone()));
+
+        #[cfg(feature = "tokio")]
+        {
+            let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+            std::fs::write(&path, &body).unwrap();
+            let output = MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .body_path(&path)
+                .object_digest(ChecksumAlgorithm::Sha256)
+                .part_size_limits(10..=10)
+                .send::<anyhow::Error>(10..=10, None)
+                .await
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(output.object_digest, Some(expected));
+        }
+
+        let output = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.object_digest, None);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_manifest() {
+        use aws_sdk_s3::types::ChecksumAlgorithm;
+        use md5::Md5;
+        use sha2::{Digest, Sha256};
+
+        let fake = FakeS3::new();
+        let body = (0..25).collect::<Vec<u8>>();
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body.clone()))
+            .object_digest(ChecksumAlgorithm::Sha256)
+            .manifest(true)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        let manifest = fake.object("bucket", "key.manifest").unwrap();
+        let manifest = std::str::from_utf8(&manifest).unwrap();
+        assert!(manifest.starts_with(r#"{"bucket":"bucket","key":"key","upload_id":""#));
+        assert!(manifest.contains(r#""content_length":25,"#));
+        assert!(manifest.contains(&format!(
+            r#""object_digest":{{"algorithm":"SHA256","value":"{}"}}"#,
+            base64::encode(Sha256::digest(&body)),
+        )));
+        assert!(manifest.ends_with(&format!(
+            r#"{{"number":3,"offset":20,"len":5,"content_md5":"{}","checksum":null,"digests":[]}}]}}"#,
+            base64::encode(Md5::digest(&body[20..])),
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_resume_parts() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let body = (0..25).collect::<Vec<u8>>();
+        let err = MultipartUpload::new(&client)
+            .mpu_client(Arc::new(
+                FaultInjector::new(client.clone()).part(3, Fault::error(500, "InternalError")),
+            ))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body.clone()))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        let upload_id = err.upload_id.unwrap();
+
+        let mut initiated = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .upload_id(&upload_id)
+            .initiate::<anyhow::Error>()
+            .await
+            .unwrap();
+        initiated.list_parts::<anyhow::Error>().await.unwrap();
+        let parts = initiated.parts().to_vec();
+        assert_eq!(parts.len(), 2);
+        let output = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .upload_id(&upload_id)
+            .resume_parts(parts[1..].to_vec())
+            .body(ByteStream::from(body[20..].to_vec()))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(output.error.downcast_ref::<BuildError>().is_some());
+
+        let output = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .upload_id(&upload_id)
+            .resume_parts(parts)
+            .body(ByteStream::from(body[20..].to_vec()))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.content_length, 25);
+        assert_eq!(output.parts.len(), 3);
+        assert_eq!(fake.object("bucket", "key").unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_full_object_listed_part() {
+        use crate::IntegrityError;
+        use aws_sdk_s3::types::{ChecksumAlgorithm, ChecksumType};
+
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let mut initiated = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .initiate::<anyhow::Error>()
+            .await
+            .unwrap();
+        initiated
+            .upload_parts::<anyhow::Error>(ByteStream::from_static(&[0; 10]), 10..=10, None)
+            .await
+            .unwrap();
+        let upload_id = initiated.upload_id().unwrap().to_owned();
+        initiated.list_parts::<anyhow::Error>().await.unwrap();
+        // the fake returns no checksums, so the listed part has none to combine
+        let err = MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .upload_id(&upload_id)
+            .resume_parts(initiated.parts().to_vec())
+            .checksum_algorithm(ChecksumAlgorithm::Crc32)
+            .checksum_type(ChecksumType::FullObject)
+            .body(ByteStream::from_static(&[0; 5]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.error.downcast_ref::<IntegrityError>(),
+            Some(IntegrityError::InvalidPartChecksum { part_number: 1 }),
+        ));
+        assert!(err.abort.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_to_json() {
+        let output = MultipartUpload::new(&FakeS3::new().client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        let json = output.to_json("bucket", "key");
+        assert!(json.starts_with(r#"{"bucket":"bucket","key":"key","upload_id":""#));
+        assert!(json.contains(&format!(
+            r#""e_tag":"{}","object_digest":null,"version_id":null,"checksum":null,"destination":0,"duration":{},"#,
+            output.output.e_tag.as_deref().unwrap().replace('"', r#"\""#),
+            output.duration.as_secs_f64(),
+        )));
+        assert!(json.contains(r#""stats":{"mean_part_duration":"#));
+        assert!(json.ends_with(&format!(
+            r#""digests":[],"duration":{},"attempts":1}}]}}"#,
+            output.parts[2].duration.as_secs_f64(),
+        )));
+        assert_eq!(json.matches(r#""attempts":1"#).count(), 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_fake_s3_object_digest_metadata() {
+        use crate::Checksum;
+        use sha2::{Digest, Sha256};
+        use std::sync::Mutex;
+
+        let fake = FakeS3::new();
+        let body = (0..25).collect::<Vec<u8>>();
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::write(&path, &body).unwrap();
+        let metadata = Arc::new(Mutex::new(None));
+        let output = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body_path(&path)
+            .object_digest_metadata("sha256")
+            .customize_create({
+                let metadata = metadata.clone();
+                move |create| {
+                    *metadata.lock().unwrap() = create.get_metadata().clone();
+                    create
+                }
+            })
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let expected = base64::encode(Sha256::digest(&body));
+        assert_eq!(
+            metadata.lock().unwrap().as_ref().unwrap()["sha256"],
+            expected,
+        );
+        assert_eq!(output.object_digest, Some(Checksum::Sha256(expected)));
+
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body))
+            .object_digest_metadata("sha256")
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<BuildError>());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_fake_s3_send_if_changed() {
+        use crate::SendOutput;
+
+        let fake = FakeS3::new();
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let send = || {
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .body_path(&path)
+                .part_size_limits(10..=10)
+                .send_if_changed::<anyhow::Error>(10..=10, None)
+        };
+
+        std::fs::write(&path, (0..25).collect::<Vec<u8>>()).unwrap();
+        assert!(matches!(send().await.unwrap(), SendOutput::Uploaded(_)));
+        let creates = || {
+            fake.requests()
+                .iter()
+                .filter(|request| request.operation() == "CreateMultipartUpload")
+                .count()
+        };
+        assert_eq!(creates(), 1);
+        assert!(matches!(send().await.unwrap(), SendOutput::Skipped(_)));
+        assert_eq!(creates(), 1);
+
+        std::fs::write(&path, (1..26).collect::<Vec<u8>>()).unwrap();
+        assert!(matches!(send().await.unwrap(), SendOutput::Uploaded(_)));
+        assert_eq!(creates(), 2);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            fake.object("bucket", "key").unwrap(),
+            (1..26).collect::<Vec<u8>>(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_sequential() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        MultipartUpload::new(&client)
+            .mpu_client(Arc::new(
+                FaultInjector::new(client.clone()).part(1, Fault::Delay(Duration::from_millis(50))),
+            ))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        let part_numbers = fake
+            .requests()
+            .into_iter()
+            .filter(|request| request.operation() == "UploadPart")
+            .map(|request| {
+                request
+                    .query
+                    .split('&')
+                    .find_map(|param| param.strip_prefix("partNumber="))
+                    .unwrap()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(part_numbers, ["1", "2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_verify_e_tag_copied_parts() {
+        let fake = FakeS3::new();
+        let append = |body: Vec<u8>| {
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .body(ByteStream::from(body))
+                .verify_e_tag(true)
+                .part_size_limits(10..=10)
+                .append_to::<anyhow::Error, _>("key", 10..=10, None)
+        };
+
+        append((0..25).collect()).await.unwrap();
+        // the copied parts have no MD5 to compute the ETag from
+        append((25..40).collect()).await.unwrap();
+        assert_eq!(
+            fake.object("bucket", "key").unwrap(),
+            (0..40).collect::<Vec<u8>>(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_append_to() {
+        use crate::AppendOutput;
+
+        let fake = FakeS3::new();
+        let append = |body: Vec<u8>| {
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .body(ByteStream::from(body))
+                .part_size_limits(10..=10)
+                .append_to::<anyhow::Error, _>("key", 10..=10, None)
+        };
+
+        let output = append((0..25).collect()).await.unwrap();
+        assert!(matches!(output, AppendOutput::Rewritten(_)));
+        let output = append((25..40).collect()).await.unwrap();
+        let AppendOutput::Rewritten(output) = output else {
+            panic!("{output:?}");
+        };
+        assert_eq!(output.content_length, 40);
+        assert_eq!(
+            fake.object("bucket", "key").unwrap(),
+            (0..40).collect::<Vec<u8>>(),
+        );
+        // the last 5 bytes do not fill a part and are uploaded again
+        assert_eq!(
+            fake.requests()
+                .iter()
+                .filter(|request| request.operation() == "GetObject")
+                .count(),
+            1,
+        );
+
+        let err = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 5]))
+            .if_match("\"stale\"")
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert!(err.error.is::<crate::PreconditionFailed>());
+        assert_eq!(fake.object("bucket", "key").unwrap().len(), 40);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_accelerate() {
+        let fake = FakeS3::new();
+        let send = |accelerate| {
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .body(ByteStream::from_static(&[0; 25]))
+                .part_size_limits(10..=10)
+                .accelerate(accelerate)
+                .send::<anyhow::Error>(10..=10, None)
+        };
+
+        // the endpoint of the fake is custom
+        let err = send(true).await.unwrap_err();
+        assert!(format!("{:#}", err.error).contains("S3 Accelerate"));
+        assert!(fake.requests().is_empty());
+
+        send(false).await.unwrap();
+        assert_eq!(fake.object("bucket", "key").unwrap(), &[0; 25][..]);
+    }
+
+    #[tokio::test]
+    async fn test_fake_s3_failover() {
+        let primary = FakeS3::new();
+        let replica = FakeS3::new();
+        let send = |fault| {
+            let client = primary.client();
+            MultipartUpload::new(&client)
+                .mpu_client(Arc::new(FaultInjector::new(client.clone()).create(fault)))
+                .failover(&replica.client(), "replica")
+                .bucket("bucket")
+                .key("key")
+                .body(ByteStream::from_static(&[0; 25]))
+                .part_size_limits(10..=10)
+                .send::<anyhow::Error>(10..=10, None)
+        };
+
+        let output = send(Fault::error(503, "ServiceUnavailable")).await.unwrap();
+        assert_eq!(output.destination, 1);
+        assert!(primary.object("bucket", "key").is_none());
+        assert_eq!(replica.object("replica", "key").unwrap(), &[0; 25][..]);
+
+        send(Fault::error(403, "AccessDenied")).await.unwrap_err();
+        assert!(replica.uploads().is_empty());
+    }
+
+    #[cfg(feature = "sync")]
+    #[tokio::test]
+    async fn test_fake_s3_upload_tar_truncated() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let mut archive = crate::tar::archive(&[(b'0', "foo", &[0; 25]), (b'0', "bar", &[1; 25])]);
+        // ends within the data of bar
+        archive.truncate(512 * 3 + 15);
+        let err = crate::upload_tar::<anyhow::Error, _>(
+            ByteStream::from(archive),
+            |path| {
+                MultipartUpload::new(&client)
+                    .bucket("bucket")
+                    .key(path)
+                    .part_size_limits(10..=10)
+            },
+            10..=10,
+            NonZeroUsize::new(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.error.downcast_ref(),
+            Some(TarError::UnexpectedEof)
+        ));
+        assert!(err.abort.is_none());
+        assert_eq!(fake.object("bucket", "foo").unwrap(), &[0; 25][..]);
+        assert!(fake.object("bucket", "bar").is_none());
+        assert!(fake.uploads().is_empty());
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("a%20b%2Fc+d"), "a b/c d");
+    }
+
+    #[test]
+    fn test_decode_aws_chunked() {
+        assert_eq!(
+            decode_aws_chunked(b"3;chunk-signature=x\r\nfoo\r\n4\r\nbar!\r\n0\r\nx-amz-checksum-crc32:AAAAAA==\r\n\r\n"),
+            &b"foobar!"[..],
+        );
+    }
+}
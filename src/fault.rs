@@ -0,0 +1,348 @@
+use crate::MpuClient;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{ErrorMetadata, SdkError};
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::{
+    AbortMultipartUploadError, AbortMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::{
+    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::{
+    CreateMultipartUploadError, CreateMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+use aws_sdk_s3::primitives::SdkBody;
+use futures::future::BoxFuture;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// A fault injected by [`FaultInjector`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Answers with a service error instead of sending the request, e.g. `500 InternalError`,
+    /// or `200 InternalError` for an error that S3 reports after a `200 OK`.
+    Error { status: u16, code: String },
+    /// Sends the request after a delay.
+    Delay(Duration),
+}
+
+impl Fault {
+    pub fn error(status: u16, code: impl Into<String>) -> Self {
+        Self::Error {
+            status,
+            code: code.into(),
+        }
+    }
+}
+
+/// An [`MpuClient`] that injects [`Fault`]s into the `CreateMultipartUpload` call, specific part
+/// numbers or the `CompleteMultipartUpload` call, for deterministic tests of retry and abort handling.
+///
+/// The faults for each call are queued and each request consumes one of them, so the n-th
+/// attempt of a call can be made to fail. Requests without a queued fault are passed through.
+#[derive(Debug)]
+pub struct FaultInjector<C> {
+    inner: C,
+    faults: Mutex<HashMap<Target, VecDeque<Fault>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Target {
+    Create,
+    Part(i32),
+    Complete,
+}
+
+impl<C> FaultInjector<C>
+where
+    C: MpuClient,
+{
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            faults: Mutex::default(),
+        }
+    }
+
+    /// Queues a fault for `CreateMultipartUpload`.
+    pub fn create(self, fault: Fault) -> Self {
+        self.push(Target::Create, fault)
+    }
+
+    /// Queues a fault for `UploadPart` of `part_number`.
+    pub fn part(self, part_number: i32, fault: Fault) -> Self {
+        self.push(Target::Part(part_number), fault)
+    }
+
+    /// Queues a fault for `CompleteMultipartUpload`.
+    pub fn complete(self, fault: Fault) -> Self {
+        self.push(Target::Complete, fault)
+    }
+
+    /// The number of queued faults that were not injected yet.
+    pub fn remaining(&self) -> usize {
+        self.lock().values().map(VecDeque::len).sum()
+    }
+
+    fn push(self, target: Target, fault: Fault) -> Self {
+        self.lock().entry(target).or_default().push_back(fault);
+        self
+    }
+
+    fn pop(&self, target: Target) -> Option<Fault> {
+        self.lock().get_mut(&target)?.pop_front()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Target, VecDeque<Fault>>> {
+        self.faults.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C> MpuClient for FaultInjector<C>
+where
+    C: MpuClient,
+{
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+    {
+        let fault = self.pop(Target::Create);
+        Box::pin(async move {
+            match fault {
+                Some(Fault::Error { status, code }) => Err(service_error(
+                    status,
+                    &code,
+                    CreateMultipartUploadError::generic,
+                )),
+                Some(Fault::Delay(duration)) => {
+                    tokio::time::sleep(duration).await;
+                    self.inner.create_multipart_upload(request).await
+                }
+                None => self.inner.create_multipart_upload(request).await,
+            }
+        })
+    }
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+        let fault = request
+            .get_part_number()
+            .and_then(|part_number| self.pop(Target::Part(part_number)));
+        Box::pin(async move {
+            match fault {
+                Some(Fault::Error { status, code }) => {
+                    Err(service_error(status, &code, UploadPartError::generic))
+                }
+                Some(Fault::Delay(duration)) => {
+                    tokio::time::sleep(duration).await;
+                    self.inner.upload_part(request).await
+                }
+                None => self.inner.upload_part(request).await,
+            }
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>
+    {
+        let fault = self.pop(Target::Complete);
+        Box::pin(async move {
+            match fault {
+                Some(Fault::Error { status, code }) => Err(service_error(
+                    status,
+                    &code,
+                    CompleteMultipartUploadError::generic,
+                )),
+                Some(Fault::Delay(duration)) => {
+                    tokio::time::sleep(duration).await;
+                    self.inner.complete_multipart_upload(request).await
+                }
+                None => self.inner.complete_multipart_upload(request).await,
+            }
+        })
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+    {
+        self.inner.abort_multipart_upload(request)
+    }
+}
+
+fn service_error<E>(status: u16, code: &str, generic: fn(ErrorMetadata) -> E) -> SdkError<E> {
+    SdkError::service_error(
+        generic(
+            ErrorMetadata::builder()
+                .code(code)
+                .message("injected fault")
+                .build(),
+        ),
+        HttpResponse::new(status.try_into().unwrap(), SdkBody::empty()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fault, FaultInjector};
+    use crate::{CircuitOpen, FakeS3, MultipartUpload, PartError};
+    use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+    use aws_sdk_s3::operation::upload_part::UploadPartError;
+    use aws_sdk_s3::primitives::ByteStream;
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_fault_part() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let injector = Arc::new(
+            FaultInjector::new(client.clone()).part(2, Fault::error(500, "InternalError")),
+        );
+        let err = MultipartUpload::new(&client)
+            .mpu_client(injector.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(injector.remaining(), 0);
+        let part_error = err
+            .error
+            .downcast_ref::<PartError<SdkError<UploadPartError>>>()
+            .unwrap();
+        assert_eq!(part_error.part_number, 2);
+        assert_eq!(part_error.source.code(), Some("InternalError"));
+        err.abort.unwrap().send().await.unwrap();
+        assert!(fake.uploads().is_empty());
+        assert!(fake.object("bucket", "key").is_none());
+    }
+
+    // the client of `FakeS3` has no sleep_impl of its own
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_fault_complete() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let injector = Arc::new(
+            FaultInjector::new(client.clone())
+                .part(1, Fault::Delay(Duration::from_millis(10)))
+                .complete(Fault::error(200, "InternalError"))
+                .complete(Fault::error(200, "InternalError")),
+        );
+        let start = Instant::now();
+        MultipartUpload::new(&client)
+            .mpu_client(injector.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .complete_retries(2)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(injector.remaining(), 0);
+        // 100 ms and 200 ms of backoff
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert_eq!(fake.object("bucket", "key").unwrap(), [0; 25][..]);
+        assert_eq!(
+            fake.requests()
+                .iter()
+                .filter(|request| request.operation() == "CompleteMultipartUpload")
+                .count(),
+            1,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fault_circuit_breaker() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let injector = Arc::new(
+            FaultInjector::new(client.clone())
+                .part(1, Fault::error(403, "ExpiredToken"))
+                .part(2, Fault::error(403, "ExpiredToken"))
+                .part(3, Fault::Delay(Duration::from_secs(60))),
+        );
+        // would wait for part 3 without the circuit breaker
+        let err = tokio::time::timeout(
+            Duration::from_secs(10),
+            MultipartUpload::new(&client)
+                .mpu_client(injector)
+                .bucket("bucket")
+                .key("key")
+                .body(ByteStream::from_static(&[0; 25]))
+                .fail_fast(false)
+                .circuit_breaker(NonZeroUsize::new(2).unwrap())
+                .part_size_limits(10..=10)
+                .send::<anyhow::Error>(10..=10, None),
+        )
+        .await
+        .unwrap()
+        .unwrap_err();
+        assert_eq!(
+            err.error
+                .downcast_ref::<CircuitOpen>()
+                .unwrap()
+                .consecutive_failures,
+            2,
+        );
+        assert_eq!(err.additional_errors.len(), 2);
+        err.abort.unwrap().send().await.unwrap();
+        assert!(fake.uploads().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fault_circuit_breaker_sequential() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let injector = Arc::new(
+            FaultInjector::new(client.clone())
+                .part(1, Fault::error(403, "ExpiredToken"))
+                .part(3, Fault::error(403, "ExpiredToken"))
+                .part(4, Fault::error(403, "ExpiredToken")),
+        );
+        // keeps going after part 1 but never sends part 5
+        let err = MultipartUpload::new(&client)
+            .mpu_client(injector)
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 60]))
+            .circuit_breaker(NonZeroUsize::new(2).unwrap())
+            .sequential(true)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.error
+                .downcast_ref::<CircuitOpen>()
+                .unwrap()
+                .consecutive_failures,
+            2,
+        );
+        assert_eq!(err.additional_errors.len(), 3);
+        let part_numbers = fake
+            .requests()
+            .iter()
+            .filter(|request| request.operation() == "UploadPart")
+            .map(|request| request.query.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(part_numbers.len(), 1);
+        assert!(part_numbers[0].contains("partNumber=2"));
+    }
+}
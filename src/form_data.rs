@@ -0,0 +1,128 @@
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput, UploadErrorKind};
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::{SinkExt, StreamExt};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::pin::pin;
+
+/// Uploads a `multipart/form-data` field, e.g. a file posted by a browser, as the body of
+/// `upload`, with the `Content-Type` of the field unless `upload` has one.
+///
+/// A field borrows the request it is read from, so it is streamed into the upload from this
+/// future rather than passed to [`MultipartUpload::body_stream`]. An error reading the field
+/// fails the upload with a [`ByteStreamError`](aws_sdk_s3::primitives::ByteStreamError) whose
+/// source is the [`multer::Error`].
+pub async fn upload_field<E>(
+    mut upload: MultipartUpload,
+    mut field: multer::Field<'_>,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
+where
+    E: UploadErrorKind,
+{
+    if upload.create.get_content_type().is_none() {
+        if let Some(content_type) = field.content_type() {
+            upload = upload.content_type(content_type.to_string());
+        }
+    }
+    let (mut tx, rx) = mpsc::channel(0);
+    let send = pin!(upload.body_stream(rx).send(part_size, concurrency_limit));
+    let forward = pin!(async move {
+        while let Some(chunk) = field.next().await {
+            let failed = chunk.is_err();
+            // the upload has dropped the body once it fails
+            if tx.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+    // the field may stall after the upload has failed
+    match future::select(send, forward).await {
+        Either::Left((output, _)) => output,
+        Either::Right(((), send)) => send.await,
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::upload_field;
+    use crate::{FakeS3, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStreamError;
+    use bytes::Bytes;
+    use std::convert::Infallible;
+
+    fn form_data(body: &[u8]) -> Vec<u8> {
+        [
+            &b"--boundary\r\n\
+               Content-Disposition: form-data; name=\"file\"; filename=\"a.bin\"\r\n\
+               Content-Type: application/octet-stream\r\n\r\n"[..],
+            body,
+            b"\r\n--boundary--\r\n",
+        ]
+        .concat()
+    }
+
+    #[tokio::test]
+    async fn test_upload_field() {
+        let fake = FakeS3::new();
+        let body = (0..25).collect::<Vec<u8>>();
+        let form_data = form_data(&body);
+        // a chunk per byte exercises fields split across reads
+        let chunks = form_data
+            .into_iter()
+            .map(|byte| Ok::<_, Infallible>(Bytes::from(vec![byte])));
+        let mut multipart = multer::Multipart::new(futures::stream::iter(chunks), "boundary");
+        let field = multipart.next_field().await.unwrap().unwrap();
+        let output = upload_field::<anyhow::Error>(
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .part_size_limits(10..=10),
+            field,
+            10..=10,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output.parts.len(), 3);
+        assert_eq!(fake.object("bucket", "key").unwrap(), body);
+        let create = &fake.requests()[0];
+        assert_eq!(create.operation(), "CreateMultipartUpload");
+        assert!(create.headers.contains(&(
+            "content-type".to_owned(),
+            "application/octet-stream".to_owned()
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_upload_field_error() {
+        let fake = FakeS3::new();
+        // the request ends within the field
+        let form_data = form_data(&[0; 25]);
+        let form_data = Bytes::from(form_data[..form_data.len() - 20].to_vec());
+        let mut multipart = multer::Multipart::new(
+            futures::stream::iter([Ok::<_, Infallible>(form_data)]),
+            "boundary",
+        );
+        let field = multipart.next_field().await.unwrap().unwrap();
+        let err = upload_field::<anyhow::Error>(
+            MultipartUpload::new(&fake.client())
+                .bucket("bucket")
+                .key("key")
+                .part_size_limits(10..=10),
+            field,
+            10..=10,
+            None,
+        )
+        .await
+        .unwrap_err();
+        let err = err.error.downcast_ref::<ByteStreamError>().unwrap();
+        assert!(std::error::Error::source(err)
+            .unwrap()
+            .downcast_ref::<multer::Error>()
+            .is_some());
+        assert_eq!(fake.object("bucket", "key"), None);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{MpuClient, MultipartUploadError, MultipartUploadOutput};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::{
+    AbortMultipartUploadError, AbortMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::{
+    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::{
+    CreateMultipartUploadError, CreateMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// The state of an upload started by
+/// [`MultipartUpload::send_background`](crate::MultipartUpload::send_background).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UploadStatus {
+    /// Waiting for `CreateMultipartUpload`.
+    Initiating,
+    Uploading,
+    /// Held by [`UploadHandle::pause`]; parts in flight still finish.
+    Paused,
+    /// Waiting for `CompleteMultipartUpload`.
+    Completing,
+    Completed,
+    Failed,
+}
+
+/// The parts of an upload acknowledged by S3 so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UploadProgress {
+    pub parts: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug)]
+struct Shared {
+    status: Mutex<UploadStatus>,
+    parts: AtomicUsize,
+    bytes: AtomicU64,
+    paused: watch::Sender<bool>,
+}
+
+impl Shared {
+    fn set_status(&self, status: UploadStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+/// An upload running on a spawned task. Awaiting the handle gives the result of
+/// [`MultipartUpload::send`](crate::MultipartUpload::send).
+///
+/// Dropping the handle does not stop the upload.
+#[derive(Debug)]
+pub struct UploadHandle<E> {
+    task: JoinHandle<Result<MultipartUploadOutput, MultipartUploadError<E>>>,
+    shared: Arc<Shared>,
+}
+
+impl<E> UploadHandle<E>
+where
+    E: Send + 'static,
+{
+    /// Wraps `mpu_client` to track the upload `f` will send through it, and spawns `f`.
+    pub(crate) fn spawn<F, Fut>(mpu_client: Arc<dyn MpuClient>, f: F) -> Self
+    where
+        F: FnOnce(Arc<dyn MpuClient>) -> Fut,
+        Fut: Future<Output = Result<MultipartUploadOutput, MultipartUploadError<E>>>
+            + Send
+            + 'static,
+    {
+        let shared = Arc::new(Shared {
+            status: Mutex::new(UploadStatus::Initiating),
+            parts: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            paused: watch::Sender::new(false),
+        });
+        let upload = f(Arc::new(Tracked {
+            inner: mpu_client,
+            shared: shared.clone(),
+        }));
+        let task = tokio::spawn({
+            let shared = shared.clone();
+            async move {
+                let output = upload.await;
+                shared.set_status(match output {
+                    Ok(_) => UploadStatus::Completed,
+                    Err(_) => UploadStatus::Failed,
+                });
+                output
+            }
+        });
+        Self { task, shared }
+    }
+}
+
+impl<E> UploadHandle<E> {
+    pub fn status(&self) -> UploadStatus {
+        let status = *self.shared.status.lock().unwrap();
+        match status {
+            UploadStatus::Initiating | UploadStatus::Uploading if *self.shared.paused.borrow() => {
+                UploadStatus::Paused
+            }
+            status => status,
+        }
+    }
+
+    pub fn progress(&self) -> UploadProgress {
+        UploadProgress {
+            parts: self.shared.parts.load(Ordering::Relaxed),
+            bytes: self.shared.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Holds the parts that have not been sent yet until [`Self::resume`]. The body is not read
+    /// past the parts waiting to be sent, so a paused upload buffers at most the concurrency
+    /// limit of parts.
+    pub fn pause(&self) {
+        self.shared.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.shared.paused.send_replace(false);
+    }
+}
+
+impl<E> Future for UploadHandle<E> {
+    type Output = Result<MultipartUploadOutput, MultipartUploadError<E>>;
+
+    #[allow(clippy::result_large_err)]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the task is never aborted, so it either returns or panics
+        Pin::new(&mut self.task)
+            .poll(cx)
+            .map(|output| output.unwrap_or_else(|err| panic::resume_unwind(err.into_panic())))
+    }
+}
+
+struct Tracked {
+    inner: Arc<dyn MpuClient>,
+    shared: Arc<Shared>,
+}
+
+impl MpuClient for Tracked {
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+    {
+        self.inner.create_multipart_upload(request)
+    }
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+        let content_length = request.get_content_length().unwrap_or_default();
+        Box::pin(async move {
+            // the sender lives in `shared`, so waiting cannot fail
+            let _ = self
+                .shared
+                .paused
+                .subscribe()
+                .wait_for(|paused| !paused)
+                .await;
+            self.shared.set_status(UploadStatus::Uploading);
+            let output = self.inner.upload_part(request).await;
+            if output.is_ok() {
+                self.shared.parts.fetch_add(1, Ordering::Relaxed);
+                self.shared
+                    .bytes
+                    .fetch_add(content_length as _, Ordering::Relaxed);
+            }
+            output
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>
+    {
+        self.shared.set_status(UploadStatus::Completing);
+        self.inner.complete_multipart_upload(request)
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+    {
+        self.inner.abort_multipart_upload(request)
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::{UploadProgress, UploadStatus};
+    use crate::{FakeS3, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStream;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_send_background() {
+        let fake = FakeS3::new();
+        let handle = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send_background::<anyhow::Error>(10..=10, None);
+        handle.pause();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.status(), UploadStatus::Paused);
+        assert_eq!(handle.progress(), UploadProgress::default());
+        assert!(fake
+            .requests()
+            .iter()
+            .all(|request| request.operation() != "UploadPart"));
+
+        handle.resume();
+        while handle.status() != UploadStatus::Completed {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            handle.progress(),
+            UploadProgress {
+                parts: 3,
+                bytes: 25,
+            },
+        );
+        let output = handle.await.unwrap();
+        assert_eq!(output.content_length, 25);
+        assert_eq!(fake.object("bucket", "key").unwrap(), &[0; 25][..]);
+    }
+}
@@ -0,0 +1,48 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HashOffload {
+    #[default]
+    Inline,
+    #[cfg(feature = "tokio")]
+    Tokio,
+    #[cfg(feature = "rayon")]
+    Rayon,
+}
+
+impl HashOffload {
+    pub(crate) async fn run<F, T>(self, f: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match self {
+            Self::Inline => f(),
+            #[cfg(feature = "tokio")]
+            Self::Tokio => tokio::task::spawn_blocking(f)
+                .await
+                .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic())),
+            #[cfg(feature = "rayon")]
+            Self::Rayon => {
+                let (tx, rx) = futures::channel::oneshot::channel();
+                rayon::spawn(move || {
+                    let _ = tx.send(f());
+                });
+                rx.await.unwrap()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HashOffload;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run() {
+        assert_eq!(HashOffload::Inline.run(|| 42).await, 42);
+        #[cfg(feature = "tokio")]
+        assert_eq!(HashOffload::Tokio.run(|| 42).await, 42);
+        #[cfg(feature = "rayon")]
+        assert_eq!(HashOffload::Rayon.run(|| 42).await, 42);
+    }
+}
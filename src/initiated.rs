@@ -0,0 +1,1030 @@
+use crate::part_info::Digest;
+use crate::plan::plan_parts_within;
+use crate::split::{self, Part, PartHasher};
+use crate::{
+    checksum, e_tag, into_byte_stream, manifest, Checksum, CircuitOpen, HashOffload,
+    IntegrityError, MultipartUpload, MultipartUploadError, MultipartUploadOutput, ObjectTooLarge,
+    PartError, PartInfo, PreconditionFailed, PresignedPart, RequestIds, TooManyParts, UploadedPart,
+};
+use aws_sdk_s3::config::AsyncSleep;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadError;
+use aws_sdk_s3::operation::list_parts::ListPartsError;
+use aws_sdk_s3::operation::put_object::PutObjectError;
+use aws_sdk_s3::operation::upload_part::UploadPartError;
+use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyError;
+use aws_sdk_s3::presigning::{PresigningConfig, PresigningConfigError};
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use aws_sdk_s3::types::{
+    ChecksumAlgorithm, ChecksumType, CompletedMultipartUpload, CompletedPart, CopyPartResult,
+    RequestPayer,
+};
+use futures::{Stream, StreamExt};
+use md5::Md5;
+use std::future::Future;
+use std::mem;
+use std::num::NonZeroUsize;
+use std::ops::{Range, RangeInclusive};
+use std::pin::{self, Pin};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// The wait before the first retry of `CompleteMultipartUpload`, doubled for each further one.
+const COMPLETE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A multipart upload that has been created but not completed yet.
+pub struct Initiated {
+    pub(crate) upload: MultipartUpload,
+    pub(crate) bucket: Option<String>,
+    pub(crate) key: Option<String>,
+    pub(crate) expected_bucket_owner: Option<String>,
+    pub(crate) request_payer: Option<RequestPayer>,
+    pub(crate) upload_id: Option<String>,
+    pub(crate) full_object: Option<ChecksumAlgorithm>,
+    pub(crate) parts: Vec<UploadedPart>,
+    pub(crate) next_part_number: usize,
+    pub(crate) next_offset: u64,
+    /// The hasher of [`MultipartUpload::object_digest`] and the bytes it has hashed.
+    pub(crate) object_hasher: Option<(checksum::Hasher, u64)>,
+    /// The index of the destination in use; see [`MultipartUploadOutput::destination`].
+    pub(crate) destination: usize,
+    pub(crate) started: Instant,
+}
+
+impl Initiated {
+    pub fn upload_id(&self) -> Option<&str> {
+        self.upload_id.as_deref()
+    }
+
+    /// Parts uploaded so far, in part-number order.
+    pub fn parts(&self) -> &[UploadedPart] {
+        &self.parts
+    }
+
+    pub fn abort(&self) -> AbortMultipartUploadFluentBuilder {
+        self.upload
+            .client
+            .abort_multipart_upload()
+            .set_bucket(self.bucket.clone())
+            .set_key(self.key.clone())
+            .set_upload_id(self.upload_id.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_request_payer(self.request_payer.clone())
+    }
+
+    // the upload has failed and is left to the caller to abort
+    pub(crate) fn give_up(&self) -> AbortMultipartUploadFluentBuilder {
+        if let Some(observer) = &self.upload.observer {
+            observer.on_given_up(self.upload_id.as_deref());
+        }
+        self.abort()
+    }
+
+    /// Splits `body` into parts and uploads them.
+    ///
+    /// Part numbers continue from the previous call, so every call but the last one must end
+    /// on a part boundary.
+    pub async fn upload_parts<E>(
+        &mut self,
+        mut body: ByteStream,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<(), MultipartUploadError<E>>
+    where
+        E: From<PartError<SdkError<UploadPartError>>>
+            + From<IntegrityError>
+            + From<ByteStreamError>
+            + From<ObjectTooLarge>
+            + From<TooManyParts>
+            + From<CircuitOpen>,
+    {
+        let hasher = self.hasher();
+        let parts = split::split_coalesced(
+            futures::stream::poll_fn(move |cx| Pin::new(&mut body).poll_next(cx)),
+            part_size,
+            (self.upload.hash_offload == HashOffload::Inline).then(&hasher),
+            self.upload.coalesce_chunks,
+        );
+        self.upload_split(parts, hasher, concurrency_limit).await
+    }
+
+    /// Uploads the parts of a body split by [`split::split_coalesced`] with `hasher`, which
+    /// may have been polled already.
+    pub(crate) async fn upload_split<S, H, E>(
+        &mut self,
+        parts: S,
+        hasher: H,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<(), MultipartUploadError<E>>
+    where
+        S: Stream<Item = Result<Part<Option<Digest>>, ByteStreamError>>,
+        H: Fn() -> Hasher + Clone + Send + 'static,
+        E: From<PartError<SdkError<UploadPartError>>>
+            + From<IntegrityError>
+            + From<ByteStreamError>
+            + From<ObjectTooLarge>
+            + From<TooManyParts>
+            + From<CircuitOpen>,
+    {
+        let max_object_size = self.upload.limits.max_object_size;
+        let max_parts = self.upload.limits.max_parts;
+        let hash_offload = self.upload.hash_offload;
+        let (first_part_number, first_offset) = (self.next_part_number, self.next_offset);
+        let next_part_number = AtomicUsize::new(first_part_number);
+        let next_offset = AtomicU64::new(first_offset);
+        let mut object_hasher = self.object_hasher.take();
+        let this = &*self;
+        // Holding back the next part until the budget admits this one bounds the buffered bytes.
+        #[cfg(feature = "sync")]
+        let parts = parts.then(|part| async {
+            let permit = match (&self.upload.buffer_budget, &part) {
+                (Some(buffer_budget), Ok(part)) => buffer_budget.acquire(part.content_length).await,
+                _ => None,
+            };
+            (part, permit)
+        });
+        #[cfg(not(feature = "sync"))]
+        let parts = parts.map(|part| (part, None::<()>));
+        let parts = parts.map(|(part, permit)| {
+            let hasher = hasher.clone();
+            let part = part.map(|mut part| {
+                part.part_number += first_part_number - 1;
+                part.offset += first_offset;
+                next_part_number.fetch_max(part.part_number + 1, Ordering::Relaxed);
+                next_offset.fetch_max(part.offset + part.content_length, Ordering::Relaxed);
+                // parts arrive here in order
+                if let Some((hasher, hashed)) = &mut object_hasher {
+                    if *hashed == part.offset {
+                        for chunk in &part.body {
+                            hasher.update(chunk);
+                        }
+                        *hashed += part.content_length;
+                    }
+                }
+                part
+            });
+            async move {
+                let _permit = permit;
+                let mut part = part.map_err(|err| (err.into(), RequestIds::default()))?;
+                // fails before the part is sent instead of on completion
+                if part.offset + part.content_length > max_object_size {
+                    return Err((
+                        ObjectTooLarge { max_object_size }.into(),
+                        RequestIds::default(),
+                    ));
+                }
+                // a body of unknown length runs out of parts at its part size
+                if part.part_number > max_parts {
+                    return Err((TooManyParts { max_parts }.into(), RequestIds::default()));
+                }
+                let mut part = match part.digest.take() {
+                    Some(digest) => part.with_digest(digest),
+                    None => {
+                        hash_offload
+                            .run(move || {
+                                let mut hasher = hasher();
+                                for chunk in &part.body {
+                                    hasher.update(chunk);
+                                }
+                                let digest = hasher.finalize_reset();
+                                part.with_digest(digest)
+                            })
+                            .await
+                    }
+                };
+                let body = mem::take(&mut part.body);
+                #[cfg(feature = "tokio")]
+                let (body, _staged) = match &this.upload.spill {
+                    Some(spill) => {
+                        let (body, staged) = spill
+                            .stage(body, part.content_length)
+                            .await
+                            .map_err(|err| (err.into(), RequestIds::default()))?;
+                        (body, Some(staged))
+                    }
+                    None => (into_byte_stream::into_byte_stream(body), None),
+                };
+                #[cfg(not(feature = "tokio"))]
+                let body = into_byte_stream::into_byte_stream(body);
+                this.upload_part(part, body).await
+            }
+        });
+        let (uploaded_parts, errors, circuit_open) = collect(
+            parts,
+            self.concurrency_limit(concurrency_limit),
+            self.upload.fail_fast,
+            self.upload.circuit_breaker,
+        )
+        .await;
+        self.next_part_number = next_part_number.into_inner();
+        self.next_offset = next_offset.into_inner();
+        self.object_hasher = object_hasher;
+        self.finish_parts(uploaded_parts, errors, circuit_open)
+    }
+
+    /// Uploads the file at `path` as parts, reading each part's range from disk twice (once
+    /// for hashing and once for sending) instead of buffering it.
+    ///
+    /// With the `io-uring` feature on Linux, the ranges are read with io_uring, several reads
+    /// at a time.
+    #[cfg(feature = "tokio")]
+    pub async fn upload_path<E, P>(
+        &mut self,
+        path: P,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<(), MultipartUploadError<E>>
+    where
+        E: From<PartError<SdkError<UploadPartError>>>
+            + From<IntegrityError>
+            + From<ByteStreamError>
+            + From<ObjectTooLarge>
+            + From<TooManyParts>
+            + From<CircuitOpen>,
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref();
+        let len = tokio::fs::metadata(path)
+            .await
+            .map_err(|err| MultipartUploadError::new(ByteStreamError::from(err)))?
+            .len();
+        let max_object_size = self.upload.limits.max_object_size;
+        if self.next_offset + len > max_object_size {
+            return Err(MultipartUploadError::new(ObjectTooLarge {
+                max_object_size,
+            }));
+        }
+        let max_parts = self.upload.limits.max_parts;
+        let plan = plan_parts_within(len, part_size, max_parts);
+        if self.next_part_number - 1 + plan.len() > max_parts {
+            return Err(MultipartUploadError::new(TooManyParts { max_parts }));
+        }
+
+        let hasher = self.hasher();
+        let (first_part_number, first_offset) = (self.next_part_number, self.next_offset);
+        let object_hasher = self.object_hasher.take();
+        let this = &*self;
+        let hash_object = async move {
+            let (mut hasher, hashed) =
+                object_hasher.filter(|(_, hashed)| *hashed == first_offset)?;
+            let mut body = ByteStream::read_from().path(path).build().await.ok()?;
+            while let Some(chunk) = body.next().await {
+                hasher.update(&chunk.ok()?);
+            }
+            Some((hasher, hashed + len))
+        };
+        let parts = futures::stream::iter(&plan).map(|plan| {
+            let hasher = hasher.clone();
+            let (offset, content_length) = (plan.offset, plan.len);
+            let read = move || async move {
+                #[cfg(all(feature = "io-uring", target_os = "linux"))]
+                return crate::uring::read_range(path, offset, content_length).await;
+                #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+                ByteStream::read_from()
+                    .path(path)
+                    .offset(offset)
+                    .length(aws_smithy_types::byte_stream::Length::Exact(content_length))
+                    .build()
+                    .await
+            };
+            async move {
+                let mut hasher = hasher();
+                let mut body = read()
+                    .await
+                    .map_err(|err| (err.into(), RequestIds::default()))?;
+                while let Some(chunk) = body.next().await {
+                    let chunk = chunk.map_err(|err| (err.into(), RequestIds::default()))?;
+                    hasher.update(&chunk);
+                }
+                let part = Part {
+                    body: Vec::new(),
+                    content_length,
+                    digest: hasher.finalize_reset(),
+                    offset: first_offset + offset,
+                    part_number: first_part_number - 1 + plan.part_number,
+                };
+                let body = read()
+                    .await
+                    .map_err(|err| (err.into(), RequestIds::default()))?;
+                this.upload_part(part, body).await
+            }
+        });
+        let ((uploaded_parts, errors, circuit_open), object_hasher) = futures::future::join(
+            collect(
+                parts,
+                self.concurrency_limit(concurrency_limit),
+                self.upload.fail_fast,
+                self.upload.circuit_breaker,
+            ),
+            hash_object,
+        )
+        .await;
+        self.next_part_number = first_part_number + plan.len();
+        self.next_offset = first_offset + len;
+        self.object_hasher = object_hasher;
+        self.finish_parts(uploaded_parts, errors, circuit_open)
+    }
+
+    /// Copies the first `len` bytes of `copy_source` (`bucket/key`, URL-encoded, optionally
+    /// followed by `?versionId=`) with ranged `UploadPartCopy` requests.
+    pub async fn copy_parts<E>(
+        &mut self,
+        copy_source: &str,
+        len: u64,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<(), MultipartUploadError<E>>
+    where
+        E: From<PartError<SdkError<UploadPartCopyError>>> + From<CircuitOpen>,
+    {
+        let plan = plan_parts_within(len, part_size, self.upload.limits.max_parts);
+        let (first_part_number, first_offset) = (self.next_part_number, self.next_offset);
+        let this = &*self;
+        let parts = futures::stream::iter(&plan).map(|plan| {
+            this.copy_part(
+                copy_source,
+                plan.offset..plan.offset + plan.len,
+                first_part_number - 1 + plan.part_number,
+                first_offset + plan.offset,
+            )
+        });
+        let (uploaded_parts, errors, circuit_open) = collect(
+            parts,
+            self.concurrency_limit(concurrency_limit),
+            self.upload.fail_fast,
+            self.upload.circuit_breaker,
+        )
+        .await;
+        self.next_part_number = first_part_number + plan.len();
+        self.next_offset = first_offset + len;
+        self.finish_parts(uploaded_parts, errors, circuit_open)
+    }
+
+    /// Presigns an `UploadPart` request for every part of the next `len` bytes, so that a client
+    /// without credentials can upload them.
+    ///
+    /// S3 cannot presign `CompleteMultipartUpload` or `AbortMultipartUpload`, so the holder of
+    /// the credentials finishes the upload with [`Self::list_parts`] and [`Self::complete`], or
+    /// cancels it with [`Self::abort`].
+    pub async fn presign_parts<E>(
+        &mut self,
+        len: u64,
+        part_size: RangeInclusive<u64>,
+        expires_in: Duration,
+    ) -> Result<Vec<PresignedPart>, E>
+    where
+        E: From<PresigningConfigError> + From<SdkError<UploadPartError>>,
+    {
+        let plan = plan_parts_within(len, part_size, self.upload.limits.max_parts);
+        let presigning_config = PresigningConfig::expires_in(expires_in)?;
+        let mut presigned_parts = Vec::with_capacity(plan.len());
+        for plan in &plan {
+            let number = (self.next_part_number - 1 + plan.part_number) as i32;
+            let offset = self.next_offset + plan.offset;
+            let upload_part = self
+                .upload
+                .client
+                .upload_part()
+                .set_bucket(self.bucket.clone())
+                .content_length(plan.len as _)
+                .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+                .set_request_payer(self.request_payer.clone())
+                .set_key(self.key.clone())
+                .part_number(number)
+                .set_upload_id(self.upload_id.clone());
+            let request = crate::customize(&self.upload.customize_upload_part, upload_part)
+                .presigned(presigning_config.clone())
+                .await?;
+            presigned_parts.push(PresignedPart {
+                number,
+                range: offset..offset + plan.len,
+                request,
+            });
+        }
+        self.next_part_number += plan.len();
+        self.next_offset += len;
+        Ok(presigned_parts)
+    }
+
+    /// Replaces [`Self::parts`] with the parts S3 has received for the upload, e.g. from
+    /// requests of [`Self::presign_parts`], so that [`Self::complete`] completes them.
+    ///
+    /// The byte ranges assume that the parts are contiguous.
+    pub async fn list_parts<E>(&mut self) -> Result<(), MultipartUploadError<E>>
+    where
+        E: From<SdkError<ListPartsError>>,
+    {
+        let mut parts = self
+            .upload
+            .client
+            .list_parts()
+            .set_bucket(self.bucket.clone())
+            .set_key(self.key.clone())
+            .set_upload_id(self.upload_id.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_request_payer(self.request_payer.clone())
+            .into_paginator()
+            .items()
+            .send()
+            .try_collect()
+            .await
+            .map_err(|err| {
+                let request_ids = RequestIds::new(&err);
+                MultipartUploadError::new(err).request_ids(request_ids)
+            })?;
+        parts.sort_by_key(|part| part.part_number);
+
+        let mut offset = 0;
+        self.parts = parts
+            .into_iter()
+            .map(|part| {
+                let number = part.part_number.unwrap_or_default();
+                let len = part.size.unwrap_or_default() as u64;
+                let checksum = Checksum::from_part(&part);
+                let completed_part = CompletedPart::builder()
+                    .set_e_tag(part.e_tag)
+                    .part_number(number);
+                let completed_part = match &checksum {
+                    Some(checksum) => checksum.set_completed_part(completed_part),
+                    None => completed_part,
+                }
+                .build();
+                offset += len;
+                UploadedPart {
+                    info: PartInfo {
+                        number,
+                        len,
+                        content_md5: None,
+                        checksum,
+                        digests: Vec::new(),
+                        range: offset - len..offset,
+                    },
+                    completed_part,
+                    duration: Duration::ZERO,
+                    attempts: 0,
+                }
+            })
+            .collect();
+        if let Some(uploaded_part) = self.parts.last() {
+            self.next_part_number = uploaded_part.info.number as usize + 1;
+        }
+        self.next_offset = offset;
+        Ok(())
+    }
+
+    fn hasher(&self) -> impl Fn() -> Hasher + Clone + Send + Sync + 'static {
+        hasher(&self.upload)
+    }
+
+    fn concurrency_limit(&self, inp: Option<NonZeroUsize>) -> Option<NonZeroUsize> {
+        if self.upload.sequential {
+            Some(NonZeroUsize::MIN)
+        } else {
+            inp
+        }
+    }
+
+    async fn upload_part<E>(
+        &self,
+        part: Part<Digest>,
+        body: ByteStream,
+    ) -> Result<UploadedPart, (E, RequestIds)>
+    where
+        E: From<PartError<SdkError<UploadPartError>>> + From<IntegrityError>,
+    {
+        #[cfg(feature = "sync")]
+        let _permit = match &self.upload.semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        if let Some(rate_limiter) = &self.upload.rate_limiter {
+            let wait = rate_limiter.reserve(part.content_length as _);
+            if !wait.is_zero() {
+                // `validate` rejects a rate limiter without a sleep
+                if let Some(sleep_impl) = crate::sleep_impl(&self.upload.client) {
+                    sleep_impl.sleep(wait).await;
+                }
+            }
+        }
+
+        let part_info = PartInfo::from(&part);
+        if let Some(observer) = &self.upload.observer {
+            observer.on_part_started(&part_info);
+        }
+        let upload_part = self.upload.client.upload_part();
+        let upload_part = match &part.digest.1 {
+            Some(checksum) => checksum.set_upload_part(upload_part),
+            None => upload_part,
+        };
+        let start = Instant::now();
+        let upload_part = upload_part
+            .body(body)
+            .set_bucket(self.bucket.clone())
+            .content_length(part.content_length as _)
+            .set_content_md5(
+                part.digest
+                    .0
+                    .filter(|_| self.upload.send_content_md5)
+                    .map(base64::encode),
+            )
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_request_payer(self.request_payer.clone())
+            .set_key(self.key.clone())
+            .part_number(part.part_number as _)
+            .set_upload_id(self.upload_id.clone());
+        let output = instrument!(
+            self.upload.mpu_client.upload_part(crate::customize(
+                &self.upload.customize_upload_part,
+                upload_part,
+            )),
+            "upload_part",
+            bucket = self.bucket.as_deref(),
+            key = self.key.as_deref(),
+            upload_id = self.upload_id.as_deref(),
+            part_number = part.part_number,
+            bytes = part.content_length,
+        )
+        .await;
+        let attempts = self
+            .upload
+            .attempts
+            .take(self.upload_id.as_deref(), part.part_number);
+        let output = output.map_err(|err| {
+            let request_ids = RequestIds::new(&err);
+            (
+                PartError {
+                    part_number: part_info.number,
+                    range: part_info.range.clone(),
+                    attempts,
+                    request_ids: request_ids.clone(),
+                    source: err,
+                }
+                .into(),
+                request_ids,
+            )
+        })?;
+        if let Some(verify_part) = &self.upload.verify_part {
+            verify_part(&part_info, &output).await.map_err(|source| {
+                (
+                    IntegrityError::PartRejected {
+                        part_number: part_info.number,
+                        source,
+                    }
+                    .into(),
+                    RequestIds::new(&output),
+                )
+            })?;
+        }
+
+        let completed_part = CompletedPart::builder()
+            .set_e_tag(output.e_tag)
+            .part_number(part.part_number as _);
+        let completed_part = match &part.digest.1 {
+            Some(checksum) => checksum.set_completed_part(completed_part),
+            None => completed_part,
+        }
+        .build();
+        let uploaded_part = UploadedPart {
+            info: part_info,
+            completed_part,
+            duration: start.elapsed(),
+            attempts,
+        };
+        self.report(&uploaded_part);
+        Ok(uploaded_part)
+    }
+
+    async fn copy_part<E>(
+        &self,
+        copy_source: &str,
+        range: Range<u64>,
+        part_number: usize,
+        offset: u64,
+    ) -> Result<UploadedPart, (E, RequestIds)>
+    where
+        E: From<PartError<SdkError<UploadPartCopyError>>>,
+    {
+        #[cfg(feature = "sync")]
+        let _permit = match &self.upload.semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+
+        let part_info = PartInfo {
+            number: part_number as _,
+            len: range.end - range.start,
+            content_md5: None,
+            checksum: None,
+            digests: Vec::new(),
+            range: offset..offset + range.end - range.start,
+        };
+        if let Some(observer) = &self.upload.observer {
+            observer.on_part_started(&part_info);
+        }
+        let start = Instant::now();
+        let upload_part_copy = self
+            .upload
+            .client
+            .upload_part_copy()
+            .set_bucket(self.bucket.clone())
+            .copy_source(copy_source)
+            .copy_source_range(format!("bytes={}-{}", range.start, range.end - 1))
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_request_payer(self.request_payer.clone())
+            .set_key(self.key.clone())
+            .part_number(part_info.number)
+            .set_upload_id(self.upload_id.clone());
+        let output = instrument!(
+            upload_part_copy.send(),
+            "upload_part_copy",
+            bucket = self.bucket.as_deref(),
+            key = self.key.as_deref(),
+            upload_id = self.upload_id.as_deref(),
+            part_number = part_info.number,
+            bytes = part_info.len,
+        )
+        .await;
+        let attempts = self
+            .upload
+            .attempts
+            .take(self.upload_id.as_deref(), part_number);
+        let output = output.map_err(|err| {
+            let request_ids = RequestIds::new(&err);
+            (
+                PartError {
+                    part_number: part_info.number,
+                    range: part_info.range.clone(),
+                    attempts,
+                    request_ids: request_ids.clone(),
+                    source: err,
+                }
+                .into(),
+                request_ids,
+            )
+        })?;
+
+        let result = output
+            .copy_part_result
+            .unwrap_or_else(|| CopyPartResult::builder().build());
+        let checksum = self
+            .upload
+            .create
+            .get_checksum_algorithm()
+            .as_ref()
+            .and_then(|checksum_algorithm| {
+                Checksum::from_copy_part_result(checksum_algorithm, &result)
+            });
+        let completed_part = CompletedPart::builder()
+            .set_e_tag(result.e_tag)
+            .part_number(part_info.number);
+        let completed_part = match &checksum {
+            Some(checksum) => checksum.set_completed_part(completed_part),
+            None => completed_part,
+        }
+        .build();
+        let uploaded_part = UploadedPart {
+            info: PartInfo {
+                checksum,
+                ..part_info
+            },
+            completed_part,
+            duration: start.elapsed(),
+            attempts,
+        };
+        self.report(&uploaded_part);
+        Ok(uploaded_part)
+    }
+
+    fn report(&self, uploaded_part: &UploadedPart) {
+        if let Some(observer) = &self.upload.observer {
+            observer.on_part_completed(uploaded_part);
+        }
+        if let Some(part_tx) = &self.upload.part_tx {
+            // the receiver may have been dropped with the stream
+            let _ = part_tx.unbounded_send(uploaded_part.clone());
+        }
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn finish_parts<E>(
+        &mut self,
+        mut uploaded_parts: Vec<UploadedPart>,
+        errors: Vec<(E, RequestIds)>,
+        circuit_open: Option<CircuitOpen>,
+    ) -> Result<(), MultipartUploadError<E>>
+    where
+        E: From<CircuitOpen>,
+    {
+        uploaded_parts.sort_by_key(|uploaded_part| uploaded_part.info.number);
+        self.parts.extend_from_slice(&uploaded_parts);
+        self.parts
+            .sort_by_key(|uploaded_part| uploaded_part.info.number);
+
+        if let Some(circuit_open) = circuit_open {
+            let request_ids = errors
+                .last()
+                .map(|(_, request_ids)| request_ids.clone())
+                .unwrap_or_default();
+            let mut err = MultipartUploadError::new(circuit_open)
+                .abort(&self.upload_id, self.give_up())
+                .request_ids(request_ids);
+            err.additional_errors = errors.into_iter().map(|(err, _)| err).collect();
+            err.uploaded_parts = uploaded_parts;
+            return Err(err);
+        }
+        let mut errors = errors.into_iter();
+        if let Some((err, request_ids)) = errors.next() {
+            let mut err = MultipartUploadError::new(err)
+                .abort(&self.upload_id, self.give_up())
+                .request_ids(request_ids);
+            err.additional_errors = errors.map(|(err, _)| err).collect();
+            err.uploaded_parts = uploaded_parts;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    pub async fn complete<E>(mut self) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
+    where
+        E: From<SdkError<CompleteMultipartUploadError>>
+            + From<SdkError<PutObjectError>>
+            + From<PreconditionFailed>
+            + From<IntegrityError>,
+    {
+        let content_length = self
+            .parts
+            .iter()
+            .map(|uploaded_part| uploaded_part.info.len)
+            .sum();
+        // parts copied, listed or uploaded elsewhere leave the digest incomplete
+        let object_digest = self.upload.known_object_digest.take().or_else(|| {
+            self.object_hasher
+                .take()
+                .filter(|(_, hashed)| *hashed == content_length)
+                .map(|(mut hasher, _)| hasher.finalize_reset())
+        });
+
+        let complete_multipart_upload = self.upload.client.complete_multipart_upload();
+        let complete_multipart_upload = match &self.full_object {
+            Some(checksum_algorithm) => {
+                // parts listed or attached from elsewhere may lack a CRC to combine
+                let crcs = self
+                    .parts
+                    .iter()
+                    .map(|uploaded_part| {
+                        let info = &uploaded_part.info;
+                        info.checksum
+                            .as_ref()
+                            .and_then(|checksum| checksum.crc(checksum_algorithm))
+                            .map(|crc| (crc, info.len))
+                            .ok_or(IntegrityError::InvalidPartChecksum {
+                                part_number: info.number,
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+                let crcs = match crcs {
+                    Ok(crcs) => crcs,
+                    Err(err) => {
+                        return Err(
+                            MultipartUploadError::new(err).abort(&self.upload_id, self.give_up())
+                        )
+                    }
+                };
+                match Checksum::combine(checksum_algorithm, crcs) {
+                    Some(checksum) => checksum
+                        .set_complete(complete_multipart_upload)
+                        .checksum_type(ChecksumType::FullObject)
+                        .mpu_object_size(content_length as _),
+                    // `validate` only admits the algorithms that combine
+                    None => complete_multipart_upload,
+                }
+            }
+            None => complete_multipart_upload,
+        };
+        // the ETag of S3 covers every part, so a part without an MD5 leaves nothing to compare
+        let e_tag = self
+            .upload
+            .verify_e_tag
+            .then(|| {
+                self.parts
+                    .iter()
+                    .map(|uploaded_part| {
+                        base64::decode(uploaded_part.info.content_md5.as_ref()?).ok()
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .flatten()
+            .map(e_tag::composite);
+        let complete_multipart_upload = complete_multipart_upload
+            .set_bucket(self.bucket.clone())
+            .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+            .set_request_payer(self.request_payer.clone())
+            .set_key(self.key.clone())
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(
+                        self.parts
+                            .iter()
+                            .map(|uploaded_part| uploaded_part.completed_part.clone())
+                            .collect(),
+                    ))
+                    .build(),
+            )
+            .set_upload_id(self.upload_id.clone())
+            .set_if_none_match(self.upload.if_none_match.clone())
+            .set_if_match(self.upload.if_match.clone());
+        let complete_multipart_upload =
+            crate::customize(&self.upload.customize_complete, complete_multipart_upload);
+        let sleep_impl = crate::sleep_impl(&self.upload.client);
+        // retries back off with a sleep, or are not made without one
+        let complete_retries = if sleep_impl.is_some() {
+            self.upload.complete_retries
+        } else {
+            0
+        };
+        let mut retries = 0;
+        let mut backoff = COMPLETE_BACKOFF;
+        let output = loop {
+            match instrument!(
+                self.upload
+                    .mpu_client
+                    .complete_multipart_upload(complete_multipart_upload.clone()),
+                "complete_multipart_upload",
+                bucket = self.bucket.as_deref(),
+                key = self.key.as_deref(),
+                upload_id = self.upload_id.as_deref(),
+                parts = self.parts.len(),
+                bytes = content_length,
+            )
+            .await
+            {
+                Ok(output) => break output,
+                Err(err) if retries < complete_retries && is_error_in_200(&err) => {
+                    if let Some(sleep_impl) = &sleep_impl {
+                        sleep_impl.sleep(backoff).await;
+                    }
+                    backoff *= 2;
+                    retries += 1;
+                }
+                Err(err) => {
+                    let request_ids = RequestIds::new(&err);
+                    let err: E = if err.code() == Some("PreconditionFailed") {
+                        PreconditionFailed(err).into()
+                    } else {
+                        err.into()
+                    };
+                    return Err(MultipartUploadError::new(err)
+                        .abort(&self.upload_id, self.give_up())
+                        .request_ids(request_ids));
+                }
+            }
+        };
+
+        if let Some(e_tag) = e_tag {
+            if output.e_tag.as_ref() != Some(&e_tag) {
+                return Err(MultipartUploadError {
+                    error: IntegrityError::ETagMismatch {
+                        expected: e_tag,
+                        actual: output.e_tag,
+                    }
+                    .into(),
+                    upload_id: self.upload_id,
+                    abort: None,
+                    request_ids: RequestIds::default(),
+                    additional_errors: Vec::new(),
+                    uploaded_parts: Vec::new(),
+                });
+            }
+        }
+        let output = MultipartUploadOutput {
+            upload_id: self.upload_id,
+            content_length,
+            parts: self.parts,
+            output,
+            object_digest,
+            destination: self.destination,
+            duration: self.started.elapsed(),
+        };
+        if self.upload.manifest {
+            let key = self.key.as_deref().unwrap_or_default();
+            let body = manifest::manifest(self.bucket.as_deref().unwrap_or_default(), key, &output);
+            self.upload
+                .client
+                .put_object()
+                .set_bucket(self.bucket.clone())
+                .key(format!("{key}.manifest"))
+                .content_type("application/json")
+                .set_expected_bucket_owner(self.expected_bucket_owner.clone())
+                .set_request_payer(self.request_payer.clone())
+                .body(ByteStream::from(body.into_bytes()))
+                .send()
+                .await
+                .map_err(|err| {
+                    let request_ids = RequestIds::new(&err);
+                    MultipartUploadError::new(err).request_ids(request_ids)
+                })?;
+        }
+        if let Some(observer) = &self.upload.observer {
+            observer.on_completed(&output);
+        }
+        Ok(output)
+    }
+}
+
+pub(crate) type Hasher = (Option<Md5>, Option<checksum::Hasher>, Vec<checksum::Hasher>);
+
+pub(crate) fn hasher(
+    upload: &MultipartUpload,
+) -> impl Fn() -> Hasher + Clone + Send + Sync + 'static {
+    let content_md5 = upload.content_md5;
+    let checksum_algorithm = upload.create.get_checksum_algorithm().clone();
+    let digests = upload.digests.clone();
+    move || {
+        (
+            content_md5.then(Md5::default),
+            checksum_algorithm.as_ref().and_then(checksum::Hasher::new),
+            digests
+                .iter()
+                .filter_map(checksum::Hasher::new)
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+// Runs the part uploads. Without `circuit_breaker`, no new part is started once a part fails;
+// with `fail_fast`, the parts in flight are dropped as well. With it, parts keep being started
+// past isolated failures until `circuit_breaker` parts in a row have failed, and then the parts
+// in flight are dropped. With a limit of one, parts run strictly in order.
+pub(crate) async fn collect<S, F, E>(
+    parts: S,
+    concurrency_limit: Option<NonZeroUsize>,
+    fail_fast: bool,
+    circuit_breaker: Option<NonZeroUsize>,
+) -> (Vec<UploadedPart>, Vec<(E, RequestIds)>, Option<CircuitOpen>)
+where
+    S: Stream<Item = F>,
+    F: Future<Output = Result<UploadedPart, (E, RequestIds)>>,
+{
+    let stop = AtomicBool::new(false);
+    let consecutive_failures = AtomicUsize::new(0);
+    let mut parts = pin::pin!(parts);
+    let parts = futures::stream::poll_fn(|cx| {
+        let circuit_open = circuit_breaker
+            .is_some_and(|n| consecutive_failures.load(Ordering::Relaxed) >= n.get());
+        if stop.load(Ordering::Relaxed) || circuit_open {
+            Poll::Ready(None)
+        } else {
+            parts.poll_next_unpin(cx)
+        }
+    });
+    let results = match concurrency_limit {
+        // the next part is not even read until the previous one is uploaded
+        Some(concurrency_limit) if concurrency_limit.get() == 1 => {
+            parts.then(|part| part).left_stream()
+        }
+        _ => parts
+            .buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+            .right_stream(),
+    };
+    let mut results = pin::pin!(results);
+
+    let mut uploaded_parts = Vec::new();
+    let mut errors = Vec::new();
+    while let Some(result) = results.next().await {
+        match result {
+            Ok(uploaded_part) => {
+                uploaded_parts.push(uploaded_part);
+                consecutive_failures.store(0, Ordering::Relaxed);
+            }
+            Err(err) => {
+                errors.push(err);
+                let failures = consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                match circuit_breaker {
+                    Some(n) if failures >= n.get() => {
+                        return (
+                            uploaded_parts,
+                            errors,
+                            Some(CircuitOpen {
+                                consecutive_failures: failures,
+                            }),
+                        );
+                    }
+                    Some(_) => (),
+                    None if fail_fast => break,
+                    None => stop.store(true, Ordering::Relaxed),
+                }
+            }
+        }
+    }
+    (uploaded_parts, errors, None)
+}
+
+// S3 may return 200 OK for CompleteMultipartUpload and put the error in the body.
+fn is_error_in_200<E>(err: &SdkError<E>) -> bool {
+    matches!(err, SdkError::ServiceError(_))
+        && err
+            .raw_response()
+            .is_some_and(|response| response.status().as_u16() == 200)
+}
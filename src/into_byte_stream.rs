@@ -1,11 +1,12 @@
 use aws_sdk_s3::primitives::{ByteStream, SdkBody};
 use aws_smithy_types::body::Error;
 use bytes::Bytes;
+use futures::Stream;
 use http::header::HeaderMap;
 use http_body::combinators::BoxBody;
 use http_body::Body;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::task::{Context, Poll};
 
 pub fn into_byte_stream(body: Vec<Bytes>) -> ByteStream {
@@ -39,11 +40,108 @@ pub fn into_byte_stream(body: Vec<Bytes>) -> ByteStream {
     }))
 }
 
+pub fn from_stream<S, E>(body: S) -> ByteStream
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Into<Error>,
+{
+    // `SdkBody` requires `Sync`. The mutex makes any `Send` stream `Sync` and is only
+    // accessed through `&mut`, so it is never locked.
+    struct B<S>(Mutex<Pin<Box<S>>>);
+
+    impl<S, E> Body for B<S>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: Into<Error>,
+    {
+        type Data = Bytes;
+        type Error = Error;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            self.get_mut()
+                .0
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+                .poll_next(cx)
+                .map(|data| data.map(|data| data.map_err(Into::into)))
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(None))
+        }
+    }
+
+    ByteStream::new(SdkBody::from_body_0_4(B(Mutex::new(Box::pin(body)))))
+}
+
+pub fn from_body_0_4<B>(body: B) -> ByteStream
+where
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<Error>,
+{
+    from_stream(futures::stream::unfold(Box::pin(body), |mut body| async {
+        let data = body.data().await?;
+        Some((data, body))
+    }))
+}
+
+#[cfg(feature = "http-body-1")]
+pub fn from_body_1<B>(body: B) -> ByteStream
+where
+    B: http_body_1::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<Error>,
+{
+    from_stream(http_body_util::BodyDataStream::new(body))
+}
+
+/// Reads `reader` on a blocking thread, keeping at most `buffers` chunks of `capacity` bytes
+/// in flight.
+#[cfg(feature = "tokio")]
+pub fn from_sync_reader<R>(mut reader: R, capacity: usize, buffers: usize) -> ByteStream
+where
+    R: std::io::Read + Send + 'static,
+{
+    use futures::{SinkExt, StreamExt};
+    use std::io;
+
+    from_stream(
+        futures::stream::once(async move {
+            let (mut tx, rx) = futures::channel::mpsc::channel(buffers);
+            tokio::task::spawn_blocking(move || loop {
+                let mut buf = bytes::BytesMut::zeroed(capacity);
+                let item = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => {
+                        buf.truncate(len);
+                        Ok(buf.freeze())
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+                let is_err = item.is_err();
+                if futures::executor::block_on(tx.send(item)).is_err() || is_err {
+                    break;
+                }
+            });
+            rx
+        })
+        .flatten(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use super::into_byte_stream;
+    use super::{from_stream, into_byte_stream};
     use aws_sdk_s3::primitives::ByteStream;
     use bytes::Bytes;
+    use std::io;
 
     #[tokio::test]
     async fn test_into_byte_stream() {
@@ -65,4 +163,49 @@ mod tests {
             Bytes::from_static(&[0, 1, 2, 3, 4])
         );
     }
+
+    #[tokio::test]
+    async fn test_from_stream() {
+        let body = from_stream(futures::stream::iter([
+            Ok::<_, io::Error>(Bytes::from_static(&[0, 1, 2])),
+            Ok(Bytes::from_static(&[3, 4])),
+        ]));
+        assert_eq!(
+            body.collect().await.unwrap().into_bytes(),
+            Bytes::from_static(&[0, 1, 2, 3, 4])
+        );
+
+        let body = from_stream(futures::stream::iter([
+            Ok(Bytes::from_static(&[0, 1, 2])),
+            Err(io::Error::other("error")),
+        ]));
+        assert!(body.collect().await.is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_from_sync_reader() {
+        let data = (0..=255).cycle().take(10000).collect::<Vec<u8>>();
+        let body = super::from_sync_reader(io::Cursor::new(data.clone()), 1000, 2);
+        assert_eq!(body.collect().await.unwrap().into_bytes(), data);
+    }
+
+    #[tokio::test]
+    async fn test_from_body_0_4() {
+        let body = super::from_body_0_4(http_body::Full::new(Bytes::from_static(&[0, 1, 2])));
+        assert_eq!(
+            body.collect().await.unwrap().into_bytes(),
+            Bytes::from_static(&[0, 1, 2])
+        );
+    }
+
+    #[cfg(feature = "http-body-1")]
+    #[tokio::test]
+    async fn test_from_body_1() {
+        let body = super::from_body_1(http_body_util::Full::new(Bytes::from_static(&[0, 1, 2])));
+        assert_eq!(
+            body.collect().await.unwrap().into_bytes(),
+            Bytes::from_static(&[0, 1, 2])
+        );
+    }
 }
@@ -0,0 +1,203 @@
+use crate::Checksum;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+/// Renders a JSON object one field at a time, as [`MultipartUploadOutput::to_json`] and the
+/// manifest do, e.g. for other reports of a program that prints those.
+///
+/// [`MultipartUploadOutput::to_json`]: crate::MultipartUploadOutput::to_json
+#[derive(Clone, Debug)]
+pub struct JsonObject(String);
+
+impl JsonObject {
+    pub fn new() -> Self {
+        Self("{".to_owned())
+    }
+
+    pub fn field<T>(mut self, name: &str, value: T) -> Self
+    where
+        T: JsonValue,
+    {
+        if self.0.len() > 1 {
+            self.0.push(',');
+        }
+        name.write_json(&mut self.0);
+        self.0.push(':');
+        value.write_json(&mut self.0);
+        self
+    }
+}
+
+impl Default for JsonObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for JsonObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}}}", self.0)
+    }
+}
+
+/// A value of a [`JsonObject`] field. `None` is `null`, a [`Duration`] is in seconds and a
+/// [`Checksum`] is `{"algorithm":...,"value":...}`.
+pub trait JsonValue {
+    fn write_json(&self, json: &mut String);
+}
+
+impl<T> JsonValue for &T
+where
+    T: JsonValue + ?Sized,
+{
+    fn write_json(&self, json: &mut String) {
+        (**self).write_json(json);
+    }
+}
+
+impl JsonValue for str {
+    fn write_json(&self, json: &mut String) {
+        json.reserve(self.len() + 2);
+        json.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+                c => json.push(c),
+            }
+        }
+        json.push('"');
+    }
+}
+
+impl JsonValue for String {
+    fn write_json(&self, json: &mut String) {
+        self.as_str().write_json(json);
+    }
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonValue for $t {
+                fn write_json(&self, json: &mut String) {
+                    write!(json, "{self}").unwrap();
+                }
+            }
+        )*
+    };
+}
+
+integer!(i32, i64, u64, usize);
+
+/// `null` for NaN and infinities, which JSON cannot represent.
+impl JsonValue for f64 {
+    fn write_json(&self, json: &mut String) {
+        if self.is_finite() {
+            write!(json, "{self}").unwrap();
+        } else {
+            json.push_str("null");
+        }
+    }
+}
+
+impl JsonValue for Duration {
+    fn write_json(&self, json: &mut String) {
+        self.as_secs_f64().write_json(json);
+    }
+}
+
+impl<T> JsonValue for Option<T>
+where
+    T: JsonValue,
+{
+    fn write_json(&self, json: &mut String) {
+        match self {
+            Some(value) => value.write_json(json),
+            None => json.push_str("null"),
+        }
+    }
+}
+
+impl<T> JsonValue for [T]
+where
+    T: JsonValue,
+{
+    fn write_json(&self, json: &mut String) {
+        json.push('[');
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            value.write_json(json);
+        }
+        json.push(']');
+    }
+}
+
+impl<T> JsonValue for Vec<T>
+where
+    T: JsonValue,
+{
+    fn write_json(&self, json: &mut String) {
+        self.as_slice().write_json(json);
+    }
+}
+
+impl JsonValue for JsonObject {
+    fn write_json(&self, json: &mut String) {
+        write!(json, "{self}").unwrap();
+    }
+}
+
+impl JsonValue for Checksum {
+    fn write_json(&self, json: &mut String) {
+        JsonObject::new()
+            .field("algorithm", self.algorithm().as_str())
+            .field("value", self.value())
+            .write_json(json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonObject, JsonValue};
+    use std::time::Duration;
+
+    fn render<T>(value: T) -> String
+    where
+        T: JsonValue,
+    {
+        let mut json = String::new();
+        value.write_json(&mut json);
+        json
+    }
+
+    #[test]
+    fn test_string() {
+        assert_eq!(render(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(render("a\nb"), r#""a\u000ab""#);
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(render(1.5), "1.5");
+        assert_eq!(render(f64::INFINITY), "null");
+        assert_eq!(render(f64::NAN), "null");
+        assert_eq!(render(Duration::from_millis(1500)), "1.5");
+    }
+
+    #[test]
+    fn test_object() {
+        assert_eq!(JsonObject::new().to_string(), "{}");
+        assert_eq!(
+            JsonObject::new()
+                .field("a", 1_u64)
+                .field("b", None::<&str>)
+                .field("c", vec![JsonObject::new().field("d", "e")])
+                .to_string(),
+            r#"{"a":1,"b":null,"c":[{"d":"e"}]}"#
+        );
+    }
+}
@@ -1,40 +1,306 @@
+// runs a request future in a span named after the S3 operation, or as is without `tracing`
+#[cfg(feature = "tracing")]
+macro_rules! instrument {
+    ($future:expr, $name:literal, $($fields:tt)*) => {
+        tracing::Instrument::instrument($future, tracing::info_span!($name, $($fields)*))
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! instrument {
+    ($future:expr, $($_:tt)*) => {
+        $future
+    };
+}
+
+mod abort;
+mod append;
+mod arn;
+mod attempts;
+#[cfg(feature = "tokio")]
+mod audit;
+#[cfg(feature = "azure")]
+mod azure;
+#[cfg(any(feature = "object_store", feature = "opendal"))]
+mod background;
+#[cfg(feature = "tokio")]
+mod batch;
+mod checksum;
+#[cfg(feature = "compression")]
+mod compress;
+#[cfg(feature = "copy")]
+mod copy;
+mod copy_source;
+#[cfg(feature = "tokio")]
+mod dir;
+#[cfg(feature = "download")]
+mod download;
+mod e_tag;
+mod error;
+mod failover;
+#[cfg(feature = "test-util")]
+mod fake;
+#[cfg(feature = "test-util")]
+mod fault;
+#[cfg(feature = "multer")]
+mod form_data;
+#[cfg(feature = "tokio")]
+mod handle;
+mod hash_offload;
+mod initiated;
 mod into_byte_stream;
+mod json;
+mod limits;
+#[cfg(feature = "sync")]
+mod manager;
+mod manifest;
+#[cfg(feature = "opentelemetry")]
+mod metrics;
+mod mpu_client;
+#[cfg(feature = "object_store")]
+mod object_store_upload;
+mod observer;
+#[cfg(feature = "opendal")]
+mod opendal_writer;
+mod output;
+mod part_info;
+mod part_sink;
+mod plan;
+#[cfg(feature = "hyper")]
+mod presigned;
+#[cfg(feature = "indicatif")]
+mod progress;
+mod rate_limiter;
+mod sink;
+#[cfg(feature = "tokio")]
+mod skip;
+#[cfg(feature = "tokio")]
+mod spill;
 mod split;
+mod streaming;
+#[cfg(feature = "sync")]
+mod tar;
+mod tee;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+#[cfg(feature = "tokio")]
+mod writer;
 
-use aws_sdk_s3::error::SdkError;
-use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
-use aws_sdk_s3::operation::complete_multipart_upload::{
-    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+pub use abort::{abort_incomplete_uploads, abort_verified};
+pub use append::AppendOutput;
+#[cfg(feature = "tokio")]
+pub use audit::audit;
+#[cfg(feature = "azure")]
+pub use azure::AzureBlockBlob;
+#[cfg(feature = "tokio")]
+pub use batch::{upload_batch, Source};
+pub use checksum::Checksum;
+#[cfg(feature = "compression")]
+pub use compress::Codec;
+#[cfg(feature = "copy")]
+pub use copy::MultipartCopy;
+#[cfg(feature = "tokio")]
+pub use dir::{sync, upload_dir, ObjectOutput, SyncOutput};
+#[cfg(feature = "download")]
+pub use download::{MultipartDownload, MultipartDownloadOutput};
+pub use e_tag::{predict_e_tag, ETagHasher};
+#[cfg(feature = "azure")]
+pub use error::AzureError;
+#[cfg(feature = "hyper")]
+pub use error::PresignedError;
+pub use error::{
+    AbortError, CircuitOpen, IntegrityError, InvalidPartSize, MultipartUploadError, ObjectTooLarge,
+    PartError, PreconditionFailed, RequestIds, TarError, TooManyParts, UploadErrorKind,
 };
+#[cfg(feature = "test-util")]
+pub use fake::{FakeS3, RecordedRequest};
+#[cfg(feature = "test-util")]
+pub use fault::{Fault, FaultInjector};
+#[cfg(feature = "multer")]
+pub use form_data::upload_field;
+#[cfg(feature = "tokio")]
+pub use handle::{UploadHandle, UploadProgress, UploadStatus};
+pub use hash_offload::HashOffload;
+pub use initiated::Initiated;
+pub use json::{JsonObject, JsonValue};
+pub use limits::ProviderLimits;
+#[cfg(feature = "sync")]
+pub use manager::UploadManager;
+#[cfg(feature = "opentelemetry")]
+pub use metrics::Metered;
+pub use mpu_client::MpuClient;
+#[cfg(feature = "object_store")]
+pub use object_store_upload::ObjectStoreUpload;
+pub use observer::MpuObserver;
+#[cfg(feature = "opendal")]
+pub use opendal_writer::OpendalWriter;
+pub use output::{MultipartUploadOutput, PresignedPart, UploadStats, UploadedPart};
+pub use part_info::PartInfo;
+pub use part_sink::{upload_to_sink, PartSink};
+pub use plan::{plan_parts, PartPlan};
+#[cfg(feature = "hyper")]
+pub use presigned::upload_presigned;
+#[cfg(feature = "indicatif")]
+pub use progress::Progress;
+pub use rate_limiter::RateLimiter;
+pub use sink::S3Sink;
+#[cfg(feature = "tokio")]
+pub use skip::SendOutput;
+pub use split::{split, split_coalesced, Part, PartHasher};
+pub use streaming::UploadEvent;
+#[cfg(feature = "sync")]
+pub use tar::upload_tar;
+pub use tee::upload_tee;
+#[cfg(feature = "tokio")]
+pub use writer::S3Writer;
+
+use aws_sdk_s3::config::{RequestChecksumCalculation, SharedAsyncSleep};
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
 use aws_sdk_s3::operation::create_multipart_upload::CreateMultipartUploadError;
-use aws_sdk_s3::operation::upload_part::UploadPartError;
-use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
-use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::UploadPartOutput;
+use aws_sdk_s3::operation::upload_part_copy::UploadPartCopyError;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use aws_sdk_s3::types::{ChecksumAlgorithm, ChecksumType, RequestPayer};
 use aws_sdk_s3::Client;
-use futures::{TryFutureExt, TryStreamExt};
+use aws_smithy_types::error::operation::BuildError;
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::future::{self, BoxFuture, Either};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
+use std::error::Error;
+use std::future::Future;
+use std::mem;
 use std::num::NonZeroUsize;
 use std::ops::RangeInclusive;
-use std::pin::Pin;
+use std::time::Instant;
 
 // https://docs.aws.amazon.com/AmazonS3/latest/userguide/qfacts.html
-pub const PART_SIZE: RangeInclusive<usize> = 5 << 20..=5 << 30;
+pub const PART_SIZE: RangeInclusive<u64> = 5 << 20..=5 << 30;
+pub const MAX_PARTS: usize = 10000;
+pub const MAX_OBJECT_SIZE: u64 = 5 << 40;
+
+#[cfg(feature = "tokio")]
+const READER_CAPACITY: usize = 1 << 20;
+#[cfg(feature = "tokio")]
+const SYNC_READER_BUFFERS: usize = 4;
+
+const CHANNEL_BUFFER: usize = 4;
+
+type Customize<T> = Option<Box<dyn Fn(T) -> T + Send + Sync>>;
+type VerifyPart = Box<
+    dyn Fn(
+            &PartInfo,
+            &UploadPartOutput,
+        ) -> BoxFuture<'static, Result<(), Box<dyn Error + Send + Sync>>>
+        + Send
+        + Sync,
+>;
 
 pub struct MultipartUpload {
     client: Client,
+    // installed on `client` and the clients of `failover`
+    attempts: attempts::Attempts,
+    mpu_client: std::sync::Arc<dyn MpuClient>,
     body: ByteStream,
-    bucket: Option<String>,
-    key: Option<String>,
+    #[cfg(feature = "tokio")]
+    path: Option<std::path::PathBuf>,
+    create: CreateMultipartUploadFluentBuilder,
+    if_none_match: Option<String>,
+    if_match: Option<String>,
+    content_md5: bool,
+    send_content_md5: bool,
+    verify_e_tag: bool,
+    digests: Vec<ChecksumAlgorithm>,
+    object_digest: Option<ChecksumAlgorithm>,
+    object_digest_metadata: Option<String>,
+    // computed before `CreateMultipartUpload` for `object_digest_metadata`
+    known_object_digest: Option<Checksum>,
+    manifest: bool,
+    #[cfg(feature = "compression")]
+    compress: Option<(Codec, i32)>,
+    hash_offload: HashOffload,
+    fail_fast: bool,
+    circuit_breaker: Option<NonZeroUsize>,
+    sequential: bool,
+    gcs_compat: bool,
+    complete_retries: usize,
+    limits: ProviderLimits,
+    size_hint: Option<u64>,
+    failover: Vec<(Client, String)>,
+    coalesce_chunks: usize,
+    upload_id: Option<String>,
+    starting_part_number: usize,
+    resume_parts: Vec<UploadedPart>,
+    customize_create: Customize<CreateMultipartUploadFluentBuilder>,
+    customize_upload_part: Customize<UploadPartFluentBuilder>,
+    customize_complete: Customize<CompleteMultipartUploadFluentBuilder>,
+    #[cfg(feature = "sync")]
+    semaphore: Option<std::sync::Arc<tokio::sync::Semaphore>>,
+    #[cfg(feature = "sync")]
+    buffer_budget: Option<manager::BufferBudget>,
+    rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+    #[cfg(feature = "tokio")]
+    spill: Option<std::sync::Arc<spill::Spill>>,
+    // receives each part as it is uploaded, for `send_streaming`
+    part_tx: Option<mpsc::UnboundedSender<UploadedPart>>,
+    observer: Option<std::sync::Arc<dyn MpuObserver>>,
+    verify_part: Option<VerifyPart>,
 }
 
-pub type MultipartUploadOutput = CompleteMultipartUploadOutput;
-
 impl MultipartUpload {
     pub fn new(client: &Client) -> Self {
+        let attempts = attempts::Attempts::default();
         Self {
-            client: client.clone(),
+            client: attempts.install(client),
+            attempts,
+            mpu_client: std::sync::Arc::new(client.clone()),
             body: ByteStream::default(),
-            bucket: None,
-            key: None,
+            #[cfg(feature = "tokio")]
+            path: None,
+            create: client.create_multipart_upload(),
+            if_none_match: None,
+            if_match: None,
+            content_md5: true,
+            send_content_md5: true,
+            verify_e_tag: false,
+            digests: Vec::new(),
+            object_digest: None,
+            object_digest_metadata: None,
+            known_object_digest: None,
+            manifest: false,
+            #[cfg(feature = "compression")]
+            compress: None,
+            hash_offload: HashOffload::default(),
+            fail_fast: true,
+            circuit_breaker: None,
+            sequential: false,
+            gcs_compat: false,
+            complete_retries: 3,
+            limits: ProviderLimits::AWS,
+            size_hint: None,
+            failover: Vec::new(),
+            coalesce_chunks: 0,
+            upload_id: None,
+            starting_part_number: 1,
+            resume_parts: Vec::new(),
+            customize_create: None,
+            customize_upload_part: None,
+            customize_complete: None,
+            #[cfg(feature = "sync")]
+            semaphore: None,
+            #[cfg(feature = "sync")]
+            buffer_budget: None,
+            rate_limiter: None,
+            #[cfg(feature = "tokio")]
+            spill: None,
+            part_tx: None,
+            observer: None,
+            verify_part: None,
         }
     }
 
@@ -43,11 +309,110 @@ impl MultipartUpload {
         self
     }
 
+    /// Streams any bytes stream, e.g. `reqwest::Response::bytes_stream()` or an `axum`
+    /// multipart `Field`. A `multer` `Field` borrows its request; see [`upload_field`].
+    pub fn body_stream<S, E>(mut self, inp: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.body = into_byte_stream::from_stream(inp);
+        self
+    }
+
+    /// Streams an `http-body` 0.4 body, e.g. a `hyper` 0.14 request body.
+    pub fn body_http_0_4<B>(mut self, inp: B) -> Self
+    where
+        B: http_body::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.body = into_byte_stream::from_body_0_4(inp);
+        self
+    }
+
+    /// Streams an `http-body` 1.x body, e.g. a `hyper` 1.x or `axum` request body.
+    #[cfg(feature = "http-body-1")]
+    pub fn body_http<B>(mut self, inp: B) -> Self
+    where
+        B: http_body_1::Body<Data = Bytes> + Send + 'static,
+        B::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.body = into_byte_stream::from_body_1(inp);
+        self
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn body_reader<R>(self, inp: R) -> Self
+    where
+        R: tokio::io::AsyncRead + Send + 'static,
+    {
+        self.body_stream(tokio_util::io::ReaderStream::with_capacity(
+            inp,
+            READER_CAPACITY,
+        ))
+    }
+
+    /// Streams the standard input, e.g. the output of `pg_dump` piped into the program. Parts are
+    /// read only as they can be sent, so a fast writer is held back by the upload.
+    ///
+    /// The length of a pipe is unknown; see [`Self::size_hint`].
+    #[cfg(feature = "tokio")]
+    pub fn body_stdin(self) -> Self {
+        self.body_reader(tokio::io::stdin())
+    }
+
+    /// Uploads the file at `path`, reading each part from disk instead of buffering it.
+    #[cfg(feature = "tokio")]
+    pub fn body_path<P>(mut self, inp: P) -> Self
+    where
+        P: Into<std::path::PathBuf>,
+    {
+        self.path = Some(inp.into());
+        self
+    }
+
+    /// Encodes each frame with `encoder` and streams the result, e.g. to re-encode the frames of
+    /// a `FramedRead`.
+    #[cfg(feature = "tokio")]
+    pub fn body_framed<S, T, E, C>(self, frames: S, mut encoder: C) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+        C: tokio_util::codec::Encoder<T> + Send + 'static,
+        C::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.body_stream(frames.map(move |frame| {
+            let mut buf = bytes::BytesMut::new();
+            encoder
+                .encode(frame.map_err(Into::into)?, &mut buf)
+                .map_err(Into::into)?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(buf.freeze())
+        }))
+    }
+
+    /// Reads a blocking reader on `spawn_blocking`, e.g. for synchronous producers.
+    #[cfg(feature = "tokio")]
+    pub fn body_sync_reader<R>(mut self, inp: R) -> Self
+    where
+        R: std::io::Read + Send + 'static,
+    {
+        self.body = into_byte_stream::from_sync_reader(inp, READER_CAPACITY, SYNC_READER_BUFFERS);
+        self
+    }
+
+    /// Replaces the `CreateMultipartUpload` request, e.g. to set options not exposed here.
+    pub fn create_multipart_upload(mut self, inp: CreateMultipartUploadFluentBuilder) -> Self {
+        self.create = inp;
+        self
+    }
+
+    /// Also accepts an access point ARN, or a multi-region access point alias together with
+    /// [`Self::expected_bucket_owner`], which is sent as the ARN of the alias.
     pub fn bucket<S>(mut self, inp: S) -> Self
     where
         S: Into<String>,
     {
-        self.bucket = Some(inp.into());
+        self.create = self.create.bucket(inp);
         self
     }
 
@@ -55,87 +420,1073 @@ impl MultipartUpload {
     where
         S: Into<String>,
     {
-        self.key = Some(inp.into());
+        self.create = self.create.key(inp);
+        self
+    }
+
+    pub fn cache_control<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.cache_control(inp);
+        self
+    }
+
+    /// Overrides the type guessed from the key with the `mime_guess` feature.
+    pub fn content_type<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.content_type(inp);
+        self
+    }
+
+    pub fn content_disposition<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.content_disposition(inp);
+        self
+    }
+
+    pub fn content_encoding<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.content_encoding(inp);
+        self
+    }
+
+    pub fn content_language<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.content_language(inp);
+        self
+    }
+
+    pub fn expires(mut self, inp: DateTime) -> Self {
+        self.create = self.create.expires(inp);
+        self
+    }
+
+    pub fn website_redirect_location<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.website_redirect_location(inp);
+        self
+    }
+
+    pub fn expected_bucket_owner<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.create = self.create.expected_bucket_owner(inp);
+        self
+    }
+
+    pub fn request_payer(mut self, inp: RequestPayer) -> Self {
+        self.create = self.create.request_payer(inp);
+        self
+    }
+
+    pub fn if_none_match<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.if_none_match = Some(inp.into());
+        self
+    }
+
+    /// Completes the upload only if the existing object has the ETag `inp`.
+    pub fn if_match<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.if_match = Some(inp.into());
+        self
+    }
+
+    pub fn checksum_algorithm(mut self, inp: ChecksumAlgorithm) -> Self {
+        self.create = self.create.checksum_algorithm(inp);
+        self
+    }
+
+    pub fn checksum_type(mut self, inp: ChecksumType) -> Self {
+        self.create = self.create.checksum_type(inp);
+        self
+    }
+
+    pub fn content_md5(mut self, inp: bool) -> Self {
+        self.content_md5 = inp;
+        self
+    }
+
+    /// Leaves out the `Content-MD5` header, for stores and FIPS environments that reject it.
+    /// With [`Self::content_md5`], the digests are still computed for [`Self::verify_e_tag`]
+    /// and [`PartInfo::content_md5`].
+    pub fn send_content_md5(mut self, inp: bool) -> Self {
+        self.send_content_md5 = inp;
+        self
+    }
+
+    /// Compares the ETag of the completed object with the one computed from the MD5 of each
+    /// part, failing with [`IntegrityError::ETagMismatch`]. Skipped when a part has no MD5,
+    /// e.g. a part copied with `UploadPartCopy` or listed by [`Initiated::list_parts`].
+    pub fn verify_e_tag(mut self, inp: bool) -> Self {
+        self.verify_e_tag = inp;
+        self
+    }
+
+    pub fn digest(mut self, inp: ChecksumAlgorithm) -> Self {
+        self.digests.push(inp);
+        self
+    }
+
+    /// Hashes the whole body with `inp` as it streams and returns the digest in
+    /// [`MultipartUploadOutput::object_digest`], so that the content hash is recorded without
+    /// reading the data twice. The body is hashed in order, without [`Self::hash_offload`].
+    ///
+    /// A file given by [`Self::body_path`] is read once more for the digest, alongside its
+    /// parts.
+    pub fn object_digest(mut self, inp: ChecksumAlgorithm) -> Self {
+        self.object_digest = Some(inp);
+        self
+    }
+
+    /// Stores the [`Self::object_digest`], SHA-256 unless set otherwise, as the user metadata
+    /// `inp` of the object, e.g. `sha256` for `x-amz-meta-sha256`. The value is base64-encoded
+    /// like the `x-amz-checksum-*` headers.
+    ///
+    /// The metadata is set by `CreateMultipartUpload`, so this requires [`Self::body_path`]:
+    /// the file is hashed before the upload is created.
+    pub fn object_digest_metadata<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.object_digest.get_or_insert(ChecksumAlgorithm::Sha256);
+        self.object_digest_metadata = Some(inp.into());
+        self
+    }
+
+    /// Compresses the body with `codec` at `level` before splitting it and sets
+    /// `Content-Encoding` accordingly. The body is compressed by [`Self::send`], which streams a
+    /// file given by [`Self::body_path`] instead of reading its parts by range.
+    #[cfg(feature = "compression")]
+    pub fn compress(mut self, codec: Codec, level: i32) -> Self {
+        self.compress = Some((codec, level));
+        self
+    }
+
+    /// Writes a JSON manifest to `<key>.manifest` once the upload has been completed. It holds
+    /// the offset, length, `Content-MD5`, checksum and digests of each part and the
+    /// [`Self::object_digest`], for auditing or repairing the object later.
+    ///
+    /// The upload fails if the manifest cannot be written, although the object is complete.
+    pub fn manifest(mut self, inp: bool) -> Self {
+        self.manifest = inp;
+        self
+    }
+
+    pub fn hash_offload(mut self, inp: HashOffload) -> Self {
+        self.hash_offload = inp;
+        self
+    }
+
+    pub fn fail_fast(mut self, inp: bool) -> Self {
+        self.fail_fast = inp;
+        self
+    }
+
+    /// Keeps uploading the remaining parts when a part fails, whatever [`Self::fail_fast`], and
+    /// stops starting parts, drops the parts in flight and fails with [`CircuitOpen`] once `inp`
+    /// parts in a row have failed, e.g. because the credentials expired. The upload still fails
+    /// with the first error when fewer parts in a row fail.
+    pub fn circuit_breaker(mut self, inp: NonZeroUsize) -> Self {
+        self.circuit_breaker = Some(inp);
+        self
+    }
+
+    /// Uploads one part at a time in part-number order, whatever the concurrency limit given
+    /// to [`Self::send`]. For stores and audit requirements that expect parts in order.
+    pub fn sequential(mut self, inp: bool) -> Self {
+        self.sequential = inp;
+        self
+    }
+
+    /// Adapts the upload to the XML multipart API of Google Cloud Storage, which accepts neither
+    /// the checksum trailers the SDK adds to `UploadPart` nor `x-amz-checksum-*` headers, and
+    /// returns ETags that are not S3-style. Options that depend on those are rejected up front.
+    /// Combine with [`ProviderLimits::GCS`].
+    pub fn gcs_compat(mut self, inp: bool) -> Self {
+        if inp {
+            self.client = Client::from_conf(
+                self.client
+                    .config()
+                    .to_builder()
+                    .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                    .build(),
+            );
+            self.create = failover::rebuild(&self.client, &self.create);
+        }
+        self.gcs_compat = inp;
+        self
+    }
+
+    /// Retries `CompleteMultipartUpload` when S3 answers 200 OK with an error document, with
+    /// exponential backoff from 100 ms. Retries sleep with the `sleep_impl` of the client, or
+    /// with tokio under the `tokio` feature, and are not made without either.
+    pub fn complete_retries(mut self, inp: usize) -> Self {
+        self.complete_retries = inp;
+        self
+    }
+
+    /// Limits of the storage provider, which bound part sizes, part numbers and the object
+    /// size. Defaults to [`ProviderLimits::AWS`].
+    pub fn provider_limits(mut self, inp: ProviderLimits) -> Self {
+        self.limits = inp;
+        self
+    }
+
+    /// Bounds of the `part_size` given to [`Self::send`]. Defaults to [`PART_SIZE`]; S3-compatible
+    /// stores and test doubles may accept smaller parts.
+    pub fn part_size_limits(mut self, inp: RangeInclusive<u64>) -> Self {
+        self.limits.part_size = inp;
+        self
+    }
+
+    /// The expected length of a body of unknown length, e.g. a pipe. [`Self::send`] starts the
+    /// parts at the size that keeps `inp` bytes within the part limit, rather than at the
+    /// smallest `part_size`, which would run out of parts after `max_parts` of them.
+    pub fn size_hint(mut self, inp: u64) -> Self {
+        self.size_hint = Some(inp);
+        self
+    }
+
+    /// Fails the upload with [`ObjectTooLarge`] once the body exceeds `inp` bytes, before the
+    /// part that crosses it is sent. Defaults to [`MAX_OBJECT_SIZE`].
+    pub fn max_object_size(mut self, inp: u64) -> Self {
+        self.limits.max_object_size = inp;
+        self
+    }
+
+    /// Copies body chunks shorter than `inp` bytes into buffers of `inp` bytes, for sources that
+    /// emit many tiny chunks. Disabled by default; see [`split_coalesced`].
+    pub fn coalesce_chunks(mut self, inp: usize) -> Self {
+        self.coalesce_chunks = inp;
         self
     }
 
+    /// Uploads parts to an existing multipart upload instead of creating one.
+    pub fn upload_id<S>(mut self, inp: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.upload_id = Some(inp.into());
+        self
+    }
+
+    /// Number of the first uploaded part. Defaults to 1.
+    ///
+    /// With a number other than 1, the parts uploaded here are only a subset of the object, so
+    /// the caller has to complete the upload with the other parts itself.
+    pub fn starting_part_number(mut self, inp: usize) -> Self {
+        self.starting_part_number = inp;
+        self
+    }
+
+    /// Resumes the upload given by [`Self::upload_id`] after `inp`, the parts from 1 that it
+    /// already has, e.g. listed by [`Initiated::list_parts`]. The body is uploaded after them
+    /// and has to start where the last of them ends, and the upload is completed with all of
+    /// them.
+    pub fn resume_parts(mut self, inp: Vec<UploadedPart>) -> Self {
+        self.resume_parts = inp;
+        self
+    }
+
+    /// Modifies the `CreateMultipartUpload` request before it is sent.
+    pub fn customize_create<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CreateMultipartUploadFluentBuilder) -> CreateMultipartUploadFluentBuilder
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.customize_create = Some(Box::new(f));
+        self
+    }
+
+    /// Modifies each `UploadPart` request before it is sent.
+    pub fn customize_upload_part<F>(mut self, f: F) -> Self
+    where
+        F: Fn(UploadPartFluentBuilder) -> UploadPartFluentBuilder + Send + Sync + 'static,
+    {
+        self.customize_upload_part = Some(Box::new(f));
+        self
+    }
+
+    /// Takes a permit from `inp` for every `UploadPart` request, so that uploads sharing the
+    /// semaphore share one concurrency budget.
+    #[cfg(feature = "sync")]
+    pub fn semaphore(mut self, inp: std::sync::Arc<tokio::sync::Semaphore>) -> Self {
+        self.semaphore = Some(inp);
+        self
+    }
+
+    /// Creates the upload in `bucket` with `client` instead, e.g. in another region, if
+    /// `CreateMultipartUpload` fails with a connection or server error. Destinations are tried
+    /// in the order they are added; [`MultipartUploadOutput::destination`] tells which one was
+    /// used.
+    pub fn failover<S>(mut self, client: &Client, bucket: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.failover.push((client.clone(), bucket.into()));
+        self
+    }
+
+    /// Sends the requests of this upload to the S3 Transfer Acceleration endpoint, without
+    /// changing the client given to [`Self::new`]. The bucket must have acceleration enabled.
+    pub fn accelerate(mut self, inp: bool) -> Self {
+        self.client = Client::from_conf(self.client.config().to_builder().accelerate(inp).build());
+        self.create = failover::rebuild(&self.client, &self.create);
+        self
+    }
+
+    /// Reports the events of this upload to `inp`. Retries of parts are observed through an
+    /// interceptor added to the client of this upload, so set this after [`Self::accelerate`]
+    /// and note that the clients of [`Self::failover`] report no retries.
+    pub fn observer(mut self, inp: std::sync::Arc<dyn MpuObserver>) -> Self {
+        self.client = Client::from_conf(
+            self.client
+                .config()
+                .to_builder()
+                .interceptor(observer::Retries(inp.clone()))
+                .build(),
+        );
+        self.create = failover::rebuild(&self.client, &self.create);
+        self.observer = Some(inp);
+        self
+    }
+
+    /// Calls `inp` with each uploaded part and the response of its `UploadPart`, e.g. to record
+    /// the ETag and checksums in an audit system, before the part is accepted. An error fails
+    /// the upload with [`IntegrityError::PartRejected`]. Parts copied with `UploadPartCopy` are
+    /// not passed to `inp`.
+    pub fn verify_part<F, Fut>(mut self, inp: F) -> Self
+    where
+        F: Fn(&PartInfo, &UploadPartOutput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Box<dyn Error + Send + Sync>>> + Send + 'static,
+    {
+        self.verify_part = Some(Box::new(move |part_info, output| {
+            Box::pin(inp(part_info, output))
+        }));
+        self
+    }
+
+    /// Sends the `CreateMultipartUpload`, `UploadPart` and `CompleteMultipartUpload` requests
+    /// through `inp` instead of the client, e.g. to test without S3.
+    pub fn mpu_client(mut self, inp: std::sync::Arc<dyn MpuClient>) -> Self {
+        self.mpu_client = inp;
+        self
+    }
+
+    /// Delays each `UploadPart` request until `inp` admits its bytes, so that uploads sharing
+    /// the limiter stay under its bandwidth together. Without the `tokio` feature, the client
+    /// needs a `sleep_impl` to wait with.
+    pub fn rate_limiter(mut self, inp: std::sync::Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(inp);
+        self
+    }
+
+    /// Writes parts to temporary files in `dir` once the parts held in memory would exceed
+    /// `bytes_in_memory` bytes, and streams them from disk when uploading them. This bounds
+    /// memory without lowering the concurrency limit.
+    #[cfg(feature = "tokio")]
+    pub fn spill_to_disk<P>(mut self, dir: P, bytes_in_memory: u64) -> Self
+    where
+        P: Into<std::path::PathBuf>,
+    {
+        self.spill = Some(std::sync::Arc::new(spill::Spill::new(
+            dir.into(),
+            bytes_in_memory,
+        )));
+        self
+    }
+
+    /// Modifies the `CompleteMultipartUpload` request before it is sent.
+    pub fn customize_complete<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CompleteMultipartUploadFluentBuilder) -> CompleteMultipartUploadFluentBuilder
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.customize_complete = Some(Box::new(f));
+        self
+    }
+
+    // for the requests sent before `CreateMultipartUpload`
+    fn resolve_bucket(&mut self) -> Result<(), BuildError> {
+        let create = mem::replace(&mut self.create, self.client.create_multipart_upload());
+        self.create = arn::resolve(create)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<Option<ChecksumAlgorithm>, BuildError> {
+        for (field, checksum_algorithm) in self
+            .create
+            .get_checksum_algorithm()
+            .iter()
+            .map(|checksum_algorithm| ("checksum_algorithm", checksum_algorithm))
+            .chain(self.digests.iter().map(|digest| ("digests", digest)))
+            .chain(
+                self.object_digest
+                    .iter()
+                    .map(|object_digest| ("object_digest", object_digest)),
+            )
+        {
+            if checksum::Hasher::new(checksum_algorithm).is_none() {
+                return Err(BuildError::invalid_field(
+                    field,
+                    format!("{checksum_algorithm} is not supported"),
+                ));
+            }
+        }
+        if self.object_digest_metadata.is_some() {
+            return Err(BuildError::invalid_field(
+                "object_digest_metadata",
+                "requires body_path",
+            ));
+        }
+        if self.verify_e_tag && !self.content_md5 {
+            return Err(BuildError::invalid_field(
+                "verify_e_tag",
+                "requires content_md5",
+            ));
+        }
+        if self.gcs_compat {
+            for (field, is_set) in [
+                (
+                    "checksum_algorithm",
+                    self.create.get_checksum_algorithm().is_some(),
+                ),
+                ("checksum_type", self.create.get_checksum_type().is_some()),
+                ("verify_e_tag", self.verify_e_tag),
+                ("if_none_match", self.if_none_match.is_some()),
+                ("if_match", self.if_match.is_some()),
+            ] {
+                if is_set {
+                    return Err(BuildError::invalid_field(
+                        field,
+                        "not supported by the GCS XML API",
+                    ));
+                }
+            }
+        }
+        if self.rate_limiter.is_some()
+            && std::iter::once(&self.client)
+                .chain(self.failover.iter().map(|(client, _)| client))
+                .any(|client| sleep_impl(client).is_none())
+        {
+            return Err(BuildError::invalid_field(
+                "rate_limiter",
+                "requires a client with a sleep_impl",
+            ));
+        }
+        if !(1..=self.limits.max_parts).contains(&self.starting_part_number) {
+            return Err(BuildError::invalid_field(
+                "starting_part_number",
+                format!("must be between 1 and {}", self.limits.max_parts),
+            ));
+        }
+        if !self.resume_parts.is_empty() {
+            if self.upload_id.is_none() {
+                return Err(BuildError::invalid_field(
+                    "resume_parts",
+                    "requires upload_id",
+                ));
+            }
+            if self.starting_part_number != 1 {
+                return Err(BuildError::invalid_field(
+                    "resume_parts",
+                    "requires starting_part_number to be 1",
+                ));
+            }
+            let mut offset = 0;
+            for (i, uploaded_part) in self.resume_parts.iter().enumerate() {
+                let info = &uploaded_part.info;
+                if info.number as usize != i + 1 || info.range.start != offset {
+                    return Err(BuildError::invalid_field(
+                        "resume_parts",
+                        "must be contiguous parts from 1",
+                    ));
+                }
+                offset = info.range.end;
+            }
+        }
+        if self.starting_part_number != 1 {
+            if self.verify_e_tag {
+                return Err(BuildError::invalid_field(
+                    "verify_e_tag",
+                    "requires starting_part_number to be 1",
+                ));
+            }
+            if self.create.get_checksum_type() == &Some(ChecksumType::FullObject) {
+                return Err(BuildError::invalid_field(
+                    "checksum_type",
+                    "FULL_OBJECT requires starting_part_number to be 1",
+                ));
+            }
+        }
+        match (
+            self.create.get_checksum_type(),
+            self.create.get_checksum_algorithm(),
+        ) {
+            (Some(ChecksumType::FullObject), Some(checksum_algorithm))
+                if Checksum::combine(checksum_algorithm, []).is_some() =>
+            {
+                Ok(Some(checksum_algorithm.clone()))
+            }
+            (Some(ChecksumType::FullObject), _) => Err(BuildError::invalid_field(
+                "checksum_type",
+                "FULL_OBJECT requires CRC32, CRC32C or CRC64NVME",
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    // S3 would only reject it with EntityTooSmall after the parts were sent
+    pub(crate) fn validate_part_size(
+        &self,
+        part_size: &RangeInclusive<u64>,
+    ) -> Result<(), InvalidPartSize> {
+        if part_size.is_empty()
+            || *part_size.start() == 0
+            || !self.limits.part_size.contains(part_size.start())
+            || !self.limits.part_size.contains(part_size.end())
+        {
+            return Err(InvalidPartSize {
+                part_size: part_size.clone(),
+                limits: self.limits.part_size.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Creates the multipart upload, or attaches to the one given by `upload_id`.
+    pub async fn initiate<E>(mut self) -> Result<Initiated, MultipartUploadError<E>>
+    where
+        E: From<SdkError<CreateMultipartUploadError>> + From<BuildError>,
+    {
+        let full_object = self.validate().map_err(MultipartUploadError::new)?;
+        let started = Instant::now();
+
+        let create = mem::replace(&mut self.create, self.client.create_multipart_upload());
+        let create_multipart_upload = customize(&self.customize_create, create);
+        #[cfg(feature = "mime_guess")]
+        let create_multipart_upload = match create_multipart_upload.get_content_type() {
+            Some(_) => create_multipart_upload,
+            None => {
+                let content_type = create_multipart_upload
+                    .get_key()
+                    .as_deref()
+                    .and_then(guess_content_type);
+                create_multipart_upload.set_content_type(content_type)
+            }
+        };
+        // parts are hashed with the checksum algorithm of the upload
+        self.create = self
+            .create
+            .set_checksum_algorithm(create_multipart_upload.get_checksum_algorithm().clone());
+        let mut create_multipart_upload = create_multipart_upload;
+        let mut failover = mem::take(&mut self.failover).into_iter();
+        let mut destination = 0;
+        let (bucket, key, expected_bucket_owner, request_payer, upload_id) = loop {
+            create_multipart_upload =
+                arn::resolve(create_multipart_upload).map_err(MultipartUploadError::new)?;
+            let bucket = create_multipart_upload.get_bucket().clone();
+            let key = create_multipart_upload.get_key().clone();
+            // the SDK would only reject these while sending the first request
+            for (field, value) in [("bucket", &bucket), ("key", &key)] {
+                if value.is_none() {
+                    return Err(MultipartUploadError::new(BuildError::missing_field(
+                        field, "required",
+                    )));
+                }
+            }
+            let expected_bucket_owner = create_multipart_upload.get_expected_bucket_owner().clone();
+            let request_payer = create_multipart_upload.get_request_payer().clone();
+            let upload_id = match self.upload_id.take() {
+                Some(upload_id) => Some(upload_id),
+                None => {
+                    let output = instrument!(
+                        self.mpu_client
+                            .create_multipart_upload(create_multipart_upload.clone()),
+                        "create_multipart_upload",
+                        bucket = bucket.as_deref(),
+                        key = key.as_deref(),
+                    )
+                    .await;
+                    match output {
+                        Ok(output) => output.upload_id,
+                        Err(err) => match failover.next() {
+                            Some((client, failover_bucket))
+                                if failover::is_region_failure(&err) =>
+                            {
+                                let client = self.attempts.install(&client);
+                                create_multipart_upload =
+                                    failover::rebuild(&client, &create_multipart_upload)
+                                        .bucket(failover_bucket);
+                                self.client = client;
+                                destination += 1;
+                                continue;
+                            }
+                            _ => {
+                                let request_ids = RequestIds::new(&err);
+                                return Err(MultipartUploadError::new(err).request_ids(request_ids));
+                            }
+                        },
+                    }
+                }
+            };
+            break (bucket, key, expected_bucket_owner, request_payer, upload_id);
+        };
+
+        let parts = mem::take(&mut self.resume_parts);
+        let (next_part_number, next_offset) = match parts.last() {
+            Some(uploaded_part) => (
+                uploaded_part.info.number as usize + 1,
+                uploaded_part.info.range.end,
+            ),
+            None => (self.starting_part_number, 0),
+        };
+        let object_hasher = self
+            .object_digest
+            .as_ref()
+            .and_then(checksum::Hasher::new)
+            .map(|hasher| (hasher, 0));
+        Ok(Initiated {
+            upload: self,
+            bucket,
+            key,
+            expected_bucket_owner,
+            request_payer,
+            upload_id,
+            full_object,
+            parts,
+            next_part_number,
+            next_offset,
+            object_hasher,
+            destination,
+            started,
+        })
+    }
+
     pub async fn send<E>(
         mut self,
-        part_size: RangeInclusive<usize>,
+        part_size: RangeInclusive<u64>,
         concurrency_limit: Option<NonZeroUsize>,
-    ) -> Result<MultipartUploadOutput, (E, Option<AbortMultipartUploadFluentBuilder>)>
+    ) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
     where
-        E: From<SdkError<CreateMultipartUploadError>>
-            + From<SdkError<UploadPartError>>
-            + From<SdkError<CompleteMultipartUploadError>>
-            + From<ByteStreamError>,
+        E: UploadErrorKind,
     {
-        let output = self
-            .client
-            .create_multipart_upload()
-            .set_bucket(self.bucket.clone())
-            .set_key(self.key.clone())
-            .send()
-            .map_err(|err| (err.into(), None))
-            .await?;
-        let upload_id = output.upload_id;
-
-        let abort = || {
-            self.client
-                .abort_multipart_upload()
-                .set_bucket(self.bucket.clone())
-                .set_key(self.key.clone())
-                .set_upload_id(upload_id.clone())
+        self.validate_part_size(&part_size)
+            .map_err(MultipartUploadError::new)?;
+        let part_size = match self.size_hint {
+            Some(len) => {
+                plan::part_size_for(len, &part_size, self.limits.max_parts)..=*part_size.end()
+            }
+            None => part_size,
         };
 
-        let parts = split::split(
-            futures::stream::poll_fn(move |cx| Pin::new(&mut self.body).poll_next(cx)),
+        #[cfg(feature = "compression")]
+        if let Some((codec, level)) = self.compress {
+            if let Some(path) = self.path.take() {
+                self.body = ByteStream::from_path(path)
+                    .await
+                    .map_err(MultipartUploadError::new)?;
+            }
+            self.body = codec.encode(level, mem::take(&mut self.body));
+            self.create = self.create.content_encoding(codec.content_encoding());
+        }
+        let mut body = mem::take(&mut self.body);
+        #[cfg(feature = "tokio")]
+        if let Some(path) = self.path.take() {
+            // `upload_path` checks this too, but only after `CreateMultipartUpload`
+            let len = tokio::fs::metadata(&path)
+                .await
+                .map_err(|err| {
+                    MultipartUploadError::new(aws_sdk_s3::primitives::ByteStreamError::from(err))
+                })?
+                .len();
+            let first_part_number = self
+                .resume_parts
+                .last()
+                .map_or(self.starting_part_number, |uploaded_part| {
+                    uploaded_part.info.number as usize + 1
+                });
+            let max_parts = self.limits.max_parts;
+            let parts = plan::plan_parts_within(len, part_size.clone(), max_parts).len();
+            if first_part_number - 1 + parts > max_parts {
+                return Err(MultipartUploadError::new(TooManyParts { max_parts }));
+            }
+            let hasher = self.object_digest.as_ref().and_then(checksum::Hasher::new);
+            if let (Some(key), Some(hasher)) = (self.object_digest_metadata.take(), hasher) {
+                let object_digest = hash_file(&path, hasher)
+                    .await
+                    .map_err(MultipartUploadError::new)?;
+                self.create = self.create.metadata(key, object_digest.value());
+                self.object_digest = None;
+                self.known_object_digest = Some(object_digest);
+            }
+            let mut initiated = self.initiate().await?;
+            initiated
+                .upload_path(path, part_size, concurrency_limit)
+                .await?;
+            return initiated.complete().await;
+        }
+
+        // Reading and hashing the first part while CreateMultipartUpload is in flight saves a
+        // round trip before the first UploadPart.
+        let hasher = initiated::hasher(&self);
+        let mut parts = std::pin::pin!(split::split_coalesced(
+            futures::stream::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)),
             part_size,
+            (self.hash_offload == HashOffload::Inline).then(&hasher),
+            self.coalesce_chunks,
         )
-        .map_ok(|part| {
-            self.client
-                .upload_part()
-                .body(into_byte_stream::into_byte_stream(part.body))
-                .set_bucket(self.bucket.clone())
-                .content_length(part.content_length as _)
-                .content_md5(base64::encode(part.content_md5))
-                .set_key(self.key.clone())
-                .part_number(part.part_number as _)
-                .set_upload_id(upload_id.clone())
-                .send()
-                .map_ok({
-                    move |output| {
-                        CompletedPart::builder()
-                            .set_e_tag(output.e_tag)
-                            .part_number(part.part_number as _)
-                            .build()
-                    }
-                })
-                .err_into()
+        .peekable());
+        let initiate = std::pin::pin!(self.initiate());
+        let mut initiated = match future::select(initiate, parts.as_mut().peek()).await {
+            Either::Left((initiated, _)) => initiated?,
+            Either::Right((_, initiate)) => initiate.await?,
+        };
+        initiated
+            .upload_split(parts, hasher, concurrency_limit)
+            .await?;
+        initiated.complete().await
+    }
+
+    /// Like [`Self::send`], but yields each part as S3 acknowledges it, e.g. to checkpoint the
+    /// upload, and then the output of the upload. The stream ends after the first error.
+    pub fn send_streaming<E>(
+        mut self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> impl Stream<Item = Result<UploadEvent, MultipartUploadError<E>>>
+    where
+        E: UploadErrorKind,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        self.part_tx = Some(tx);
+        streaming::stream(self.send(part_size, concurrency_limit), rx)
+    }
+
+    /// Runs [`Self::send`] on a spawned task and returns a handle to watch, pause and await it.
+    #[cfg(feature = "tokio")]
+    pub fn send_background<E>(
+        mut self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> UploadHandle<E>
+    where
+        E: UploadErrorKind + Send + 'static,
+    {
+        UploadHandle::spawn(self.mpu_client.clone(), |mpu_client| {
+            self.mpu_client = mpu_client;
+            self.send(part_size, concurrency_limit)
         })
-        .err_into();
+    }
 
-        let mut completed_parts = parts
-            .try_buffer_unordered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
-            .try_collect::<Vec<_>>()
-            .map_err(|err| (err, Some(abort())))
-            .await?;
+    /// Appends the body to the object at `key`, or creates the object if it does not exist.
+    ///
+    /// In an S3 Express One Zone directory bucket, the body is appended in place with a
+    /// `PutObject` at the end of the object per part. In other buckets, a new upload copies the
+    /// existing object with `UploadPartCopy`, uploads the body after it and completes only if
+    /// the object still has the ETag it was copied from. The tail of the existing object that
+    /// does not fill a whole part is downloaded and uploaded with the body.
+    pub async fn append_to<E, S>(
+        mut self,
+        key: S,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<AppendOutput, MultipartUploadError<E>>
+    where
+        E: UploadErrorKind
+            + From<PartError<SdkError<UploadPartCopyError>>>
+            + From<SdkError<HeadObjectError>>
+            + From<SdkError<GetObjectError>>,
+        S: Into<String>,
+    {
+        self.validate_part_size(&part_size)
+            .map_err(MultipartUploadError::new)?;
+        #[cfg(feature = "compression")]
+        if self.compress.is_some() {
+            return Err(MultipartUploadError::new(BuildError::invalid_field(
+                "compress",
+                "is not supported for appends",
+            )));
+        }
+        self.create = self.create.key(key);
+        self.resolve_bucket().map_err(MultipartUploadError::new)?;
+        #[cfg(feature = "tokio")]
+        if let Some(path) = self.path.take() {
+            self.body = ByteStream::from_path(path)
+                .await
+                .map_err(MultipartUploadError::new)?;
+        }
 
-        completed_parts.sort_by_key(|completed_part| completed_part.part_number);
+        let bucket = self.create.get_bucket().clone().unwrap_or_default();
+        let key = self.create.get_key().clone().unwrap_or_default();
+        let head = self
+            .client
+            .head_object()
+            .bucket(&bucket)
+            .key(&key)
+            .set_expected_bucket_owner(self.create.get_expected_bucket_owner().clone())
+            .set_request_payer(self.create.get_request_payer().clone())
+            .send()
+            .await;
+        let head = match head {
+            Ok(head) => Some(head),
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => None,
+            Err(err) => {
+                let request_ids = RequestIds::new(&err);
+                return Err(MultipartUploadError::new(err).request_ids(request_ids));
+            }
+        };
+        let len = head
+            .as_ref()
+            .and_then(|head| head.content_length)
+            .unwrap_or_default() as u64;
+        if append::is_express(&bucket) {
+            let body = mem::take(&mut self.body);
+            return append::append_express(&self, body, len, part_size)
+                .await
+                .map(AppendOutput::Appended);
+        }
+        let Some(head) = head else {
+            return self
+                .send(part_size, concurrency_limit)
+                .await
+                .map(AppendOutput::Rewritten);
+        };
 
-        self.client
-            .complete_multipart_upload()
-            .set_bucket(self.bucket.clone())
-            .set_key(self.key.clone())
-            .multipart_upload(
-                CompletedMultipartUpload::builder()
-                    .set_parts(Some(completed_parts))
-                    .build(),
-            )
-            .set_upload_id(upload_id.clone())
+        // copied parts must not be smaller than the others, so only whole parts are copied
+        let size = plan::part_size_for(len, &part_size, self.limits.max_parts);
+        let copied = len / size * size;
+        let copy_source = copy_source::copy_source(&bucket, &key, head.version_id.as_deref());
+        self.if_match.clone_from(&head.e_tag);
+        let mut body = mem::take(&mut self.body);
+        let mut initiated = self.initiate().await?;
+        initiated
+            .copy_parts(&copy_source, copied, size..=size, concurrency_limit)
+            .await?;
+        if copied < len {
+            let tail = initiated
+                .upload
+                .client
+                .get_object()
+                .bucket(&bucket)
+                .key(&key)
+                .set_version_id(head.version_id.clone())
+                .set_if_match(head.e_tag.clone())
+                .set_expected_bucket_owner(initiated.expected_bucket_owner.clone())
+                .set_request_payer(initiated.request_payer.clone())
+                .range(format!("bytes={copied}-"))
+                .send()
+                .await
+                .map_err(|err| {
+                    let request_ids = RequestIds::new(&err);
+                    MultipartUploadError::new(err)
+                        .abort(&initiated.upload_id, initiated.give_up())
+                        .request_ids(request_ids)
+                })?;
+            body = append::chain(tail.body, body);
+        }
+        initiated
+            .upload_parts(body, part_size, concurrency_limit)
+            .await?;
+        initiated.complete().await.map(AppendOutput::Rewritten)
+    }
+
+    /// Like [`Self::send`], but first sends `HeadObject` for the key and returns
+    /// [`SendOutput::Skipped`] without creating the upload when the object already has the size
+    /// and the full-object checksum or ETag of the file given by [`Self::body_path`].
+    ///
+    /// The expected ETag assumes the part size that [`Self::send`] chooses for the file, so
+    /// objects written by other tools or encrypted with SSE-KMS are uploaded again.
+    #[cfg(feature = "tokio")]
+    pub async fn send_if_changed<E>(
+        mut self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<SendOutput, MultipartUploadError<E>>
+    where
+        E: UploadErrorKind + From<SdkError<HeadObjectError>>,
+    {
+        self.validate_part_size(&part_size)
+            .map_err(MultipartUploadError::new)?;
+        self.resolve_bucket().map_err(MultipartUploadError::new)?;
+        let Some(path) = &self.path else {
+            return Err(MultipartUploadError::new(BuildError::invalid_field(
+                "body",
+                "send_if_changed requires body_path",
+            )));
+        };
+        let head = self
+            .client
+            .head_object()
+            .set_bucket(self.create.get_bucket().clone())
+            .set_key(self.create.get_key().clone())
+            .set_expected_bucket_owner(self.create.get_expected_bucket_owner().clone())
+            .set_request_payer(self.create.get_request_payer().clone())
+            .checksum_mode(aws_sdk_s3::types::ChecksumMode::Enabled)
             .send()
-            .map_err(|err| (err.into(), Some(abort())))
+            .await;
+        match head {
+            Ok(head) => {
+                if skip::is_identical(&head, path, &part_size, self.limits.max_parts)
+                    .await
+                    .map_err(|err| {
+                        MultipartUploadError::new(aws_sdk_s3::primitives::ByteStreamError::from(
+                            err,
+                        ))
+                    })?
+                {
+                    return Ok(SendOutput::Skipped(head));
+                }
+            }
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => {}
+            Err(err) => {
+                let request_ids = RequestIds::new(&err);
+                return Err(MultipartUploadError::new(err).request_ids(request_ids));
+            }
+        }
+        self.send(part_size, concurrency_limit)
             .await
+            .map(SendOutput::Uploaded)
+    }
+
+    /// Runs [`Self::send`] to completion on a new single-threaded runtime, for callers that are
+    /// not async themselves.
+    ///
+    /// Panics when called from within an async runtime.
+    #[cfg(feature = "blocking")]
+    #[allow(clippy::result_large_err)]
+    pub fn send_blocking<E>(
+        self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
+    where
+        E: UploadErrorKind,
+    {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| {
+                MultipartUploadError::new(aws_sdk_s3::primitives::ByteStreamError::from(err))
+            })?
+            .block_on(self.send(part_size, concurrency_limit))
+    }
+
+    /// Returns a sender for the body chunks and the upload. The body ends when the sender and
+    /// all its clones are dropped.
+    pub fn channel<E>(
+        self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> (
+        mpsc::Sender<Bytes>,
+        impl Future<Output = Result<MultipartUploadOutput, MultipartUploadError<E>>>,
+    )
+    where
+        E: UploadErrorKind,
+    {
+        let (tx, rx) = mpsc::channel(CHANNEL_BUFFER);
+        let upload = self
+            .body_stream(rx.map(Ok::<_, Infallible>))
+            .send(part_size, concurrency_limit);
+        (tx, upload)
+    }
+
+    /// Returns an [`S3Sink`] that uploads the chunks sent to it.
+    pub fn sink<E>(
+        self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> S3Sink<E>
+    where
+        E: UploadErrorKind + Send + 'static,
+    {
+        let (tx, upload) = self.channel(part_size, concurrency_limit);
+        S3Sink::new(tx, Box::pin(upload))
+    }
+
+    /// Returns an [`S3Writer`] that uploads what is written to it.
+    #[cfg(feature = "tokio")]
+    pub fn writer<E>(
+        self,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> S3Writer<E>
+    where
+        E: UploadErrorKind + std::fmt::Display + Send + 'static,
+    {
+        S3Writer::new(self.sink(part_size, concurrency_limit))
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn hash_file(
+    path: &std::path::Path,
+    mut hasher: checksum::Hasher,
+) -> Result<Checksum, aws_sdk_s3::primitives::ByteStreamError> {
+    let mut body = ByteStream::from_path(path).await?;
+    while let Some(chunk) = body.next().await {
+        split::PartHasher::update(&mut hasher, &chunk?);
+    }
+    Ok(split::PartHasher::finalize_reset(&mut hasher))
+}
+
+#[cfg(feature = "mime_guess")]
+fn guess_content_type(key: &str) -> Option<String> {
+    mime_guess::from_path(key)
+        .first_raw()
+        .map(ToOwned::to_owned)
+}
+
+fn customize<T>(f: &Customize<T>, builder: T) -> T {
+    match f {
+        Some(f) => f(builder),
+        None => builder,
+    }
+}
+
+// the config of a client built by hand has no sleep unless aws-sdk-s3 fills it in
+fn sleep_impl(client: &Client) -> Option<SharedAsyncSleep> {
+    #[cfg(feature = "tokio")]
+    return Some(
+        client
+            .config()
+            .sleep_impl()
+            .unwrap_or_else(|| SharedAsyncSleep::new(TokioSleep)),
+    );
+    #[cfg(not(feature = "tokio"))]
+    client.config().sleep_impl()
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+struct TokioSleep;
+
+#[cfg(feature = "tokio")]
+impl aws_sdk_s3::config::AsyncSleep for TokioSleep {
+    fn sleep(&self, duration: std::time::Duration) -> aws_sdk_s3::config::Sleep {
+        aws_sdk_s3::config::Sleep::new(tokio::time::sleep(duration))
     }
 }
 
@@ -0,0 +1,52 @@
+use crate::{MAX_OBJECT_SIZE, MAX_PARTS, PART_SIZE};
+use std::ops::RangeInclusive;
+
+/// Limits of a storage provider, used to validate uploads and to size parts.
+///
+/// Start from one of the presets and adjust the fields for other providers or configurations,
+/// e.g. a MinIO deployment with a different part size limit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProviderLimits {
+    /// Bounds of a part size. The last part may be smaller.
+    pub part_size: RangeInclusive<u64>,
+    pub max_parts: usize,
+    pub max_object_size: u64,
+}
+
+impl ProviderLimits {
+    // https://docs.aws.amazon.com/AmazonS3/latest/userguide/qfacts.html
+    pub const AWS: Self = Self {
+        part_size: PART_SIZE,
+        max_parts: MAX_PARTS,
+        max_object_size: MAX_OBJECT_SIZE,
+    };
+
+    // https://developers.cloudflare.com/r2/platform/limits/
+    pub const R2: Self = Self {
+        part_size: 5 << 20..=5 << 30,
+        max_parts: 10000,
+        // 5 GiB less than 5 TiB
+        max_object_size: (5 << 40) - (5 << 30),
+    };
+
+    // https://cloud.google.com/storage/quotas#requests
+    pub const GCS: Self = Self {
+        part_size: 5 << 20..=5 << 30,
+        max_parts: 10000,
+        max_object_size: 5 << 40,
+    };
+
+    // https://min.io/docs/minio/linux/operations/concepts/thresholds.html
+    pub const MINIO: Self = Self {
+        part_size: 5 << 20..=5 << 30,
+        max_parts: 10000,
+        max_object_size: 50 << 40,
+    };
+}
+
+impl Default for ProviderLimits {
+    fn default() -> Self {
+        Self::AWS
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput, UploadErrorKind};
+use aws_sdk_s3::Client;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Runs many multipart uploads under shared limits.
+///
+/// Parts of all uploads wait for the same semaphores, which hand out permits in request order,
+/// so a new upload queues behind the parts already waiting instead of starving them.
+#[derive(Clone, Debug)]
+pub struct UploadManager {
+    client: Client,
+    parts_in_flight: Arc<Semaphore>,
+    buffer_budget: Option<BufferBudget>,
+}
+
+impl UploadManager {
+    /// Allows at most `parts_in_flight` `UploadPart` requests at a time.
+    pub fn new(client: &Client, parts_in_flight: NonZeroUsize) -> Self {
+        Self {
+            client: client.clone(),
+            parts_in_flight: Arc::new(Semaphore::new(parts_in_flight.get())),
+            buffer_budget: None,
+        }
+    }
+
+    /// Stops reading bodies while the buffered parts of all uploads exceed `bytes`. A part
+    /// larger than the budget still proceeds once it has the whole budget.
+    pub fn bytes_buffered(mut self, bytes: u64) -> Self {
+        self.buffer_budget = Some(BufferBudget::new(bytes));
+        self
+    }
+
+    /// Creates a request using the client of the manager.
+    pub fn multipart_upload(&self) -> MultipartUpload {
+        MultipartUpload::new(&self.client)
+    }
+
+    /// Sends `upload` like [`MultipartUpload::send`], with its parts under the limits of the
+    /// manager instead of a `concurrency_limit`. Replaces the semaphore of `upload`.
+    pub async fn send<E>(
+        &self,
+        mut upload: MultipartUpload,
+        part_size: RangeInclusive<u64>,
+    ) -> Result<MultipartUploadOutput, MultipartUploadError<E>>
+    where
+        E: UploadErrorKind,
+    {
+        upload.semaphore = Some(self.parts_in_flight.clone());
+        upload.buffer_budget.clone_from(&self.buffer_budget);
+        upload.send(part_size, None).await
+    }
+}
+
+/// A budget of buffered bytes, counted in KiB to fit the permits of a semaphore.
+#[derive(Clone, Debug)]
+pub(crate) struct BufferBudget {
+    semaphore: Arc<Semaphore>,
+    kib: u32,
+}
+
+impl BufferBudget {
+    fn new(bytes: u64) -> Self {
+        let kib = u32::try_from(bytes.div_ceil(1 << 10))
+            .unwrap_or(u32::MAX)
+            .clamp(1, Semaphore::MAX_PERMITS as u32);
+        Self {
+            semaphore: Arc::new(Semaphore::new(kib as _)),
+            kib,
+        }
+    }
+
+    pub(crate) async fn acquire(&self, bytes: u64) -> Option<OwnedSemaphorePermit> {
+        let kib = u32::try_from(bytes.div_ceil(1 << 10)).unwrap_or(u32::MAX);
+        self.semaphore
+            .clone()
+            .acquire_many_owned(kib.min(self.kib))
+            .await
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferBudget;
+
+    #[tokio::test]
+    async fn test_buffer_budget() {
+        let budget = BufferBudget::new(3000);
+        let permit = budget.acquire(2048).await.unwrap();
+        assert_eq!(budget.semaphore.available_permits(), 1);
+        assert!(budget.semaphore.try_acquire_many(2).is_err());
+        drop(permit);
+
+        // larger than the budget
+        let permit = budget.acquire(1 << 20).await.unwrap();
+        assert_eq!(budget.semaphore.available_permits(), 0);
+        drop(permit);
+        assert_eq!(budget.semaphore.available_permits(), 3);
+    }
+}
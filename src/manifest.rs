@@ -0,0 +1,62 @@
+use crate::{Checksum, JsonObject, MultipartUploadOutput};
+
+/// Renders the manifest written by [`MultipartUpload::manifest`](crate::MultipartUpload::manifest).
+pub(crate) fn manifest(bucket: &str, key: &str, output: &MultipartUploadOutput) -> String {
+    render(bucket, key, output, false)
+}
+
+/// Renders [`MultipartUploadOutput::to_json`]: the manifest with the version ID, checksum and
+/// stats of the object and the duration and attempts of each part.
+pub(crate) fn report(bucket: &str, key: &str, output: &MultipartUploadOutput) -> String {
+    render(bucket, key, output, true)
+}
+
+fn render(bucket: &str, key: &str, output: &MultipartUploadOutput, detailed: bool) -> String {
+    let mut json = JsonObject::new()
+        .field("bucket", bucket)
+        .field("key", key)
+        .field("upload_id", &output.upload_id)
+        .field("content_length", output.content_length)
+        .field("e_tag", &output.output.e_tag)
+        .field("object_digest", &output.object_digest);
+    if detailed {
+        let stats = output.stats();
+        json = json
+            .field("version_id", &output.output.version_id)
+            .field(
+                "checksum",
+                Checksum::from_complete_multipart_upload(&output.output),
+            )
+            .field("destination", output.destination)
+            .field("duration", stats.duration)
+            .field(
+                "stats",
+                JsonObject::new()
+                    .field("mean_part_duration", stats.mean_part_duration)
+                    .field("min_part_duration", stats.min_part_duration)
+                    .field("max_part_duration", stats.max_part_duration)
+                    .field("bytes_per_sec", stats.bytes_per_sec),
+            );
+    }
+    let parts = output
+        .parts
+        .iter()
+        .map(|uploaded_part| {
+            let info = &uploaded_part.info;
+            let part = JsonObject::new()
+                .field("number", info.number)
+                .field("offset", info.range.start)
+                .field("len", info.len)
+                .field("content_md5", &info.content_md5)
+                .field("checksum", &info.checksum)
+                .field("digests", &info.digests);
+            if detailed {
+                part.field("duration", uploaded_part.duration)
+                    .field("attempts", uploaded_part.attempts)
+            } else {
+                part
+            }
+        })
+        .collect::<Vec<_>>();
+    json.field("parts", parts).to_string()
+}
@@ -0,0 +1,225 @@
+use crate::MpuClient;
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::{
+    AbortMultipartUploadError, AbortMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::{
+    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::{
+    CreateMultipartUploadError, CreateMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+use futures::future::BoxFuture;
+use opentelemetry::metrics::{Counter, Histogram, Meter};
+use opentelemetry::KeyValue;
+use std::collections::HashSet;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+/// An [`MpuClient`] that records OpenTelemetry metrics of the requests it passes through,
+/// with a `bucket` attribute:
+///
+/// - `s3_mpu.bytes_uploaded` and `s3_mpu.parts_completed`, counted on each uploaded part
+/// - `s3_mpu.part_duration`, the latency of `UploadPart` in seconds
+/// - `s3_mpu.retries`, repeated `CompleteMultipartUpload` requests of an upload
+/// - `s3_mpu.failures`, failed requests with an `operation` attribute
+#[derive(Debug)]
+pub struct Metered<C> {
+    inner: C,
+    bytes_uploaded: Counter<u64>,
+    parts_completed: Counter<u64>,
+    part_duration: Histogram<f64>,
+    retries: Counter<u64>,
+    failures: Counter<u64>,
+    completing: Mutex<HashSet<String>>,
+}
+
+impl<C> Metered<C>
+where
+    C: MpuClient,
+{
+    pub fn new(inner: C, meter: &Meter) -> Self {
+        Self {
+            inner,
+            bytes_uploaded: meter
+                .u64_counter("s3_mpu.bytes_uploaded")
+                .with_unit("By")
+                .build(),
+            parts_completed: meter.u64_counter("s3_mpu.parts_completed").build(),
+            part_duration: meter
+                .f64_histogram("s3_mpu.part_duration")
+                .with_unit("s")
+                .build(),
+            retries: meter.u64_counter("s3_mpu.retries").build(),
+            failures: meter.u64_counter("s3_mpu.failures").build(),
+            completing: Mutex::default(),
+        }
+    }
+
+    fn fail(&self, bucket: &Option<String>, operation: &'static str) {
+        self.failures.add(
+            1,
+            &[attribute(bucket), KeyValue::new("operation", operation)],
+        );
+    }
+
+    fn completing(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.completing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C> MpuClient for Metered<C>
+where
+    C: MpuClient,
+{
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+    {
+        let bucket = request.get_bucket().clone();
+        Box::pin(async move {
+            let output = self.inner.create_multipart_upload(request).await;
+            if output.is_err() {
+                self.fail(&bucket, "CreateMultipartUpload");
+            }
+            output
+        })
+    }
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+        let bucket = request.get_bucket().clone();
+        let content_length = request.get_content_length().unwrap_or_default();
+        Box::pin(async move {
+            let start = Instant::now();
+            let output = self.inner.upload_part(request).await;
+            let attributes = [attribute(&bucket)];
+            self.part_duration
+                .record(start.elapsed().as_secs_f64(), &attributes);
+            match &output {
+                Ok(_) => {
+                    self.bytes_uploaded.add(content_length as _, &attributes);
+                    self.parts_completed.add(1, &attributes);
+                }
+                Err(_) => self.fail(&bucket, "UploadPart"),
+            }
+            output
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>
+    {
+        let bucket = request.get_bucket().clone();
+        let upload_id = request.get_upload_id().clone();
+        if let Some(upload_id) = &upload_id {
+            if !self.completing().insert(upload_id.clone()) {
+                self.retries.add(1, &[attribute(&bucket)]);
+            }
+        }
+        Box::pin(async move {
+            let output = self.inner.complete_multipart_upload(request).await;
+            match &output {
+                Ok(_) => {
+                    if let Some(upload_id) = &upload_id {
+                        self.completing().remove(upload_id);
+                    }
+                }
+                Err(_) => self.fail(&bucket, "CompleteMultipartUpload"),
+            }
+            output
+        })
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+    {
+        let bucket = request.get_bucket().clone();
+        let upload_id = request.get_upload_id().clone();
+        Box::pin(async move {
+            let output = self.inner.abort_multipart_upload(request).await;
+            match &output {
+                Ok(_) => {
+                    if let Some(upload_id) = &upload_id {
+                        self.completing().remove(upload_id);
+                    }
+                }
+                Err(_) => self.fail(&bucket, "AbortMultipartUpload"),
+            }
+            output
+        })
+    }
+}
+
+fn attribute(bucket: &Option<String>) -> KeyValue {
+    KeyValue::new("bucket", bucket.clone().unwrap_or_default())
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::Metered;
+    use crate::{FakeS3, Fault, FaultInjector, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStream;
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, SdkMeterProvider};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_metered() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(exporter.clone())
+            .build();
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let metered = Arc::new(Metered::new(
+            FaultInjector::new(client.clone()).complete(Fault::error(200, "InternalError")),
+            &provider.meter("test"),
+        ));
+        MultipartUpload::new(&client)
+            .mpu_client(metered)
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        provider.force_flush().unwrap();
+
+        let metrics = exporter.get_finished_metrics().unwrap();
+        let sum = |name: &str| {
+            metrics
+                .iter()
+                .flat_map(|resource_metrics| resource_metrics.scope_metrics())
+                .flat_map(|scope_metrics| scope_metrics.metrics())
+                .filter(|metric| metric.name() == name)
+                .map(|metric| match metric.data() {
+                    AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                        sum.data_points().map(|data_point| data_point.value()).sum()
+                    }
+                    _ => 0,
+                })
+                .sum::<u64>()
+        };
+        assert_eq!(sum("s3_mpu.bytes_uploaded"), 25);
+        assert_eq!(sum("s3_mpu.parts_completed"), 3);
+        assert_eq!(sum("s3_mpu.retries"), 1);
+        assert_eq!(sum("s3_mpu.failures"), 1);
+    }
+}
@@ -0,0 +1,438 @@
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::{
+    AbortMultipartUploadError, AbortMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::{
+    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::{
+    CreateMultipartUploadError, CreateMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+use aws_sdk_s3::Client;
+use futures::future::BoxFuture;
+
+/// Sends the requests of a multipart upload.
+///
+/// Requests are still built with the [`Client`] given to [`MultipartUpload::new`], so a test
+/// double only has to answer them, e.g. by reading their fields with `as_input()`. [`Client`]
+/// implements this by sending them to S3.
+///
+/// [`MultipartUpload::new`]: crate::MultipartUpload::new
+pub trait MpuClient: Send + Sync {
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>;
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>>;
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>;
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>;
+}
+
+impl MpuClient for Client {
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+    {
+        Box::pin(request.send())
+    }
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+        Box::pin(request.send())
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>
+    {
+        Box::pin(request.send())
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+    {
+        Box::pin(request.send())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MpuClient;
+    use crate::{InvalidPartSize, MultipartUpload, ObjectTooLarge, PartError};
+    use aws_sdk_s3::config::Region;
+    use aws_sdk_s3::error::SdkError;
+    use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+    use aws_sdk_s3::operation::abort_multipart_upload::{
+        AbortMultipartUploadError, AbortMultipartUploadOutput,
+    };
+    use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+    use aws_sdk_s3::operation::complete_multipart_upload::{
+        CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+    };
+    use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+    use aws_sdk_s3::operation::create_multipart_upload::{
+        CreateMultipartUploadError, CreateMultipartUploadOutput,
+    };
+    use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+    use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+    use aws_sdk_s3::primitives::ByteStream;
+    use aws_sdk_s3::{Client, Config};
+    use bytes::Bytes;
+    use futures::future::{self, BoxFuture};
+    use std::convert::Infallible;
+    use std::ops::RangeInclusive;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::Poll;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Double {
+        failing_part: Option<i32>,
+        // CreateMultipartUpload fails unless this is set while it is pending
+        body_read: Option<Arc<AtomicBool>>,
+        parts: Mutex<Vec<(i32, i64)>>,
+        content_md5s: Mutex<Vec<Option<String>>>,
+        content_type: Mutex<Option<String>>,
+        completed: Mutex<Vec<i32>>,
+    }
+
+    impl MpuClient for Double {
+        fn create_multipart_upload(
+            &self,
+            request: CreateMultipartUploadFluentBuilder,
+        ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+        {
+            *self.content_type.lock().unwrap() = request.get_content_type().clone();
+            let mut polls = 0;
+            Box::pin(future::poll_fn(move |cx| match &self.body_read {
+                Some(body_read) if !body_read.load(Ordering::SeqCst) => {
+                    polls += 1;
+                    if polls > 100 {
+                        Poll::Ready(Err(SdkError::construction_failure("body not read")))
+                    } else {
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                }
+                _ => Poll::Ready(Ok(CreateMultipartUploadOutput::builder()
+                    .upload_id("upload")
+                    .build())),
+            }))
+        }
+
+        fn upload_part(
+            &self,
+            request: UploadPartFluentBuilder,
+        ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+            let part_number = request.get_part_number().unwrap();
+            if self.failing_part == Some(part_number) {
+                return Box::pin(future::err(SdkError::construction_failure("failing part")));
+            }
+            self.parts
+                .lock()
+                .unwrap()
+                .push((part_number, request.get_content_length().unwrap()));
+            self.content_md5s
+                .lock()
+                .unwrap()
+                .push(request.get_content_md5().clone());
+            Box::pin(future::ok(
+                UploadPartOutput::builder()
+                    .e_tag(format!("\"{part_number}\""))
+                    .build(),
+            ))
+        }
+
+        fn complete_multipart_upload(
+            &self,
+            request: CompleteMultipartUploadFluentBuilder,
+        ) -> BoxFuture<
+            '_,
+            Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>,
+        > {
+            *self.completed.lock().unwrap() = request
+                .get_multipart_upload()
+                .as_ref()
+                .unwrap()
+                .parts()
+                .iter()
+                .map(|completed_part| completed_part.part_number.unwrap())
+                .collect();
+            Box::pin(future::ok(CompleteMultipartUploadOutput::builder().build()))
+        }
+
+        fn abort_multipart_upload(
+            &self,
+            _: AbortMultipartUploadFluentBuilder,
+        ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+        {
+            Box::pin(future::ok(AbortMultipartUploadOutput::builder().build()))
+        }
+    }
+
+    fn client() -> Client {
+        Client::from_conf(
+            Config::builder()
+                .behavior_version_latest()
+                .region(Region::from_static("test"))
+                .build(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_double() {
+        let double = Arc::new(Double::default());
+        let mut output = MultipartUpload::new(&client())
+            .mpu_client(double.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.upload_id.as_deref(), Some("upload"));
+        assert_eq!(output.content_length, 25);
+        assert!(output
+            .parts
+            .iter()
+            .all(|uploaded_part| uploaded_part.attempts == 1));
+        let stats = output.stats();
+        assert_eq!(stats.duration, output.duration);
+        assert!(stats.min_part_duration <= stats.mean_part_duration);
+        assert!(stats.mean_part_duration <= stats.max_part_duration);
+        assert!(stats.max_part_duration.unwrap() <= output.duration);
+        output.duration = Duration::ZERO;
+        assert_eq!(output.stats().bytes_per_sec, 0.);
+
+        let mut parts = double.parts.lock().unwrap().clone();
+        parts.sort();
+        assert_eq!(parts, [(1, 10), (2, 10), (3, 5)]);
+        assert_eq!(*double.completed.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_double_failing_part() {
+        let double = Arc::new(Double {
+            failing_part: Some(2),
+            ..Double::default()
+        });
+        let err = MultipartUpload::new(&client())
+            .mpu_client(double.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.upload_id.as_deref(), Some("upload"));
+        assert!(err.abort.is_some());
+        assert_eq!(
+            err.error
+                .downcast_ref::<PartError<SdkError<UploadPartError>>>()
+                .unwrap()
+                .part_number,
+            2,
+        );
+        assert!(double.completed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_double_invalid_part_size() {
+        let double = Arc::new(Double::default());
+        for part_size in [
+            10..=10,
+            0..=(5 << 20),
+            RangeInclusive::new(6 << 20, 5 << 20),
+        ] {
+            let err = MultipartUpload::new(&client())
+                .mpu_client(double.clone())
+                .bucket("bucket")
+                .key("key")
+                .body(ByteStream::from_static(&[0; 25]))
+                .send::<anyhow::Error>(part_size.clone(), None)
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.error
+                    .downcast_ref::<InvalidPartSize>()
+                    .unwrap()
+                    .part_size,
+                part_size,
+            );
+        }
+        assert!(double.parts.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_double_pipelined_create() {
+        let body_read = Arc::new(AtomicBool::new(false));
+        let double = Arc::new(Double {
+            body_read: Some(body_read.clone()),
+            ..Double::default()
+        });
+        let mut chunks = vec![Bytes::from_static(&[0; 10]), Bytes::from_static(&[1; 5])];
+        let body = futures::stream::poll_fn(move |_| {
+            body_read.store(true, Ordering::SeqCst);
+            Poll::Ready((!chunks.is_empty()).then(|| Ok::<_, Infallible>(chunks.remove(0))))
+        });
+        let output = MultipartUpload::new(&client())
+            .mpu_client(double)
+            .bucket("bucket")
+            .key("key")
+            .body_stream(body)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(output.parts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_double_send_content_md5() {
+        let double = Arc::new(Double::default());
+        let output = MultipartUpload::new(&client())
+            .mpu_client(double.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .send_content_md5(false)
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(*double.content_md5s.lock().unwrap(), [None, None, None]);
+        assert!(output
+            .parts
+            .iter()
+            .all(|uploaded_part| uploaded_part.info.content_md5.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_double_object_too_large() {
+        let double = Arc::new(Double::default());
+        let err = MultipartUpload::new(&client())
+            .mpu_client(double.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .max_object_size(20)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.error
+                .downcast_ref::<ObjectTooLarge>()
+                .unwrap()
+                .max_object_size,
+            20,
+        );
+        assert!(double
+            .parts
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|&(part_number, _)| part_number < 3));
+        assert!(double.completed.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_double_tracing() {
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Clone, Default)]
+        struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+        impl Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut names = self.0.lock().unwrap();
+                names.push(span.metadata().name());
+                Id::from_u64(names.len() as _)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = Spans::default();
+        let _guard = tracing::subscriber::set_default(spans.clone());
+        MultipartUpload::new(&client())
+            .mpu_client(Arc::new(Double::default()))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            *spans.0.lock().unwrap(),
+            [
+                "create_multipart_upload",
+                "upload_part",
+                "upload_part",
+                "upload_part",
+                "complete_multipart_upload",
+            ],
+        );
+    }
+
+    #[cfg(feature = "mime_guess")]
+    #[tokio::test]
+    async fn test_double_content_type() {
+        for (key, content_type, expected) in [
+            ("key.json", None, Some("application/json")),
+            ("key.tar.gz", None, Some("application/gzip")),
+            ("key", None, None),
+            ("key.json", Some("text/plain"), Some("text/plain")),
+        ] {
+            let double = Arc::new(Double::default());
+            let mut upload = MultipartUpload::new(&client())
+                .mpu_client(double.clone())
+                .bucket("bucket")
+                .key(key)
+                .body(ByteStream::from_static(&[0; 25]))
+                .part_size_limits(10..=10);
+            if let Some(content_type) = content_type {
+                upload = upload.content_type(content_type);
+            }
+            upload.send::<anyhow::Error>(10..=10, None).await.unwrap();
+            assert_eq!(double.content_type.lock().unwrap().as_deref(), expected);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::background::{BackgroundUpload, BoxError};
+use crate::MultipartUpload;
+use ::object_store::{PutPayload, PutResult, UploadPart};
+use futures::TryFutureExt;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+
+const STORE: &str = "S3";
+
+/// An [`object_store::MultipartUpload`](::object_store::MultipartUpload) that uploads the parts
+/// put to it through a [`MultipartUpload`], so that its part sizes, hashing and concurrency
+/// limit apply regardless of the sizes of the payloads.
+///
+/// The payloads are uploaded by a task spawned on the tokio runtime. The future returned by
+/// `put_part` resolves once its payload has been taken into a part, not once the part has been
+/// uploaded; `complete` waits for the parts and reports their failures.
+#[derive(Debug)]
+pub struct ObjectStoreUpload(BackgroundUpload);
+
+impl ObjectStoreUpload {
+    /// Creates the multipart upload and starts uploading the parts within `part_size`.
+    pub async fn new(
+        upload: MultipartUpload,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> ::object_store::Result<Self> {
+        BackgroundUpload::start(upload, part_size, concurrency_limit)
+            .await
+            .map(Self)
+            .map_err(generic)
+    }
+}
+
+#[async_trait::async_trait]
+impl ::object_store::MultipartUpload for ObjectStoreUpload {
+    fn put_part(&mut self, data: PutPayload) -> UploadPart {
+        Box::pin(self.0.put(data.into_iter().collect()).map_err(generic))
+    }
+
+    async fn complete(&mut self) -> ::object_store::Result<PutResult> {
+        let output = self.0.complete().await.map_err(generic)?;
+        Ok(PutResult {
+            e_tag: output.output.e_tag,
+            version: output.output.version_id,
+            extensions: Default::default(),
+        })
+    }
+
+    async fn abort(&mut self) -> ::object_store::Result<()> {
+        self.0.abort().await.map_err(generic)
+    }
+}
+
+fn generic(source: BoxError) -> ::object_store::Error {
+    ::object_store::Error::Generic {
+        store: STORE,
+        source,
+    }
+}
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::ObjectStoreUpload;
+    use crate::{FakeS3, MultipartUpload};
+    use object_store::MultipartUpload as _;
+
+    fn upload(fake: &FakeS3) -> MultipartUpload {
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .part_size_limits(10..=10)
+    }
+
+    #[tokio::test]
+    async fn test_object_store_upload() {
+        let fake = FakeS3::new();
+        let mut upload = ObjectStoreUpload::new(upload(&fake), 10..=10, None)
+            .await
+            .unwrap();
+        let parts = [
+            upload.put_part(vec![0; 7].into()),
+            upload.put_part(vec![1; 7].into()),
+            upload.put_part(vec![2; 11].into()),
+        ];
+        futures::future::try_join_all(parts).await.unwrap();
+        let output = upload.complete().await.unwrap();
+        assert!(output.e_tag.is_some());
+        assert_eq!(
+            fake.object("bucket", "key").unwrap(),
+            [vec![0; 7], vec![1; 7], vec![2; 11]].concat(),
+        );
+        let parts = fake
+            .requests()
+            .into_iter()
+            .filter(|request| request.operation() == "UploadPart")
+            .count();
+        assert_eq!(parts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_object_store_upload_abort() {
+        let fake = FakeS3::new();
+        let mut upload = ObjectStoreUpload::new(upload(&fake), 10..=10, None)
+            .await
+            .unwrap();
+        upload.put_part(vec![0; 25].into()).await.unwrap();
+        upload.abort().await.unwrap();
+        assert!(fake.uploads().is_empty());
+        assert!(fake.object("bucket", "key").is_none());
+    }
+}
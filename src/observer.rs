@@ -0,0 +1,200 @@
+use crate::{attempts, MultipartUploadOutput, PartInfo, UploadedPart};
+use aws_sdk_s3::config::interceptors::{
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextRef,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use std::fmt;
+use std::sync::Arc;
+
+/// Receives the events of an upload, e.g. for logging, metrics and progress at once.
+///
+/// Attach it with [`MultipartUpload::observer`](crate::MultipartUpload::observer). Every
+/// method does nothing by default, so an observer implements only the events it needs. The
+/// methods are called from the tasks of the upload and should return quickly.
+pub trait MpuObserver: Send + Sync {
+    /// Before the `UploadPart` or `UploadPartCopy` request of a part is sent.
+    fn on_part_started(&self, part: &PartInfo) {
+        let _ = part;
+    }
+
+    /// Before the client retries the request of part `part_number`, with `attempt` counting the
+    /// first request as 1.
+    fn on_part_retried(&self, part_number: i32, attempt: usize) {
+        let _ = (part_number, attempt);
+    }
+
+    fn on_part_completed(&self, part: &UploadedPart) {
+        let _ = part;
+    }
+
+    fn on_completed(&self, output: &MultipartUploadOutput) {
+        let _ = output;
+    }
+
+    /// When the upload fails after `CreateMultipartUpload`. No abort is sent: its parts stay in
+    /// S3 until the `abort` request of the
+    /// [`MultipartUploadError`](crate::MultipartUploadError) is sent.
+    fn on_given_up(&self, upload_id: Option<&str>) {
+        let _ = upload_id;
+    }
+}
+
+/// Reports the retries of `UploadPart` and `UploadPartCopy` requests, which happen within the
+/// client.
+#[derive(Clone)]
+pub(crate) struct Retries(pub(crate) Arc<dyn MpuObserver>);
+
+impl fmt::Debug for Retries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Retries").finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Attempts {
+    part_number: i32,
+    attempts: usize,
+}
+
+impl Storable for Attempts {
+    type Storer = StoreReplace<Self>;
+}
+
+impl Intercept for Retries {
+    fn name(&self) -> &'static str {
+        "MpuObserver"
+    }
+
+    fn read_before_execution(
+        &self,
+        context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some((_, part_number)) = attempts::part_of(context) {
+            cfg.interceptor_state().store_put(Attempts {
+                part_number,
+                attempts: 0,
+            });
+        }
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _: &BeforeTransmitInterceptorContextRef<'_>,
+        _: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(mut attempts) = cfg.load::<Attempts>().cloned() {
+            attempts.attempts += 1;
+            if attempts.attempts > 1 {
+                self.0
+                    .on_part_retried(attempts.part_number, attempts.attempts);
+            }
+            cfg.interceptor_state().store_put(attempts);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::MpuObserver;
+    use crate::fake::Flaky;
+    use crate::{
+        FakeS3, Fault, FaultInjector, MultipartUpload, MultipartUploadOutput, PartInfo,
+        UploadedPart,
+    };
+    use aws_sdk_s3::primitives::ByteStream;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Recorder {
+        fn push(&self, event: String) {
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn events(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl MpuObserver for Recorder {
+        fn on_part_started(&self, part: &PartInfo) {
+            self.push(format!("started {}", part.number));
+        }
+
+        fn on_part_retried(&self, part_number: i32, attempt: usize) {
+            self.push(format!("retried {part_number} ({attempt})"));
+        }
+
+        fn on_part_completed(&self, part: &UploadedPart) {
+            self.push(format!("completed {}", part.info.number));
+        }
+
+        fn on_completed(&self, output: &MultipartUploadOutput) {
+            self.push(format!("completed {} bytes", output.content_length));
+        }
+
+        fn on_given_up(&self, upload_id: Option<&str>) {
+            self.push(format!("given up {}", upload_id.unwrap_or_default()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer() {
+        let client = Flaky::new(1).client(3);
+        let recorder = Arc::new(Recorder::default());
+        MultipartUpload::new(&client)
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .observer(recorder.clone())
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            recorder.events(),
+            [
+                "started 1",
+                "retried 1 (2)",
+                "completed 1",
+                "started 2",
+                "completed 2",
+                "started 3",
+                "completed 3",
+                "completed 25 bytes",
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observer_failure() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let recorder = Arc::new(Recorder::default());
+        MultipartUpload::new(&client)
+            .mpu_client(Arc::new(
+                FaultInjector::new(client.clone()).part(2, Fault::error(500, "InternalError")),
+            ))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .observer(recorder.clone())
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            recorder.events(),
+            ["started 1", "completed 1", "started 2", "given up upload-1"],
+        );
+    }
+}
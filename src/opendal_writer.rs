@@ -0,0 +1,101 @@
+use crate::background::{BackgroundUpload, BoxError};
+use crate::MultipartUpload;
+use opendal::raw::oio;
+use opendal::{Buffer, Error, ErrorKind, Metadata, MetadataBuilder};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+
+/// An [`oio::Write`] that uploads what is written to it through a [`MultipartUpload`], so that
+/// an OpenDAL service or layer can hand large writes to its part sizes, hashing, concurrency
+/// limit and [`MpuClient`](crate::MpuClient), e.g. one reporting progress.
+///
+/// The parts are uploaded by a task spawned on the tokio runtime. `write` returns once its
+/// buffer has been taken into a part; `close` waits for the parts and reports their failures.
+/// `abort` aborts the multipart upload.
+#[derive(Debug)]
+pub struct OpendalWriter(BackgroundUpload);
+
+impl OpendalWriter {
+    /// Creates the multipart upload and starts uploading the parts within `part_size`.
+    pub async fn new(
+        upload: MultipartUpload,
+        part_size: RangeInclusive<u64>,
+        concurrency_limit: Option<NonZeroUsize>,
+    ) -> opendal::Result<Self> {
+        BackgroundUpload::start(upload, part_size, concurrency_limit)
+            .await
+            .map(Self)
+            .map_err(unexpected)
+    }
+}
+
+impl oio::Write for OpendalWriter {
+    async fn write(&mut self, bs: Buffer) -> opendal::Result<()> {
+        self.0.put(bs.collect()).await.map_err(unexpected)
+    }
+
+    async fn close(&mut self) -> opendal::Result<Metadata> {
+        let output = self.0.complete().await.map_err(unexpected)?;
+        let mut metadata = MetadataBuilder::file(output.content_length);
+        if let Some(e_tag) = output.output.e_tag {
+            metadata.etag(e_tag);
+        }
+        if let Some(version_id) = output.output.version_id {
+            metadata.version(version_id);
+        }
+        Ok(metadata.build())
+    }
+
+    async fn abort(&mut self) -> opendal::Result<()> {
+        self.0.abort().await.map_err(unexpected)
+    }
+}
+
+fn unexpected(source: BoxError) -> Error {
+    Error::new(ErrorKind::Unexpected, "multipart upload failed")
+        .set_source(anyhow::Error::from_boxed(source))
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::OpendalWriter;
+    use crate::{FakeS3, MultipartUpload};
+    use opendal::raw::oio::Write;
+    use opendal::Buffer;
+
+    fn upload(fake: &FakeS3) -> MultipartUpload {
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .part_size_limits(10..=10)
+    }
+
+    #[tokio::test]
+    async fn test_opendal_writer() {
+        let fake = FakeS3::new();
+        let mut writer = OpendalWriter::new(upload(&fake), 10..=10, None)
+            .await
+            .unwrap();
+        writer.write(Buffer::from(vec![0; 7])).await.unwrap();
+        writer.write(Buffer::from(vec![1; 18])).await.unwrap();
+        let metadata = writer.close().await.unwrap();
+        assert_eq!(metadata.content_length(), 25);
+        assert!(metadata.etag().is_some());
+        assert_eq!(
+            fake.object("bucket", "key").unwrap(),
+            [vec![0; 7], vec![1; 18]].concat(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_opendal_writer_abort() {
+        let fake = FakeS3::new();
+        let mut writer = OpendalWriter::new(upload(&fake), 10..=10, None)
+            .await
+            .unwrap();
+        writer.write(Buffer::from(vec![0; 25])).await.unwrap();
+        writer.abort().await.unwrap();
+        assert!(fake.uploads().is_empty());
+        assert!(fake.object("bucket", "key").is_none());
+    }
+}
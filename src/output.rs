@@ -0,0 +1,94 @@
+use crate::{Checksum, PartInfo};
+use aws_sdk_s3::operation::complete_multipart_upload::CompleteMultipartUploadOutput;
+use aws_sdk_s3::presigning::PresignedRequest;
+use aws_sdk_s3::types::CompletedPart;
+use std::ops::Range;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct MultipartUploadOutput {
+    pub upload_id: Option<String>,
+    pub content_length: u64,
+    /// Uploaded parts in part-number order.
+    pub parts: Vec<UploadedPart>,
+    pub output: CompleteMultipartUploadOutput,
+    /// The digest of the whole object, with
+    /// [`MultipartUpload::object_digest`](crate::MultipartUpload::object_digest).
+    pub object_digest: Option<Checksum>,
+    /// 0 if the upload was created in the first destination, or `n` if it was created in the
+    /// `n`-th [`MultipartUpload::failover`](crate::MultipartUpload::failover).
+    pub destination: usize,
+    /// Time from `CreateMultipartUpload` to the end of `CompleteMultipartUpload`.
+    pub duration: Duration,
+}
+
+impl MultipartUploadOutput {
+    pub fn stats(&self) -> UploadStats {
+        let part_durations = self
+            .parts
+            .iter()
+            .map(|uploaded_part| uploaded_part.duration);
+        UploadStats {
+            duration: self.duration,
+            mean_part_duration: u32::try_from(self.parts.len())
+                .ok()
+                .filter(|len| *len > 0)
+                .map(|len| part_durations.clone().sum::<Duration>() / len),
+            min_part_duration: part_durations.clone().min(),
+            max_part_duration: part_durations.max(),
+            bytes_per_sec: if self.duration.is_zero() {
+                0.
+            } else {
+                self.content_length as f64 / self.duration.as_secs_f64()
+            },
+        }
+    }
+
+    /// Renders the upload of `bucket`/`key` as a JSON object for scripts: the fields of the
+    /// [`MultipartUpload::manifest`](crate::MultipartUpload::manifest) plus `version_id`,
+    /// `checksum`, `destination`, `duration` and [`stats`](Self::stats), and the `duration`
+    /// and `attempts` of each part. Durations are in seconds.
+    pub fn to_json(&self, bucket: &str, key: &str) -> String {
+        crate::manifest::report(bucket, key, self)
+    }
+}
+
+/// Timing of a multipart upload, returned by [`MultipartUploadOutput::stats`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct UploadStats {
+    pub duration: Duration,
+    /// `None` without parts.
+    pub mean_part_duration: Option<Duration>,
+    pub min_part_duration: Option<Duration>,
+    pub max_part_duration: Option<Duration>,
+    /// Effective throughput over [`duration`](Self::duration), or `0` if it is zero.
+    pub bytes_per_sec: f64,
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct UploadedPart {
+    pub info: PartInfo,
+    pub completed_part: CompletedPart,
+    /// Time spent in the `UploadPart` request.
+    pub duration: Duration,
+    /// `UploadPart` requests sent for the part, including retries. `0` for the parts listed by
+    /// [`Initiated::list_parts`].
+    ///
+    /// [`Initiated::list_parts`]: crate::Initiated::list_parts
+    pub attempts: usize,
+}
+
+/// A presigned `UploadPart` request returned by [`Initiated::presign_parts`].
+///
+/// [`Initiated::presign_parts`]: crate::Initiated::presign_parts
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PresignedPart {
+    pub number: i32,
+    /// Byte range of the part within the object.
+    pub range: Range<u64>,
+    pub request: PresignedRequest,
+}
@@ -0,0 +1,36 @@
+use crate::split::Part;
+use crate::Checksum;
+use md5::digest::Output;
+use md5::Md5;
+use std::ops::Range;
+
+/// Metadata of a single part of a multipart upload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PartInfo {
+    pub number: i32,
+    pub len: u64,
+    /// Base64-encoded `Content-MD5` of the part.
+    pub content_md5: Option<String>,
+    pub checksum: Option<Checksum>,
+    /// Extra digests computed for auditing; they are not sent to S3.
+    pub digests: Vec<Checksum>,
+    /// Byte range of the part within the object.
+    pub range: Range<u64>,
+}
+
+pub(crate) type Digest = (Option<Output<Md5>>, Option<Checksum>, Vec<Checksum>);
+
+impl From<&Part<Digest>> for PartInfo {
+    fn from(part: &Part<Digest>) -> Self {
+        let start = part.offset;
+        Self {
+            number: part.part_number as _,
+            len: part.content_length,
+            content_md5: part.digest.0.map(base64::encode),
+            checksum: part.digest.1.clone(),
+            digests: part.digest.2.clone(),
+            range: start..start + part.content_length,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::split::split;
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::{StreamExt, TryStreamExt};
+use md5::digest::Output;
+use md5::{Digest, Md5};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+
+/// A destination that stores a body as numbered parts and commits them at the end, such as
+/// the blocks of an Azure block blob.
+///
+/// [`upload_to_sink`] splits and hashes the body and dispatches the parts, so that a sink only
+/// has to send them.
+pub trait PartSink: Send + Sync {
+    /// Returned by [`Self::put_part`] and passed back to [`Self::commit`], e.g. a block ID.
+    type Receipt: Send;
+    type Output;
+    type Error;
+
+    /// Stores `body` as the part `part_number`, which is 1-based. `content_md5` is the MD5 of
+    /// `body`.
+    fn put_part(
+        &self,
+        part_number: usize,
+        body: Vec<Bytes>,
+        content_md5: Output<Md5>,
+    ) -> BoxFuture<'_, Result<Self::Receipt, Self::Error>>;
+
+    /// Commits the parts. `receipts` are in part-number order.
+    fn commit(
+        &self,
+        receipts: Vec<Self::Receipt>,
+    ) -> BoxFuture<'_, Result<Self::Output, Self::Error>>;
+}
+
+/// Splits `body` into parts within `part_size`, puts them to `sink` with at most
+/// `concurrency_limit` of them in flight and commits them.
+///
+/// Nothing is cleaned up on failure; uncommitted parts are left to the sink, e.g. Azure
+/// discards uncommitted blocks after a week.
+pub async fn upload_to_sink<S, E>(
+    sink: &S,
+    mut body: ByteStream,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<S::Output, E>
+where
+    S: PartSink,
+    E: From<S::Error> + From<ByteStreamError>,
+{
+    let body = futures::stream::poll_fn(move |cx| Pin::new(&mut body).poll_next(cx));
+    let receipts = split(body, part_size, Md5::new())
+        .map(|part| async move {
+            let part = part?;
+            sink.put_part(part.part_number, part.body, part.digest)
+                .await
+                .map_err(E::from)
+        })
+        .buffered(concurrency_limit.map_or(usize::MAX, NonZeroUsize::get))
+        .try_collect()
+        .await?;
+    sink.commit(receipts).await.map_err(E::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{upload_to_sink, PartSink};
+    use aws_sdk_s3::primitives::ByteStream;
+    use bytes::Bytes;
+    use futures::future::{self, BoxFuture};
+    use md5::digest::Output;
+    use md5::{Digest, Md5};
+    use std::collections::BTreeMap;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Blocks(Mutex<BTreeMap<usize, Bytes>>);
+
+    impl PartSink for Blocks {
+        type Receipt = usize;
+        type Output = Vec<u8>;
+        type Error = Infallible;
+
+        fn put_part(
+            &self,
+            part_number: usize,
+            body: Vec<Bytes>,
+            content_md5: Output<Md5>,
+        ) -> BoxFuture<'_, Result<Self::Receipt, Self::Error>> {
+            let body = Bytes::from(body.concat());
+            assert_eq!(content_md5, Md5::digest(&body));
+            self.0.lock().unwrap().insert(part_number, body);
+            Box::pin(future::ok(part_number))
+        }
+
+        fn commit(
+            &self,
+            receipts: Vec<Self::Receipt>,
+        ) -> BoxFuture<'_, Result<Self::Output, Self::Error>> {
+            let blocks = self.0.lock().unwrap();
+            Box::pin(future::ok(
+                receipts
+                    .iter()
+                    .flat_map(|part_number| blocks[part_number].clone())
+                    .collect(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_to_sink() {
+        let body = (0..25).collect::<Vec<u8>>();
+        let output = upload_to_sink::<_, anyhow::Error>(
+            &Blocks::default(),
+            ByteStream::from(body.clone()),
+            10..=10,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, body);
+    }
+}
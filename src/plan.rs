@@ -0,0 +1,97 @@
+use crate::MAX_PARTS;
+use std::ops::RangeInclusive;
+
+/// A part of a planned upload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PartPlan {
+    /// 1-based part number.
+    pub part_number: usize,
+    /// Byte offset of the part within the body.
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Splits a body of `total_len` bytes into the parts [`Initiated::upload_path`] and
+/// [`Initiated::copy_parts`] would upload, without any I/O.
+///
+/// The part size is the smallest size within `part_size` that keeps the number of parts within
+/// the [`MAX_PARTS`] S3 accepts. An empty body has no parts.
+///
+/// [`Initiated::upload_path`]: crate::Initiated::upload_path
+/// [`Initiated::copy_parts`]: crate::Initiated::copy_parts
+pub fn plan_parts(total_len: u64, part_size: RangeInclusive<u64>) -> Vec<PartPlan> {
+    plan_parts_within(total_len, part_size, MAX_PARTS)
+}
+
+/// [`plan_parts`] for a provider that accepts at most `max_parts` parts.
+pub(crate) fn plan_parts_within(
+    total_len: u64,
+    part_size: RangeInclusive<u64>,
+    max_parts: usize,
+) -> Vec<PartPlan> {
+    let size = part_size_for(total_len, &part_size, max_parts);
+    (0..total_len.div_ceil(size))
+        .map(|i| {
+            let offset = i * size;
+            PartPlan {
+                part_number: i as usize + 1,
+                offset,
+                len: size.min(total_len - offset),
+            }
+        })
+        .collect()
+}
+
+/// The part size [`plan_parts`] uses for a body of `len` bytes.
+pub(crate) fn part_size_for(len: u64, part_size: &RangeInclusive<u64>, max_parts: usize) -> u64 {
+    len.div_ceil(max_parts as u64)
+        .clamp(*part_size.start(), *part_size.end())
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan_parts, plan_parts_within, PartPlan};
+
+    #[test]
+    fn test_plan_parts() {
+        assert_eq!(
+            plan_parts(25, 10..=20),
+            [
+                PartPlan {
+                    part_number: 1,
+                    offset: 0,
+                    len: 10,
+                },
+                PartPlan {
+                    part_number: 2,
+                    offset: 10,
+                    len: 10,
+                },
+                PartPlan {
+                    part_number: 3,
+                    offset: 20,
+                    len: 5,
+                },
+            ],
+        );
+        assert!(plan_parts(0, 10..=20).is_empty());
+    }
+
+    #[test]
+    fn test_plan_parts_max_parts() {
+        let plan = plan_parts(200_001, 10..=100);
+        assert_eq!(plan.len(), 9524);
+        assert!(plan.iter().all(|part| part.len <= 21));
+        assert_eq!(
+            plan.last().unwrap().offset + plan.last().unwrap().len,
+            200_001
+        );
+
+        // the upper bound wins over the limit on the number of parts
+        assert_eq!(plan_parts(200_001, 10..=20).len(), 10001);
+
+        assert_eq!(plan_parts_within(200_001, 10..=100, 4000).len(), 3922);
+    }
+}
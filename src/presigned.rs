@@ -0,0 +1,179 @@
+use crate::initiated::collect;
+use crate::split::{split, Part};
+use crate::{
+    MultipartUploadError, PartError, PartInfo, PresignedError, PresignedPart, RequestIds,
+    UploadedPart,
+};
+use aws_sdk_s3::config::{AsyncSleep, SharedAsyncSleep};
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use aws_sdk_s3::types::CompletedPart;
+use bytes::Bytes;
+use futures::StreamExt;
+use http_body_util::Full;
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::client::legacy::Client;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+const MAX_ATTEMPTS: usize = 3;
+
+/// Uploads `body` with presigned `UploadPart` requests, e.g. those of
+/// [`Initiated::presign_parts`], so that the uploader needs no credentials.
+///
+/// `body` is split at the byte ranges of `parts`, which have to cover it from the first byte.
+/// Requests that fail to connect or get a 5xx status are retried after backing off with
+/// `sleep_impl`, which keeps this independent of the async runtime. The uploaded parts are
+/// returned in part-number order; completing the upload is left to the holder of the
+/// credentials.
+///
+/// [`Initiated::presign_parts`]: crate::Initiated::presign_parts
+pub async fn upload_presigned<C, E>(
+    client: &Client<C, Full<Bytes>>,
+    mut parts: Vec<PresignedPart>,
+    mut body: ByteStream,
+    sleep_impl: SharedAsyncSleep,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<Vec<UploadedPart>, MultipartUploadError<E>>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+    E: From<PartError<PresignedError>> + From<ByteStreamError>,
+{
+    parts.sort_by_key(|presigned_part| presigned_part.number);
+    let size = parts.first().map_or(1, |presigned_part| {
+        (presigned_part.range.end - presigned_part.range.start).max(1)
+    });
+    let mut presigned_parts = parts.into_iter();
+    let body = futures::stream::poll_fn(move |cx| Pin::new(&mut body).poll_next(cx));
+    let sleep_impl = &sleep_impl;
+    let uploads = split(body, size..=size, ()).map(|part| {
+        let presigned_part = presigned_parts.next();
+        async move {
+            let part = part.map_err(|err| (err.into(), RequestIds::default()))?;
+            let range = part.offset..part.offset + part.content_length;
+            match presigned_part {
+                Some(presigned_part) if presigned_part.range == range => {
+                    upload_part(client, sleep_impl, presigned_part, part).await
+                }
+                presigned_part => Err((
+                    PartError {
+                        part_number: presigned_part
+                            .map_or(part.part_number as _, |presigned_part| {
+                                presigned_part.number
+                            }),
+                        range,
+                        attempts: 0,
+                        request_ids: RequestIds::default(),
+                        source: PresignedError::Range,
+                    }
+                    .into(),
+                    RequestIds::default(),
+                )),
+            }
+        }
+    });
+    let (mut uploaded_parts, errors, _) = collect(uploads, concurrency_limit, true, None).await;
+    uploaded_parts.sort_by_key(|uploaded_part| uploaded_part.info.number);
+
+    let mut errors = errors.into_iter();
+    let err = match (errors.next(), presigned_parts.next()) {
+        (Some((err, request_ids)), _) => MultipartUploadError::new(err).request_ids(request_ids),
+        (None, Some(presigned_part)) => MultipartUploadError::new(PartError {
+            part_number: presigned_part.number,
+            range: presigned_part.range,
+            attempts: 0,
+            request_ids: RequestIds::default(),
+            source: PresignedError::Range,
+        }),
+        (None, None) => return Ok(uploaded_parts),
+    };
+    Err(MultipartUploadError {
+        additional_errors: errors.map(|(err, _)| err).collect(),
+        uploaded_parts,
+        ..err
+    })
+}
+
+async fn upload_part<C, E>(
+    client: &Client<C, Full<Bytes>>,
+    sleep_impl: &SharedAsyncSleep,
+    presigned_part: PresignedPart,
+    part: Part<()>,
+) -> Result<UploadedPart, (E, RequestIds)>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+    E: From<PartError<PresignedError>>,
+{
+    let body = part.body.concat().into();
+    let start = Instant::now();
+    let mut attempts = 0;
+    let (source, request_ids) = loop {
+        attempts += 1;
+        let request = presigned_part
+            .request
+            .make_http_1x_request(Full::new(Bytes::clone(&body)));
+        let (source, request_ids) = match client.request(request).await {
+            Ok(response) if response.status().is_success() => {
+                let completed_part = CompletedPart::builder()
+                    .set_e_tag(
+                        response
+                            .headers()
+                            .get("etag")
+                            .and_then(|value| value.to_str().ok())
+                            .map(ToOwned::to_owned),
+                    )
+                    .part_number(presigned_part.number)
+                    .build();
+                return Ok(UploadedPart {
+                    info: PartInfo {
+                        number: presigned_part.number,
+                        len: part.content_length,
+                        content_md5: None,
+                        checksum: None,
+                        digests: Vec::new(),
+                        range: presigned_part.range,
+                    },
+                    completed_part,
+                    duration: start.elapsed(),
+                    attempts,
+                });
+            }
+            Ok(response) => {
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(ToOwned::to_owned)
+                };
+                let request_ids = RequestIds {
+                    request_id: header("x-amz-request-id"),
+                    extended_request_id: header("x-amz-id-2"),
+                };
+                (PresignedError::Status(response.status()), request_ids)
+            }
+            Err(err) => (PresignedError::Http(err), RequestIds::default()),
+        };
+        let retryable = match &source {
+            PresignedError::Status(status) => status.is_server_error(),
+            _ => true,
+        };
+        if !retryable || attempts >= MAX_ATTEMPTS {
+            break (source, request_ids);
+        }
+        sleep_impl
+            .sleep(Duration::from_millis(100 << attempts))
+            .await;
+    };
+    Err((
+        PartError {
+            part_number: presigned_part.number,
+            range: presigned_part.range,
+            attempts,
+            request_ids: request_ids.clone(),
+            source,
+        }
+        .into(),
+        request_ids,
+    ))
+}
@@ -0,0 +1,128 @@
+use crate::MpuClient;
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::abort_multipart_upload::builders::AbortMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::abort_multipart_upload::{
+    AbortMultipartUploadError, AbortMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::complete_multipart_upload::builders::CompleteMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::complete_multipart_upload::{
+    CompleteMultipartUploadError, CompleteMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::create_multipart_upload::builders::CreateMultipartUploadFluentBuilder;
+use aws_sdk_s3::operation::create_multipart_upload::{
+    CreateMultipartUploadError, CreateMultipartUploadOutput,
+};
+use aws_sdk_s3::operation::upload_part::builders::UploadPartFluentBuilder;
+use aws_sdk_s3::operation::upload_part::{UploadPartError, UploadPartOutput};
+use futures::future::BoxFuture;
+use indicatif::{ProgressBar, ProgressStyle};
+
+const TEMPLATE: &str = "{bytes}/{total_bytes} [{wide_bar}] {bytes_per_sec}, ETA {eta}";
+
+/// An [`MpuClient`] that advances a [`ProgressBar`] by the bytes of each uploaded part and
+/// finishes it on `CompleteMultipartUpload`, or abandons it on `AbortMultipartUpload`.
+///
+/// The bar is styled to show bytes and ETA. Its length is left to the caller, e.g. the size of
+/// the file to upload.
+#[derive(Debug)]
+pub struct Progress<C> {
+    inner: C,
+    bar: ProgressBar,
+}
+
+impl<C> Progress<C>
+where
+    C: MpuClient,
+{
+    pub fn new(inner: C, bar: ProgressBar) -> Self {
+        bar.set_style(ProgressStyle::with_template(TEMPLATE).unwrap());
+        Self { inner, bar }
+    }
+
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+}
+
+impl<C> MpuClient for Progress<C>
+where
+    C: MpuClient,
+{
+    fn create_multipart_upload(
+        &self,
+        request: CreateMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CreateMultipartUploadOutput, SdkError<CreateMultipartUploadError>>>
+    {
+        self.inner.create_multipart_upload(request)
+    }
+
+    fn upload_part(
+        &self,
+        request: UploadPartFluentBuilder,
+    ) -> BoxFuture<'_, Result<UploadPartOutput, SdkError<UploadPartError>>> {
+        let content_length = request.get_content_length().unwrap_or_default();
+        Box::pin(async move {
+            let output = self.inner.upload_part(request).await;
+            if output.is_ok() {
+                self.bar.inc(content_length as _);
+            }
+            output
+        })
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        request: CompleteMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<CompleteMultipartUploadOutput, SdkError<CompleteMultipartUploadError>>>
+    {
+        Box::pin(async move {
+            let output = self.inner.complete_multipart_upload(request).await;
+            if output.is_ok() {
+                self.bar.finish();
+            }
+            output
+        })
+    }
+
+    fn abort_multipart_upload(
+        &self,
+        request: AbortMultipartUploadFluentBuilder,
+    ) -> BoxFuture<'_, Result<AbortMultipartUploadOutput, SdkError<AbortMultipartUploadError>>>
+    {
+        Box::pin(async move {
+            let output = self.inner.abort_multipart_upload(request).await;
+            if output.is_ok() {
+                self.bar.abandon();
+            }
+            output
+        })
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::Progress;
+    use crate::{FakeS3, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStream;
+    use indicatif::ProgressBar;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_progress() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let progress = Arc::new(Progress::new(client.clone(), ProgressBar::hidden()));
+        progress.bar().set_length(25);
+        MultipartUpload::new(&client)
+            .mpu_client(progress.clone())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(progress.bar().position(), 25);
+        assert!(progress.bar().is_finished());
+    }
+}
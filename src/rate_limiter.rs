@@ -0,0 +1,79 @@
+use std::num::NonZeroU64;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A token bucket shared by uploads to cap their combined outbound bandwidth.
+///
+/// Each `UploadPart` request takes its content length from the bucket before it is sent, so
+/// the rate is enforced per part rather than per byte and retried requests are not counted.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_second: f64,
+    // available tokens, which go negative while requests wait, and the time they were counted
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Allows bursts of up to one second worth of bytes.
+    pub fn new(bytes_per_second: NonZeroU64) -> Self {
+        let bytes_per_second = bytes_per_second.get() as f64;
+        Self {
+            bytes_per_second,
+            state: Mutex::new((bytes_per_second, Instant::now())),
+        }
+    }
+
+    /// Takes `bytes` tokens and returns how long to wait before sending them.
+    pub(crate) fn reserve(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let refilled = now.duration_since(state.1).as_secs_f64() * self.bytes_per_second;
+        let tokens = (state.0 + refilled).min(self.bytes_per_second) - bytes as f64;
+        *state = (tokens, now);
+        if tokens < 0. {
+            Duration::from_secs_f64(-tokens / self.bytes_per_second)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reserve() {
+        let rate_limiter = RateLimiter::new(1000.try_into().unwrap());
+        assert_eq!(rate_limiter.reserve(600), Duration::ZERO);
+        let wait = rate_limiter.reserve(1000);
+        assert!(wait > Duration::from_millis(500) && wait <= Duration::from_millis(600));
+        let wait = rate_limiter.reserve(1000);
+        assert!(wait > Duration::from_millis(1500) && wait <= Duration::from_millis(1600));
+    }
+
+    // the client of `FakeS3` has no sleep_impl of its own
+    #[cfg(all(feature = "test-util", feature = "tokio"))]
+    #[tokio::test]
+    async fn test_rate_limiter_upload() {
+        use crate::{FakeS3, MultipartUpload};
+        use aws_sdk_s3::primitives::ByteStream;
+        use std::sync::Arc;
+        use std::time::Instant;
+
+        let fake = FakeS3::new();
+        let start = Instant::now();
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 30]))
+            .part_size_limits(10..=10)
+            .rate_limiter(Arc::new(RateLimiter::new(20.try_into().unwrap())))
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        // the third part waits for 10 bytes at 20 bytes per second
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{MultipartUploadError, MultipartUploadOutput};
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::Sink;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+pub(crate) type Upload<E> =
+    Pin<Box<dyn Future<Output = Result<MultipartUploadOutput, MultipartUploadError<E>>> + Send>>;
+
+/// A [`Sink`] that uploads the chunks sent to it. Closing the sink completes the upload.
+pub struct S3Sink<E> {
+    tx: Option<mpsc::Sender<Bytes>>,
+    upload: Option<Upload<E>>,
+    output: Option<MultipartUploadOutput>,
+}
+
+// No field is pinned structurally; the upload future is boxed.
+impl<E> Unpin for S3Sink<E> {}
+
+impl<E> S3Sink<E> {
+    pub(crate) fn new(tx: mpsc::Sender<Bytes>, upload: Upload<E>) -> Self {
+        Self {
+            tx: Some(tx),
+            upload: Some(upload),
+            output: None,
+        }
+    }
+
+    /// The output of the upload once the sink has been closed.
+    pub fn output(&self) -> Option<&MultipartUploadOutput> {
+        self.output.as_ref()
+    }
+
+    pub fn into_output(self) -> Option<MultipartUploadOutput> {
+        self.output
+    }
+
+    fn poll_upload(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MultipartUploadError<E>>> {
+        if let Some(upload) = &mut self.upload {
+            let result = ready!(upload.as_mut().poll(cx));
+            self.upload = None;
+            self.tx = None;
+            self.output = Some(result?);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<E> Sink<Bytes> for S3Sink<E> {
+    type Error = MultipartUploadError<E>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let tx = this.tx.as_mut().expect("S3Sink used after it finished");
+        if let Poll::Ready(Ok(())) = tx.poll_ready(cx) {
+            return Poll::Ready(Ok(()));
+        }
+        // the upload has to make progress to free the channel
+        ready!(this.poll_upload(cx))?;
+        unreachable!("the upload finished before the body ended")
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        let tx = self.get_mut().tx.as_mut();
+        // the receiver lives as long as the upload, whose failure is reported by `poll_ready`
+        let _ = tx.expect("S3Sink used after it finished").start_send(item);
+        Ok(())
+    }
+
+    /// Reports an upload failure early. It does not wait for the parts to be uploaded.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut().poll_upload(cx) {
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.tx = None;
+        this.poll_upload(cx)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::plan::part_size_for;
+use crate::split::PartHasher;
+use crate::{checksum, Checksum, ETagHasher, MultipartUploadOutput, READER_CAPACITY};
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
+use aws_sdk_s3::types::ChecksumType;
+use md5::{Digest, Md5};
+use std::io;
+use std::num::NonZeroU64;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// The result of [`MultipartUpload::send_if_changed`](crate::MultipartUpload::send_if_changed).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
+pub enum SendOutput {
+    Uploaded(MultipartUploadOutput),
+    /// The object already matched the file. Holds its `HeadObject` output.
+    Skipped(HeadObjectOutput),
+}
+
+/// Whether the object described by `head` has the size and the full-object checksum or ETag
+/// of the file at `path`, as uploaded with `part_size`.
+pub(crate) async fn is_identical(
+    head: &HeadObjectOutput,
+    path: &Path,
+    part_size: &RangeInclusive<u64>,
+    max_parts: usize,
+) -> io::Result<bool> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    if head.content_length != Some(len as i64) {
+        return Ok(false);
+    }
+
+    // a composite checksum depends on the part boundaries of the object
+    let checksum = Checksum::from_head_object(head)
+        .filter(|_| head.checksum_type == Some(ChecksumType::FullObject));
+    let mut hasher = checksum
+        .as_ref()
+        .and_then(|checksum| checksum::Hasher::new(&checksum.algorithm()));
+    let mut e_tag =
+        ETagHasher::new(NonZeroU64::new(part_size_for(len, part_size, max_parts)).unwrap());
+    // objects sent with `PutObject`
+    let mut md5 = Md5::new();
+    let mut buf = vec![0; READER_CAPACITY];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&buf[..n]);
+        }
+        e_tag.update(&buf[..n]);
+        Digest::update(&mut md5, &buf[..n]);
+    }
+
+    if let (Some(checksum), Some(mut hasher)) = (checksum, hasher) {
+        return Ok(hasher.finalize_reset() == checksum);
+    }
+    let remote = head.e_tag.as_deref();
+    Ok(remote == Some(&e_tag.finalize()) || remote == Some(&format!("\"{:x}\"", md5.finalize())))
+}
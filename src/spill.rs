@@ -0,0 +1,159 @@
+use crate::into_byte_stream::into_byte_stream;
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use aws_smithy_types::byte_stream::Length;
+use bytes::Bytes;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps parts in memory up to a budget and writes the others to temporary files.
+#[derive(Debug)]
+pub(crate) struct Spill {
+    dir: PathBuf,
+    bytes_in_memory: u64,
+    in_memory: AtomicU64,
+}
+
+/// Holds the memory budget of a part, or its temporary file, until the part is uploaded.
+#[derive(Debug)]
+pub(crate) enum Staged {
+    InMemory(Arc<Spill>, u64),
+    OnDisk(PathBuf),
+}
+
+impl Spill {
+    pub(crate) fn new(dir: PathBuf, bytes_in_memory: u64) -> Self {
+        Self {
+            dir,
+            bytes_in_memory,
+            in_memory: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns a body to send the part with, streamed from disk if the part does not fit in
+    /// the remaining budget.
+    pub(crate) async fn stage(
+        self: &Arc<Self>,
+        body: Vec<Bytes>,
+        content_length: u64,
+    ) -> Result<(ByteStream, Staged), ByteStreamError> {
+        let in_memory =
+            self.in_memory
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |in_memory| {
+                    (in_memory + content_length <= self.bytes_in_memory)
+                        .then_some(in_memory + content_length)
+                });
+        if in_memory.is_ok() {
+            return Ok((
+                into_byte_stream(body),
+                Staged::InMemory(self.clone(), content_length),
+            ));
+        }
+
+        let path = self.dir.join(format!(
+            "s3-mpu-{}-{}.part",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed),
+        ));
+        // removes the file even if writing it fails
+        let staged = Staged::OnDisk(path.clone());
+        let mut file = tokio::fs::File::create(&path).await?;
+        for chunk in body {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        drop(file);
+        let body = ByteStream::read_from()
+            .path(&path)
+            .length(Length::Exact(content_length))
+            .build()
+            .await?;
+        Ok((body, staged))
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        match self {
+            Self::InMemory(spill, content_length) => {
+                spill
+                    .in_memory
+                    .fetch_sub(*content_length, Ordering::Relaxed);
+            }
+            Self::OnDisk(path) => {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spill, Staged};
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_spill() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir(&dir).unwrap();
+        let spill = Arc::new(Spill::new(dir.clone(), 10));
+
+        let (_, in_memory) = spill
+            .stage(vec![Bytes::from_static(&[0; 8])], 8)
+            .await
+            .unwrap();
+        assert!(matches!(in_memory, Staged::InMemory(_, 8)));
+
+        let (body, on_disk) = spill
+            .stage(
+                vec![Bytes::from_static(&[1; 4]), Bytes::from_static(&[2; 4])],
+                8,
+            )
+            .await
+            .unwrap();
+        let Staged::OnDisk(path) = &on_disk else {
+            panic!("{on_disk:?}");
+        };
+        assert!(path.starts_with(&dir));
+        let body = body.collect().await.unwrap().into_bytes();
+        assert_eq!(body, [[1; 4], [2; 4]].concat());
+        drop(on_disk);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        drop(in_memory);
+        let (_, in_memory) = spill
+            .stage(vec![Bytes::from_static(&[0; 8])], 8)
+            .await
+            .unwrap();
+        assert!(matches!(in_memory, Staged::InMemory(_, 8)));
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_spill_upload() {
+        use crate::{FakeS3, MultipartUpload};
+        use aws_sdk_s3::primitives::ByteStream;
+
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir(&dir).unwrap();
+        let fake = FakeS3::new();
+        let body = (0..25).collect::<Vec<u8>>();
+        MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from(body.clone()))
+            .part_size_limits(10..=10)
+            .spill_to_disk(&dir, 10)
+            .send::<anyhow::Error>(10..=10, None)
+            .await
+            .unwrap();
+        assert_eq!(fake.object("bucket", "key").unwrap(), body);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
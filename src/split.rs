@@ -1,43 +1,187 @@
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::Stream;
-use md5::digest::Output;
+use md5::digest::{FixedOutputReset, Output};
 use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::Sha256;
 use std::cmp;
 use std::mem;
 use std::ops::RangeInclusive;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+/// Incremental hash computed over each part while splitting.
+///
+/// Implemented for MD5, SHA-1 and SHA-256, and for `()`, `Option`, `Vec` and tuples of hashers.
+pub trait PartHasher {
+    type Output;
+
+    fn update(&mut self, data: &[u8]);
+    fn finalize_reset(&mut self) -> Self::Output;
+}
+
+macro_rules! impl_part_hasher {
+    ($($t:ty),*) => {
+        $(
+            impl PartHasher for $t {
+                type Output = Output<$t>;
+
+                fn update(&mut self, data: &[u8]) {
+                    Digest::update(self, data)
+                }
+
+                fn finalize_reset(&mut self) -> Self::Output {
+                    FixedOutputReset::finalize_fixed_reset(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_part_hasher!(Md5, Sha1, Sha256);
+
+impl PartHasher for () {
+    type Output = ();
+
+    fn update(&mut self, _: &[u8]) {}
+
+    fn finalize_reset(&mut self) -> Self::Output {}
+}
+
+impl<H> PartHasher for Option<H>
+where
+    H: PartHasher,
+{
+    type Output = Option<H::Output>;
+
+    fn update(&mut self, data: &[u8]) {
+        if let Some(hasher) = self {
+            hasher.update(data)
+        }
+    }
+
+    fn finalize_reset(&mut self) -> Self::Output {
+        self.as_mut().map(H::finalize_reset)
+    }
+}
+
+impl<H> PartHasher for Vec<H>
+where
+    H: PartHasher,
+{
+    type Output = Vec<H::Output>;
+
+    fn update(&mut self, data: &[u8]) {
+        for hasher in self {
+            hasher.update(data)
+        }
+    }
+
+    fn finalize_reset(&mut self) -> Self::Output {
+        self.iter_mut().map(H::finalize_reset).collect()
+    }
+}
+
+macro_rules! impl_part_hasher_tuple {
+    ($($t:ident: $i:tt),*) => {
+        impl<$($t),*> PartHasher for ($($t,)*)
+        where
+            $($t: PartHasher,)*
+        {
+            type Output = ($($t::Output,)*);
+
+            fn update(&mut self, data: &[u8]) {
+                $(self.$i.update(data);)*
+            }
+
+            fn finalize_reset(&mut self) -> Self::Output {
+                ($(self.$i.finalize_reset(),)*)
+            }
+        }
+    };
+}
+
+impl_part_hasher_tuple!(A: 0, B: 1);
+impl_part_hasher_tuple!(A: 0, B: 1, C: 2);
+
+/// A part produced by [`split`].
 #[derive(Debug, PartialEq)]
-pub struct Part {
+#[non_exhaustive]
+pub struct Part<D> {
     pub body: Vec<Bytes>,
-    pub content_length: usize,
-    pub content_md5: Output<Md5>,
+    pub content_length: u64,
+    pub digest: D,
+    /// Byte offset of the part within the body.
+    pub offset: u64,
+    /// 1-based part number.
     pub part_number: usize,
 }
 
-pub fn split<B, E>(body: B, part_size: RangeInclusive<usize>) -> impl Stream<Item = Result<Part, E>>
+impl<D> Part<D> {
+    pub fn with_digest<T>(self, digest: T) -> Part<T> {
+        Part {
+            body: self.body,
+            content_length: self.content_length,
+            digest,
+            offset: self.offset,
+            part_number: self.part_number,
+        }
+    }
+}
+
+/// Splits `body` into parts whose sizes are within `part_size`, except for the last one.
+///
+/// A part is emitted as soon as it reaches `part_size.start()` bytes, and chunks are only cut
+/// when a part would exceed `part_size.end()`. `hasher` is fed the bytes of each part and
+/// its result is stored in [`Part::digest`]. An empty body yields no parts.
+///
+/// A chunk that starts a part and fits within `part_size` becomes the part body as is, so a
+/// body that is already chunked at part boundaries is neither split nor copied.
+pub fn split<B, E, H>(
+    body: B,
+    part_size: RangeInclusive<u64>,
+    hasher: H,
+) -> impl Stream<Item = Result<Part<H::Output>, E>>
+where
+    B: Stream<Item = Result<Bytes, E>>,
+    H: PartHasher,
+{
+    split_coalesced(body, part_size, hasher, 0)
+}
+
+/// Like [`split`], but copies chunks shorter than `coalesce` bytes into a shared buffer until it
+/// holds `coalesce` bytes, so that [`Part::body`] is not made of thousands of tiny fragments.
+///
+/// Longer chunks are stored as is. `coalesce == 0` disables coalescing.
+pub fn split_coalesced<B, E, H>(
+    body: B,
+    part_size: RangeInclusive<u64>,
+    hasher: H,
+    coalesce: usize,
+) -> impl Stream<Item = Result<Part<H::Output>, E>>
 where
     B: Stream<Item = Result<Bytes, E>>,
+    H: PartHasher,
 {
     Split {
         body,
-        inner: Some(Inner::new(part_size)),
+        inner: Some(Inner::new(part_size, hasher, coalesce)),
     }
 }
 
 #[pin_project::pin_project]
-struct Split<B> {
+struct Split<B, H> {
     #[pin]
     body: B,
-    inner: Option<Inner>,
+    inner: Option<Inner<H>>,
 }
 
-impl<B, E> Stream for Split<B>
+impl<B, E, H> Stream for Split<B, H>
 where
     B: Stream<Item = Result<Bytes, E>>,
+    H: PartHasher,
 {
-    type Item = Result<Part, E>;
+    type Item = Result<Part<H::Output>, E>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
@@ -59,34 +203,74 @@ where
             Poll::Ready(None)
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            Some(inner) => {
+                let buffered = inner.part_content_length + inner.remaining.len() as u64;
+                let lower = usize::from(buffered > 0);
+                match self.body.size_hint() {
+                    (_, Some(0)) => (
+                        lower,
+                        Some(buffered.div_ceil(cmp::max(*inner.part_size.start(), 1)) as _),
+                    ),
+                    _ => (lower, None),
+                }
+            }
+            None => (0, Some(0)),
+        }
+    }
 }
 
-struct Inner {
+struct Inner<H> {
     remaining: Bytes,
-    part_size: RangeInclusive<usize>,
+    part_size: RangeInclusive<u64>,
     part_body: Vec<Bytes>,
-    part_content_length: usize,
-    part_content_md5: Md5,
+    part_content_length: u64,
+    part_hasher: H,
+    part_offset: u64,
     part_number: usize,
+    coalesce: usize,
+    coalesced: BytesMut,
 }
 
-impl Inner {
-    fn new(part_size: RangeInclusive<usize>) -> Self {
+impl<H> Inner<H>
+where
+    H: PartHasher,
+{
+    fn new(part_size: RangeInclusive<u64>, hasher: H, coalesce: usize) -> Self {
         Self {
             remaining: Bytes::new(),
             part_size,
             part_body: Vec::new(),
             part_content_length: 0,
-            part_content_md5: Md5::new(),
+            part_hasher: hasher,
+            part_offset: 0,
             part_number: 0,
+            coalesce,
+            coalesced: BytesMut::new(),
         }
     }
 
     fn push_part(&mut self, chunk: Bytes) {
         if !chunk.is_empty() {
-            self.part_content_length += chunk.len();
-            self.part_content_md5.update(&chunk);
-            self.part_body.push(chunk);
+            self.part_content_length += chunk.len() as u64;
+            self.part_hasher.update(&chunk);
+            if chunk.len() < self.coalesce {
+                self.coalesced.extend_from_slice(&chunk);
+                if self.coalesced.len() >= self.coalesce {
+                    self.flush_coalesced();
+                }
+            } else {
+                self.flush_coalesced();
+                self.part_body.push(chunk);
+            }
+        }
+    }
+
+    fn flush_coalesced(&mut self) {
+        if !self.coalesced.is_empty() {
+            self.part_body.push(self.coalesced.split().freeze());
         }
     }
 
@@ -95,19 +279,35 @@ impl Inner {
         self.push_part(chunk);
     }
 
-    fn pop(&mut self) -> Option<Part> {
-        if self.part_content_length + self.remaining.len() >= *self.part_size.start() {
-            let chunk = self.remaining.split_to(cmp::min(
-                self.remaining.len(),
-                *self.part_size.end() - self.part_content_length,
-            ));
-            self.push_part(chunk);
+    fn pop(&mut self) -> Option<Part<H::Output>> {
+        if self.part_content_length + self.remaining.len() as u64 >= *self.part_size.start() {
+            if self.part_body.is_empty()
+                && self.coalesced.is_empty()
+                && self.part_size.contains(&(self.remaining.len() as u64))
+            {
+                // a chunk that is a whole part is passed through without splitting or copying
+                let chunk = mem::take(&mut self.remaining);
+                self.part_content_length = chunk.len() as u64;
+                self.part_hasher.update(&chunk);
+                self.part_body = vec![chunk];
+            } else {
+                let chunk = self.remaining.split_to(cmp::min(
+                    self.remaining.len() as u64,
+                    *self.part_size.end() - self.part_content_length,
+                ) as _);
+                self.push_part(chunk);
+                self.flush_coalesced();
+            }
 
             self.part_number += 1;
+            let content_length = mem::take(&mut self.part_content_length);
+            let offset = self.part_offset;
+            self.part_offset += content_length;
             Some(Part {
                 body: mem::take(&mut self.part_body),
-                content_length: mem::take(&mut self.part_content_length),
-                content_md5: self.part_content_md5.finalize_reset(),
+                content_length,
+                digest: self.part_hasher.finalize_reset(),
+                offset,
                 part_number: self.part_number,
             })
         } else {
@@ -115,16 +315,18 @@ impl Inner {
         }
     }
 
-    fn finish(mut self) -> Option<Part> {
+    fn finish(mut self) -> Option<Part<H::Output>> {
         let chunk = self.remaining.split_off(0);
         self.push_part(chunk);
+        self.flush_coalesced();
         if self.part_body.is_empty() {
             None
         } else {
             Some(Part {
                 body: self.part_body,
                 content_length: self.part_content_length,
-                content_md5: self.part_content_md5.finalize(),
+                digest: self.part_hasher.finalize_reset(),
+                offset: self.part_offset,
                 part_number: self.part_number + 1,
             })
         }
@@ -133,14 +335,15 @@ impl Inner {
 
 #[cfg(test)]
 mod tests {
-    use super::{split, Part};
+    use super::{split, split_coalesced, Part};
     use bytes::Bytes;
-    use futures::StreamExt;
+    use futures::{Stream, StreamExt};
     use md5::{Digest, Md5};
+    use sha2::Sha256;
 
     #[tokio::test]
     async fn test_split() {
-        let mut parts = split::<_, ()>(
+        let mut parts = split::<_, (), _>(
             futures::stream::iter(
                 [
                     Bytes::from_static(&[0, 1, 2]),
@@ -154,13 +357,15 @@ mod tests {
                 .map(Ok),
             ),
             4..=8,
+            Md5::new(),
         );
         assert_eq!(
             parts.next().await,
             Some(Ok(Part {
                 body: vec![Bytes::from_static(&[0, 1, 2]), Bytes::from_static(&[3, 4])],
                 content_length: 5,
-                content_md5: Md5::digest(&[0, 1, 2, 3, 4]),
+                digest: Md5::digest([0, 1, 2, 3, 4]),
+                offset: 0,
                 part_number: 1,
             }))
         );
@@ -169,7 +374,8 @@ mod tests {
             Some(Ok(Part {
                 body: vec![Bytes::from_static(&[5, 6, 7, 8, 9, 10, 11, 12])],
                 content_length: 8,
-                content_md5: Md5::digest(&[5, 6, 7, 8, 9, 10, 11, 12]),
+                digest: Md5::digest([5, 6, 7, 8, 9, 10, 11, 12]),
+                offset: 5,
                 part_number: 2,
             }))
         );
@@ -178,7 +384,8 @@ mod tests {
             Some(Ok(Part {
                 body: vec![Bytes::from_static(&[13, 14, 15, 16, 17, 18, 19, 20])],
                 content_length: 8,
-                content_md5: Md5::digest(&[13, 14, 15, 16, 17, 18, 19, 20]),
+                digest: Md5::digest([13, 14, 15, 16, 17, 18, 19, 20]),
+                offset: 13,
                 part_number: 3,
             }))
         );
@@ -187,10 +394,110 @@ mod tests {
             Some(Ok(Part {
                 body: vec![Bytes::from_static(&[21]), Bytes::from_static(&[22, 23])],
                 content_length: 3,
-                content_md5: Md5::digest(&[21, 22, 23]),
+                digest: Md5::digest([21, 22, 23]),
+                offset: 21,
                 part_number: 4,
             }))
         );
         assert_eq!(parts.next().await, None);
     }
+
+    #[tokio::test]
+    async fn test_split_coalesced() {
+        let mut parts = split_coalesced::<_, (), _>(
+            futures::stream::iter(
+                [
+                    Bytes::from_static(&[0]),
+                    Bytes::from_static(&[1, 2]),
+                    Bytes::from_static(&[3]),
+                    Bytes::from_static(&[4, 5, 6, 7, 8]),
+                    Bytes::from_static(&[9]),
+                    Bytes::from_static(&[10]),
+                ]
+                .into_iter()
+                .map(Ok),
+            ),
+            8..=8,
+            Md5::new(),
+            3,
+        );
+        assert_eq!(
+            parts.next().await,
+            Some(Ok(Part {
+                body: vec![
+                    Bytes::from_static(&[0, 1, 2]),
+                    Bytes::from_static(&[3]),
+                    Bytes::from_static(&[4, 5, 6, 7]),
+                ],
+                content_length: 8,
+                digest: Md5::digest([0, 1, 2, 3, 4, 5, 6, 7]),
+                offset: 0,
+                part_number: 1,
+            }))
+        );
+        assert_eq!(
+            parts.next().await,
+            Some(Ok(Part {
+                body: vec![Bytes::from_static(&[8, 9, 10])],
+                content_length: 3,
+                digest: Md5::digest([8, 9, 10]),
+                offset: 8,
+                part_number: 2,
+            }))
+        );
+        assert_eq!(parts.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_split_pass_through() {
+        let chunks = [Bytes::from(vec![0; 8]), Bytes::from(vec![1; 8])];
+        let parts = split::<_, (), _>(futures::stream::iter(chunks.clone().map(Ok)), 8..=8, ())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(parts.len(), 2);
+        for (part, chunk) in parts.into_iter().zip(chunks) {
+            let part = part.unwrap();
+            assert_eq!(part.body.len(), 1);
+            assert_eq!(part.body[0].as_ptr(), chunk.as_ptr());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_hasher() {
+        let mut parts = split::<_, (), _>(
+            futures::stream::iter([Ok(Bytes::from_static(&[0, 1, 2]))]),
+            4..=8,
+            ((), (None::<Md5>, Sha256::new()), vec![Sha256::new()]),
+        );
+        assert_eq!(
+            parts.next().await,
+            Some(Ok(Part {
+                body: vec![Bytes::from_static(&[0, 1, 2])],
+                content_length: 3,
+                digest: (
+                    (),
+                    (None, Sha256::digest([0, 1, 2])),
+                    vec![Sha256::digest([0, 1, 2])]
+                ),
+                offset: 0,
+                part_number: 1,
+            }))
+        );
+        assert_eq!(parts.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_split_size_hint() {
+        let mut parts = split::<_, (), _>(
+            futures::stream::iter([Ok(Bytes::from_static(&[0; 10]))]),
+            4..=8,
+            (),
+        );
+        assert_eq!(parts.size_hint(), (0, None));
+        assert!(parts.next().await.is_some());
+        assert_eq!(parts.size_hint(), (1, Some(1)));
+        assert!(parts.next().await.is_some());
+        assert_eq!(parts.size_hint(), (0, Some(0)));
+        assert_eq!(parts.next().await, None);
+    }
 }
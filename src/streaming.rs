@@ -0,0 +1,110 @@
+use crate::{MultipartUploadError, MultipartUploadOutput, UploadedPart};
+use futures::channel::mpsc;
+use futures::{Future, Stream, StreamExt};
+use std::task::Poll;
+
+/// An item of [`MultipartUpload::send_streaming`](crate::MultipartUpload::send_streaming).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
+pub enum UploadEvent {
+    /// A part acknowledged by S3, in the order the parts finish. Its `info.len` and `duration`
+    /// give the size and time of the part.
+    PartCompleted(UploadedPart),
+    /// The last item of the stream.
+    Completed(MultipartUploadOutput),
+}
+
+/// Drives `send` and yields the parts received from `rx` as they finish, then the output of
+/// `send`.
+#[allow(clippy::result_large_err)]
+pub(crate) fn stream<F, E>(
+    send: F,
+    mut rx: mpsc::UnboundedReceiver<UploadedPart>,
+) -> impl Stream<Item = Result<UploadEvent, MultipartUploadError<E>>>
+where
+    F: Future<Output = Result<MultipartUploadOutput, MultipartUploadError<E>>>,
+{
+    let mut send = Some(Box::pin(send));
+    let mut output = None;
+    futures::stream::poll_fn(move |cx| {
+        if let Some(future) = &mut send {
+            if let Poll::Ready(result) = future.as_mut().poll(cx) {
+                send = None;
+                output = Some(result);
+                // the parts sent before the output are still received
+                rx.close();
+            }
+        }
+        match rx.poll_next_unpin(cx) {
+            Poll::Ready(Some(uploaded_part)) => {
+                Poll::Ready(Some(Ok(UploadEvent::PartCompleted(uploaded_part))))
+            }
+            Poll::Ready(None) => Poll::Ready(
+                output
+                    .take()
+                    .map(|result| result.map(UploadEvent::Completed)),
+            ),
+            Poll::Pending => Poll::Pending,
+        }
+    })
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::UploadEvent;
+    use crate::{FakeS3, Fault, FaultInjector, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStream;
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_send_streaming() {
+        let fake = FakeS3::new();
+        let events = MultipartUpload::new(&fake.client())
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .send_streaming::<anyhow::Error>(10..=10, None)
+            .collect::<Vec<_>>()
+            .await;
+        let [parts @ .., Ok(UploadEvent::Completed(output))] = &events[..] else {
+            panic!("{events:?}");
+        };
+        let mut lens = parts
+            .iter()
+            .map(|event| match event {
+                Ok(UploadEvent::PartCompleted(uploaded_part)) => {
+                    (uploaded_part.info.number, uploaded_part.info.len)
+                }
+                event => panic!("{event:?}"),
+            })
+            .collect::<Vec<_>>();
+        lens.sort_unstable();
+        assert_eq!(lens, [(1, 10), (2, 10), (3, 5)]);
+        assert_eq!(output.parts.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_streaming_failure() {
+        let fake = FakeS3::new();
+        let client = fake.client();
+        let events = MultipartUpload::new(&client)
+            .mpu_client(Arc::new(
+                FaultInjector::new(client.clone()).part(2, Fault::error(500, "InternalError")),
+            ))
+            .bucket("bucket")
+            .key("key")
+            .body(ByteStream::from_static(&[0; 25]))
+            .part_size_limits(10..=10)
+            .sequential(true)
+            .send_streaming::<anyhow::Error>(10..=10, None)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            &events[..],
+            [Ok(UploadEvent::PartCompleted(_)), Err(_)],
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{
+    MultipartUpload, MultipartUploadError, MultipartUploadOutput, TarError, UploadErrorKind,
+    CHANNEL_BUFFER,
+};
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+use bytes::{Bytes, BytesMut};
+use futures::channel::mpsc;
+use futures::{FutureExt, SinkExt, StreamExt};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+const BLOCK: usize = 512;
+
+/// Uploads every regular file of a tar archive as its own object.
+///
+/// `upload` returns the request for an entry path, e.g. with the key set to a prefix plus the
+/// path. Entries are read in order while their parts are uploaded concurrently, with at most
+/// `concurrency_limit` parts in flight across all entries; this replaces any
+/// [`MultipartUpload::semaphore`] set by `upload`. Returns the path and output of every uploaded
+/// entry.
+///
+/// Once the archive or an upload fails, no further entry is started and the entry being read
+/// is failed rather than completed truncated. The uploads in flight run to completion, then the
+/// failed ones are aborted and the first error is returned with the others as
+/// `additional_errors`.
+#[allow(clippy::result_large_err)]
+pub async fn upload_tar<E, F>(
+    body: ByteStream,
+    mut upload: F,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> Result<Vec<(String, MultipartUploadOutput)>, MultipartUploadError<E>>
+where
+    F: FnMut(&str) -> MultipartUpload,
+    E: UploadErrorKind + From<TarError>,
+{
+    let semaphore = concurrency_limit
+        .map(|concurrency_limit| Arc::new(Semaphore::new(concurrency_limit.get())));
+    let failed = &AtomicBool::new(false);
+    let (uploads_tx, uploads_rx) = mpsc::unbounded();
+    let feed = async move {
+        let mut reader = Reader::new(body);
+        let mut long_name = None;
+        while !failed.load(Ordering::Relaxed) {
+            let Some(header) = reader.header::<E>().await? else {
+                break;
+            };
+            let size = header.size().map_err(E::from)?;
+            match header.typeflag() {
+                b'0' | b'\0' => {
+                    let path = long_name.take().unwrap_or_else(|| header.path());
+                    let (mut tx, rx) = mpsc::channel(CHANNEL_BUFFER);
+                    let mut request = upload(&path).body_stream(rx);
+                    if let Some(semaphore) = &semaphore {
+                        request = request.semaphore(semaphore.clone());
+                    }
+                    let upload = request.send::<E>(part_size.clone(), concurrency_limit);
+                    let _ = uploads_tx.unbounded_send(upload.map(move |result| {
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        Ok((path, result?))
+                    }));
+                    let mut remaining = size;
+                    while remaining > 0 {
+                        let chunk = match reader.chunk::<E>(remaining).await {
+                            Ok(chunk) if !failed.load(Ordering::Relaxed) => chunk,
+                            result => {
+                                // ends the body with an error so that the entry is aborted
+                                let _ = tx.send(Err(TarError::UnexpectedEof)).await;
+                                return result.map(drop);
+                            }
+                        };
+                        remaining -= chunk.len() as u64;
+                        // a failed upload drops the receiver and reports its own error
+                        let _ = tx.send(Ok(chunk)).await;
+                    }
+                }
+                // GNU long name
+                b'L' => {
+                    let data = reader.exact::<E>(size).await?;
+                    long_name = Some(cstr(&data));
+                }
+                // pax extended header
+                b'x' => {
+                    let data = reader.exact::<E>(size).await?;
+                    long_name = pax_path(&data).or(long_name);
+                }
+                _ => reader.skip::<E>(size).await?,
+            }
+            reader.skip::<E>(padding(size)).await?;
+        }
+        Ok(())
+    };
+    let (feed, results) = futures::join!(
+        feed,
+        uploads_rx.buffer_unordered(usize::MAX).collect::<Vec<_>>(),
+    );
+
+    let mut outputs = Vec::new();
+    let mut errors = Vec::new();
+    if let Err(err) = feed {
+        errors.push(MultipartUploadError::new::<E>(err));
+    }
+    for result in results {
+        match result {
+            Ok(output) => outputs.push(output),
+            Err(err) => errors.push(err),
+        }
+    }
+    match MultipartUploadError::abort_all(errors).await {
+        Some(err) => Err(err),
+        None => Ok(outputs),
+    }
+}
+
+struct Header([u8; BLOCK]);
+
+impl Header {
+    fn size(&self) -> Result<u64, TarError> {
+        let field = &self.0[124..136];
+        // GNU base-256 encoding for large files
+        if field[0] & 0x80 != 0 {
+            field[1..]
+                .iter()
+                .try_fold(u64::from(field[0] & 0x7f), |acc, &b| {
+                    acc.checked_mul(1 << 8)?.checked_add(u64::from(b))
+                })
+                .ok_or(TarError::InvalidHeader)
+        } else {
+            octal(field)
+        }
+    }
+
+    fn typeflag(&self) -> u8 {
+        self.0[156]
+    }
+
+    fn path(&self) -> String {
+        let name = cstr(&self.0[..100]);
+        if &self.0[257..263] == b"ustar\0" {
+            let prefix = cstr(&self.0[345..500]);
+            if !prefix.is_empty() {
+                return format!("{prefix}/{name}");
+            }
+        }
+        name
+    }
+
+    fn check(&self) -> Result<(), TarError> {
+        let sum = self.0[..148]
+            .iter()
+            .chain(&[b' '; 8])
+            .chain(&self.0[156..])
+            .map(|&b| u64::from(b))
+            .sum::<u64>();
+        if octal(&self.0[148..156])? == sum {
+            Ok(())
+        } else {
+            Err(TarError::InvalidHeader)
+        }
+    }
+}
+
+struct Reader {
+    body: ByteStream,
+    buf: Bytes,
+}
+
+impl Reader {
+    fn new(body: ByteStream) -> Self {
+        Self {
+            body,
+            buf: Bytes::new(),
+        }
+    }
+
+    async fn fill<E>(&mut self) -> Result<bool, E>
+    where
+        E: From<ByteStreamError>,
+    {
+        while self.buf.is_empty() {
+            match self.body.next().await {
+                Some(chunk) => self.buf = chunk.map_err(E::from)?,
+                None => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    async fn chunk<E>(&mut self, max: u64) -> Result<Bytes, E>
+    where
+        E: From<TarError> + From<ByteStreamError>,
+    {
+        if !self.fill::<E>().await? {
+            return Err(TarError::UnexpectedEof.into());
+        }
+        let len = usize::try_from(max).map_or(self.buf.len(), |max| max.min(self.buf.len()));
+        Ok(self.buf.split_to(len))
+    }
+
+    async fn exact<E>(&mut self, len: u64) -> Result<BytesMut, E>
+    where
+        E: From<TarError> + From<ByteStreamError>,
+    {
+        let len = usize::try_from(len).map_err(|_| TarError::InvalidHeader)?;
+        let mut data = BytesMut::with_capacity(len);
+        while data.len() < len {
+            data.extend_from_slice(&self.chunk::<E>((len - data.len()) as u64).await?);
+        }
+        Ok(data)
+    }
+
+    async fn skip<E>(&mut self, mut len: u64) -> Result<(), E>
+    where
+        E: From<TarError> + From<ByteStreamError>,
+    {
+        while len > 0 {
+            len -= self.chunk::<E>(len).await?.len() as u64;
+        }
+        Ok(())
+    }
+
+    // `None` at the end of the archive.
+    async fn header<E>(&mut self) -> Result<Option<Header>, E>
+    where
+        E: From<TarError> + From<ByteStreamError>,
+    {
+        if !self.fill::<E>().await? {
+            return Ok(None);
+        }
+        let mut header = Header([0; BLOCK]);
+        header
+            .0
+            .copy_from_slice(&self.exact::<E>(BLOCK as u64).await?);
+        if header.0.iter().all(|&b| b == 0) {
+            return Ok(None);
+        }
+        header.check().map_err(E::from)?;
+        Ok(Some(header))
+    }
+}
+
+fn padding(size: u64) -> u64 {
+    let block = BLOCK as u64;
+    (block - size % block) % block
+}
+
+fn cstr(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+fn octal(field: &[u8]) -> Result<u64, TarError> {
+    let field = cstr(field);
+    let field = field.trim_matches(|c: char| c == ' ' || c == '\0');
+    if field.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(field, 8).map_err(|_| TarError::InvalidHeader)
+}
+
+// Records are `<len> <key>=<value>\n`.
+fn pax_path(mut data: &[u8]) -> Option<String> {
+    let mut path = None;
+    while !data.is_empty() {
+        let space = data.iter().position(|&b| b == b' ')?;
+        let len = std::str::from_utf8(&data[..space])
+            .ok()?
+            .parse::<usize>()
+            .ok()?;
+        let record = data.get(space + 1..len)?.strip_suffix(b"\n")?;
+        if let Some(value) = record.strip_prefix(b"path=") {
+            path = Some(String::from_utf8_lossy(value).into_owned());
+        }
+        data = &data[len..];
+    }
+    path
+}
+
+#[cfg(test)]
+pub(crate) fn archive(entries: &[(u8, &str, &[u8])]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for &(typeflag, name, data) in entries {
+        let mut header = [0; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[156] = typeflag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].copy_from_slice(b"        ");
+        let sum = header.iter().map(|&b| usize::from(b)).sum::<usize>();
+        header[148..156].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(data);
+        archive.resize(archive.len() + padding(data.len() as u64) as usize, 0);
+    }
+    archive.resize(archive.len() + BLOCK * 2, 0);
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{archive, pax_path, Header, Reader, BLOCK};
+    use crate::TarError;
+    use aws_sdk_s3::primitives::{ByteStream, ByteStreamError};
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    enum E {
+        Tar(TarError),
+        ByteStream(ByteStreamError),
+    }
+
+    impl From<TarError> for E {
+        fn from(err: TarError) -> Self {
+            Self::Tar(err)
+        }
+    }
+
+    impl From<ByteStreamError> for E {
+        fn from(err: ByteStreamError) -> Self {
+            Self::ByteStream(err)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reader() {
+        let archive = archive(&[(b'5', "dir/", b""), (b'0', "dir/foo", b"hello")]);
+        let mut reader = Reader::new(ByteStream::from(archive));
+
+        let header = reader.header::<E>().await.unwrap().unwrap();
+        assert_eq!(header.typeflag(), b'5');
+        assert_eq!(header.path(), "dir/");
+        assert_eq!(header.size().unwrap(), 0);
+
+        let header = reader.header::<E>().await.unwrap().unwrap();
+        assert_eq!(header.typeflag(), b'0');
+        assert_eq!(header.path(), "dir/foo");
+        assert_eq!(header.size().unwrap(), 5);
+        assert_eq!(&reader.exact::<E>(5).await.unwrap()[..], b"hello");
+        reader.skip::<E>(512 - 5).await.unwrap();
+
+        assert!(reader.header::<E>().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reader_checksum() {
+        let mut archive = archive(&[(b'0', "foo", b"hello")]);
+        archive[0] = b'g';
+        let mut reader = Reader::new(ByteStream::from(archive));
+        assert!(matches!(
+            reader.header::<E>().await,
+            Err(E::Tar(TarError::InvalidHeader))
+        ));
+    }
+
+    #[test]
+    fn test_header_size() {
+        let mut header = Header([0; BLOCK]);
+        header.0[124] = 0x80;
+        header.0[132..136].copy_from_slice(&[1, 0, 0, 0]);
+        assert_eq!(header.size().unwrap(), 1 << 24);
+
+        // 95 bits
+        header.0[125..136].fill(0xff);
+        assert!(matches!(header.size(), Err(TarError::InvalidHeader)));
+    }
+
+    #[test]
+    fn test_pax_path() {
+        assert_eq!(
+            pax_path(b"20 path=foo/bar/baz\n17 mtime=12345.6\n").as_deref(),
+            Some("foo/bar/baz")
+        );
+        assert_eq!(pax_path(b"17 mtime=12345.6\n"), None);
+    }
+}
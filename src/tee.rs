@@ -0,0 +1,140 @@
+use crate::{MultipartUpload, MultipartUploadError, MultipartUploadOutput, UploadErrorKind};
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::{future, SinkExt};
+use std::io;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+
+const FAILED: &str = "the other upload of the tee failed";
+
+/// Uploads `body` to two destinations at once, reading it only once, e.g. to replicate an
+/// object to another region or account as it is written.
+///
+/// `first` and `second` are the requests of the destinations without a body, and may be
+/// created with different clients, e.g. for another account. The body is read as fast as the
+/// slower destination accepts it. If either upload fails, the other fails too rather than
+/// completing with a truncated body. Neither is aborted; see the `abort` field of
+/// [`MultipartUploadError`].
+#[allow(clippy::type_complexity)]
+pub async fn upload_tee<E>(
+    mut body: ByteStream,
+    first: MultipartUpload,
+    second: MultipartUpload,
+    part_size: RangeInclusive<u64>,
+    concurrency_limit: Option<NonZeroUsize>,
+) -> (
+    Result<MultipartUploadOutput, MultipartUploadError<E>>,
+    Result<MultipartUploadOutput, MultipartUploadError<E>>,
+)
+where
+    E: UploadErrorKind,
+{
+    let (mut first_tx, first_rx) = mpsc::channel::<io::Result<Bytes>>(1);
+    let (mut second_tx, second_rx) = mpsc::channel::<io::Result<Bytes>>(1);
+    let feed = async move {
+        while let Some(chunk) = body.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    if let (Ok(()), Ok(())) =
+                        future::join(first_tx.send(Ok(chunk.clone())), second_tx.send(Ok(chunk)))
+                            .await
+                    {
+                        continue;
+                    }
+                    // a failed upload drops its receiver; fails the other one
+                    let _ = first_tx.send(Err(io::Error::other(FAILED))).await;
+                    let _ = second_tx.send(Err(io::Error::other(FAILED))).await;
+                }
+                Err(err) => {
+                    let message = err.to_string();
+                    let _ = first_tx.send(Err(io::Error::other(err))).await;
+                    let _ = second_tx.send(Err(io::Error::other(message))).await;
+                }
+            }
+            return;
+        }
+    };
+    let ((), first, second) = future::join3(
+        feed,
+        first
+            .body_stream(first_rx)
+            .send(part_size.clone(), concurrency_limit),
+        second
+            .body_stream(second_rx)
+            .send(part_size, concurrency_limit),
+    )
+    .await;
+    (first, second)
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests {
+    use super::upload_tee;
+    use crate::{FakeS3, Fault, FaultInjector, MultipartUpload};
+    use aws_sdk_s3::primitives::ByteStream;
+    use bytes::Bytes;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    fn body(len: u8) -> ByteStream {
+        crate::into_byte_stream::from_stream(futures::stream::iter(
+            (0..len).map(|i| Ok::<_, Infallible>(Bytes::from(vec![i; 10]))),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_upload_tee() {
+        let primary = FakeS3::new();
+        let replica = FakeS3::new();
+        let (first, second) = upload_tee::<anyhow::Error>(
+            body(3),
+            MultipartUpload::new(&primary.client())
+                .bucket("bucket")
+                .key("key")
+                .part_size_limits(10..=10),
+            MultipartUpload::new(&replica.client())
+                .bucket("replica")
+                .key("key")
+                .part_size_limits(10..=10),
+            10..=10,
+            None,
+        )
+        .await;
+        first.unwrap();
+        second.unwrap();
+        let expected = body(3).collect().await.unwrap().into_bytes();
+        assert_eq!(primary.object("bucket", "key").unwrap(), expected);
+        assert_eq!(replica.object("replica", "key").unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_upload_tee_failure() {
+        let primary = FakeS3::new();
+        let replica = FakeS3::new();
+        let client = replica.client();
+        let (first, second) = upload_tee::<anyhow::Error>(
+            body(10),
+            MultipartUpload::new(&primary.client())
+                .bucket("bucket")
+                .key("key")
+                .part_size_limits(10..=10),
+            MultipartUpload::new(&client)
+                .mpu_client(Arc::new(
+                    FaultInjector::new(client.clone()).part(2, Fault::error(500, "InternalError")),
+                ))
+                .bucket("replica")
+                .key("key")
+                .part_size_limits(10..=10)
+                .sequential(true),
+            10..=10,
+            None,
+        )
+        .await;
+        assert!(first.is_err());
+        assert!(second.is_err());
+        assert!(primary.object("bucket", "key").is_none());
+        assert!(replica.object("replica", "key").is_none());
+    }
+}
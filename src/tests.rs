@@ -1,11 +1,17 @@
-use super::{MultipartUpload, PART_SIZE};
-use crate::into_byte_stream;
+use super::{abort_verified, MultipartUpload, PreconditionFailed, PART_SIZE};
+use crate::split::PartHasher;
+use crate::{checksum, into_byte_stream};
 use aws_config::default_provider::credentials;
 use aws_sdk_s3::config::Region;
-use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use aws_sdk_s3::primitives::{ByteStream, DateTime, SdkBody};
+use aws_sdk_s3::types::{
+    ChecksumAlgorithm, ChecksumMode, ChecksumType, CompletedMultipartUpload, CompletedPart,
+};
 use aws_sdk_s3::{Client, Config};
 use aws_smithy_types::body;
+use aws_smithy_types::date_time::Format;
 use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
 use http::header::HeaderMap;
 use http_body::combinators::BoxBody;
 use http_body::Body;
@@ -18,6 +24,11 @@ use std::task::{Context, Poll};
 use uuid::Uuid;
 
 async fn context() -> (Client, String, String) {
+    #[cfg(feature = "integration-test")]
+    if env::var_os("ENDPOINT").is_none() {
+        return local_context();
+    }
+
     let client = Client::from_conf(
         Config::builder()
             .behavior_version_latest()
@@ -32,6 +43,99 @@ async fn context() -> (Client, String, String) {
     (client, bucket, key)
 }
 
+// serves the requests in-process from a fresh s3s-fs root, so that no S3 has to be running
+#[cfg(feature = "integration-test")]
+fn local_context() -> (Client, String, String) {
+    let (service, bucket) = local_service();
+    let client = Client::from_conf(
+        local_config("http://localhost")
+            .http_client(s3s_aws::Client::from(service))
+            .build(),
+    );
+    let key = Uuid::new_v4().to_string();
+    (client, bucket, key)
+}
+
+// like `context`, but at an endpoint that clients other than the SDK can reach, e.g. for
+// presigned requests
+async fn http_context() -> (Client, String, String) {
+    #[cfg(feature = "integration-test")]
+    if env::var_os("ENDPOINT").is_none() {
+        return local_http_context().await;
+    }
+
+    context().await
+}
+
+// serves s3s-fs on a port of localhost
+#[cfg(feature = "integration-test")]
+async fn local_http_context() -> (Client, String, String) {
+    use hyper::server::conn::http1;
+    use hyper_util::rt::TokioIo;
+    use tokio::net::TcpListener;
+
+    let (service, bucket) = local_service();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let service = service.clone();
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+
+    let client = Client::from_conf(local_config(&endpoint_url).build());
+    let key = Uuid::new_v4().to_string();
+    (client, bucket, key)
+}
+
+#[cfg(feature = "integration-test")]
+const LOCAL_ACCESS_KEY: &str = "test";
+#[cfg(feature = "integration-test")]
+const LOCAL_SECRET_KEY: &str = "test";
+
+#[cfg(feature = "integration-test")]
+fn local_service() -> (s3s::service::S3Service, String) {
+    use s3s::auth::SimpleAuth;
+    use s3s::service::S3ServiceBuilder;
+    use s3s_fs::FileSystem;
+    use std::fs;
+
+    let root = env::temp_dir().join(Uuid::new_v4().to_string());
+    let bucket = "s3-mpu".to_owned();
+    fs::create_dir_all(root.join(&bucket)).unwrap();
+    let mut service = S3ServiceBuilder::new(FileSystem::new(&root).unwrap());
+    service.set_auth(SimpleAuth::from_single(LOCAL_ACCESS_KEY, LOCAL_SECRET_KEY));
+    (service.build(), bucket)
+}
+
+#[cfg(feature = "integration-test")]
+fn local_config(endpoint_url: &str) -> aws_sdk_s3::config::Builder {
+    use aws_sdk_s3::config::Credentials;
+
+    Config::builder()
+        .behavior_version_latest()
+        .credentials_provider(Credentials::new(
+            LOCAL_ACCESS_KEY,
+            LOCAL_SECRET_KEY,
+            None,
+            None,
+            "test",
+        ))
+        .endpoint_url(endpoint_url)
+        .force_path_style(true)
+        .region(Region::from_static("test"))
+}
+
+// s3s-fs does not implement everything that S3 does
+fn skip_locally(reason: &str) -> bool {
+    let skip = cfg!(feature = "integration-test") && env::var_os("ENDPOINT").is_none();
+    if skip {
+        eprintln!("skipped on s3s-fs: {reason}");
+    }
+    skip
+}
+
 fn into_chunks<R>(mut data: Bytes, rng: &mut R) -> impl Iterator<Item = Bytes>
 where
     R: Rng,
@@ -47,7 +151,7 @@ where
     sizes.into_iter().map(move |size| data.split_to(size))
 }
 
-async fn check(size: usize, concurrency_limit: Option<usize>) {
+async fn check(size: u64, concurrency_limit: Option<usize>) {
     let mut rng = rand::thread_rng();
 
     let (client, bucket, key) = context().await;
@@ -59,14 +163,21 @@ async fn check(size: usize, concurrency_limit: Option<usize>) {
         ))
         .bucket(&bucket)
         .key(&key)
+        .verify_e_tag(size > 0)
         .send::<anyhow::Error>(
             PART_SIZE,
             concurrency_limit.map(|limit| limit.try_into().unwrap()),
         )
         .await
         .unwrap();
-    assert_eq!(output.bucket.as_ref().unwrap(), &bucket);
-    assert_eq!(output.key.as_ref().unwrap(), &key);
+    assert_eq!(output.output.bucket.as_ref().unwrap(), &bucket);
+    assert_eq!(output.output.key.as_ref().unwrap(), &key);
+    assert_eq!(output.content_length, size);
+    assert!(output
+        .parts
+        .iter()
+        .enumerate()
+        .all(|(i, uploaded_part)| uploaded_part.info.number == i as i32 + 1));
 
     let output = client
         .get_object()
@@ -90,8 +201,19 @@ fn test_into_chunks() {
     );
 }
 
+#[test]
+fn test_send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let client = Client::from_conf(Config::builder().behavior_version_latest().build());
+    assert_send(&MultipartUpload::new(&client).send::<anyhow::Error>(PART_SIZE, None));
+}
+
 #[tokio::test]
 async fn test_empty() {
+    if skip_locally("a multipart upload without parts cannot be completed") {
+        return;
+    }
     check(0, None).await;
 }
 
@@ -126,63 +248,1276 @@ async fn test_concurrent_unlimited() {
 }
 
 #[tokio::test]
-async fn test_abort() {
-    struct B<const N: usize>(array::IntoIter<Result<Bytes, body::Error>, N>);
+async fn test_staged() {
+    let mut rng = rand::thread_rng();
 
-    impl<const N: usize> Body for B<N> {
-        type Data = Bytes;
-        type Error = body::Error;
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
 
-        fn poll_data(
-            self: Pin<&mut Self>,
-            _: &mut Context<'_>,
-        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
-            Poll::Ready(self.get_mut().0.next())
-        }
+    let mut initiated = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .initiate::<anyhow::Error>()
+        .await
+        .unwrap();
+    assert!(initiated.upload_id().is_some());
+    initiated
+        .upload_parts::<anyhow::Error>(
+            ByteStream::from(body.slice(..*PART_SIZE.start() as usize)),
+            PART_SIZE,
+            None,
+        )
+        .await
+        .unwrap();
+    initiated
+        .upload_parts::<anyhow::Error>(
+            ByteStream::from(body.slice(*PART_SIZE.start() as usize..)),
+            PART_SIZE,
+            None,
+        )
+        .await
+        .unwrap();
+    let output = initiated.complete::<anyhow::Error>().await.unwrap();
+    assert_eq!(output.parts.len(), 2);
+    assert_eq!(output.parts[1].info.range.start, *PART_SIZE.start());
 
-        fn poll_trailers(
-            self: Pin<&mut Self>,
-            _: &mut Context<'_>,
-        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
-            Poll::Ready(Ok(None))
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[tokio::test]
+async fn test_upload_id() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 3 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+
+    let upload_id = client
+        .create_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap()
+        .upload_id
+        .unwrap();
+    let output = client
+        .upload_part()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .part_number(1)
+        .body(ByteStream::from(body.slice(..*PART_SIZE.start() as usize)))
+        .send()
+        .await
+        .unwrap();
+    let completed_part = CompletedPart::builder()
+        .set_e_tag(output.e_tag)
+        .part_number(1)
+        .build();
+
+    let mut initiated = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .starting_part_number(2)
+        .initiate::<anyhow::Error>()
+        .await
+        .unwrap();
+    assert_eq!(initiated.upload_id(), Some(upload_id.as_str()));
+    initiated
+        .upload_parts::<anyhow::Error>(
+            ByteStream::from(body.slice(*PART_SIZE.start() as usize..)),
+            PART_SIZE,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(initiated.parts().len(), 1);
+    assert_eq!(initiated.parts()[0].info.number, 2);
+
+    client
+        .complete_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .parts(completed_part)
+                .parts(initiated.parts()[0].completed_part.clone())
+                .build(),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_body_reader() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Vec<u8>>();
+
+    MultipartUpload::new(&client)
+        .body_reader(std::io::Cursor::new(body.clone()))
+        .bucket(&bucket)
+        .key(&key)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_body_path() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let path = env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::write(&path, &body).unwrap();
+
+    let output = MultipartUpload::new(&client)
+        .body_path(&path)
+        .bucket(&bucket)
+        .key(&key)
+        .verify_e_tag(true)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(output.parts.len(), 3);
+    assert_eq!(output.content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[tokio::test]
+async fn test_channel() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+
+    let (mut tx, upload) = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .channel::<anyhow::Error>(PART_SIZE, None);
+    let chunks = into_chunks(body.clone(), &mut rng).collect::<Vec<_>>();
+    let produce = async move {
+        for chunk in chunks {
+            tx.send(chunk).await.unwrap();
         }
-    }
+    };
+    let ((), output) = futures::join!(produce, upload);
+    assert_eq!(output.unwrap().content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_writer() {
+    use tokio::io::AsyncWriteExt;
 
     let mut rng = rand::thread_rng();
 
     let (client, bucket, key) = context().await;
-    let body = [
-        Ok((0..*PART_SIZE.start() * 3 / 4)
-            .map(|_| rng.gen())
-            .collect::<Bytes>()),
-        Ok((0..*PART_SIZE.start() * 5 / 4)
-            .map(|_| rng.gen())
-            .collect::<Bytes>()),
-        Err("error".into()),
-    ];
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
 
-    let (_, abort) = MultipartUpload::new(&client)
-        .body(ByteStream::new(SdkBody::from_body_0_4(BoxBody::new(B(
-            body.into_iter(),
-        )))))
+    let mut writer = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .writer::<anyhow::Error>(PART_SIZE, None);
+    tokio::io::copy(&mut &body[..], &mut writer).await.unwrap();
+    writer.shutdown().await.unwrap();
+    assert_eq!(writer.output().unwrap().content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[tokio::test]
+async fn test_sink() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+
+    let mut sink = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .sink::<anyhow::Error>(PART_SIZE, None);
+    futures::stream::iter(into_chunks(body.clone(), &mut rng).map(Ok))
+        .forward(&mut sink)
+        .await
+        .unwrap();
+    assert_eq!(sink.output().unwrap().content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_body_framed() {
+    use tokio_util::codec::{FramedRead, LinesCodec};
+
+    let (client, bucket, key) = context().await;
+    let body = (0..100000)
+        .map(|i| format!("line {i}\n"))
+        .collect::<String>();
+
+    MultipartUpload::new(&client)
+        .body_framed(
+            FramedRead::new(std::io::Cursor::new(body.clone()), LinesCodec::new()),
+            LinesCodec::new(),
+        )
         .bucket(&bucket)
         .key(&key)
         .send::<anyhow::Error>(PART_SIZE, None)
         .await
-        .unwrap_err();
-    abort.unwrap().send().await.unwrap();
+        .unwrap();
 
     let output = client
-        .list_multipart_uploads()
+        .get_object()
         .bucket(&bucket)
+        .key(&key)
         .send()
         .await
         .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[tokio::test]
+async fn test_headers() {
+    let (client, bucket, key) = context().await;
+    let expires = DateTime::from_secs(4102444800);
+
+    MultipartUpload::new(&client)
+        .body(ByteStream::from_static(&[0, 1, 2]))
+        .bucket(&bucket)
+        .key(&key)
+        .cache_control("no-cache")
+        .content_disposition("attachment")
+        .content_encoding("identity")
+        .content_language("en")
+        .expires(expires)
+        .website_redirect_location("/index.html")
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
 
-    assert!(output
-        .uploads
-        .unwrap_or_default()
-        .iter()
-        .find(|upload| upload.key.as_ref() == Some(&key))
-        .is_none());
+    let output = client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.cache_control.as_deref(), Some("no-cache"));
+    assert_eq!(output.content_disposition.as_deref(), Some("attachment"));
+    assert_eq!(output.content_encoding.as_deref(), Some("identity"));
+    assert_eq!(output.content_language.as_deref(), Some("en"));
+    assert_eq!(
+        output.website_redirect_location.as_deref(),
+        Some("/index.html")
+    );
+    assert_eq!(
+        output.expires_string,
+        Some(expires.fmt(Format::HttpDate).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_customize() {
+    let (client, bucket, key) = context().await;
+
+    MultipartUpload::new(&client)
+        .body(ByteStream::from_static(&[0, 1, 2]))
+        .bucket(&bucket)
+        .key(&key)
+        .customize_create(|builder| {
+            builder
+                .content_type("application/octet-stream")
+                .metadata("foo", "bar")
+        })
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        output.content_type.as_deref(),
+        Some("application/octet-stream")
+    );
+    assert_eq!(
+        output.metadata.unwrap().get("foo").map(String::as_str),
+        Some("bar")
+    );
+}
+
+#[tokio::test]
+async fn test_create_multipart_upload() {
+    let (client, bucket, key) = context().await;
+
+    MultipartUpload::new(&client)
+        .create_multipart_upload(
+            client
+                .create_multipart_upload()
+                .bucket(&bucket)
+                .key(&key)
+                .content_type("text/plain"),
+        )
+        .body(ByteStream::from_static(b"foo"))
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.content_type.as_deref(), Some("text/plain"));
+}
+
+#[tokio::test]
+async fn test_checksum_algorithm() {
+    if skip_locally("additional checksums are not returned") {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+
+    MultipartUpload::new(&client)
+        .body(ByteStream::from(body))
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_algorithm(ChecksumAlgorithm::Sha256)
+        .content_md5(false)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_mode(ChecksumMode::Enabled)
+        .send()
+        .await
+        .unwrap();
+    assert!(output.checksum_sha256.unwrap().ends_with("-3"));
+}
+
+#[tokio::test]
+async fn test_full_object_checksum() {
+    if skip_locally("full-object checksums are not returned") {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+
+    MultipartUpload::new(&client)
+        .body(ByteStream::from(body.clone()))
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_algorithm(ChecksumAlgorithm::Crc64Nvme)
+        .checksum_type(ChecksumType::FullObject)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let mut hasher = checksum::Hasher::new(&ChecksumAlgorithm::Crc64Nvme).unwrap();
+    hasher.update(&body);
+
+    let output = client
+        .head_object()
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_mode(ChecksumMode::Enabled)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.checksum_type, Some(ChecksumType::FullObject));
+    assert_eq!(
+        output.checksum_crc64_nvme.as_deref(),
+        Some(hasher.finalize_reset().value())
+    );
+}
+
+#[tokio::test]
+async fn test_if_none_match() {
+    if skip_locally("If-None-Match is not honored") {
+        return;
+    }
+
+    let (client, bucket, key) = context().await;
+
+    MultipartUpload::new(&client)
+        .body(ByteStream::from_static(&[0, 1, 2]))
+        .bucket(&bucket)
+        .key(&key)
+        .if_none_match("*")
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let err = MultipartUpload::new(&client)
+        .body(ByteStream::from_static(&[3, 4, 5]))
+        .bucket(&bucket)
+        .key(&key)
+        .if_none_match("*")
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap_err();
+    assert!(err.error.is::<PreconditionFailed>());
+    assert!(err.upload_id.is_some());
+    assert!(err.request_ids.request_id.is_some());
+    abort_verified(&client, err.abort.unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_abort() {
+    if skip_locally("ListMultipartUploads is not implemented") {
+        return;
+    }
+
+    struct B<const N: usize>(array::IntoIter<Result<Bytes, body::Error>, N>);
+
+    impl<const N: usize> Body for B<N> {
+        type Data = Bytes;
+        type Error = body::Error;
+
+        fn poll_data(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            Poll::Ready(self.get_mut().0.next())
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            Poll::Ready(Ok(None))
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = [
+        Ok((0..*PART_SIZE.start() * 3 / 4)
+            .map(|_| rng.gen())
+            .collect::<Bytes>()),
+        Ok((0..*PART_SIZE.start() * 5 / 4)
+            .map(|_| rng.gen())
+            .collect::<Bytes>()),
+        Err("error".into()),
+    ];
+
+    let err = MultipartUpload::new(&client)
+        .body(ByteStream::new(SdkBody::from_body_0_4(BoxBody::new(B(
+            body.into_iter(),
+        )))))
+        .bucket(&bucket)
+        .key(&key)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap_err();
+    assert!(err.upload_id.is_some());
+    err.abort.unwrap().send().await.unwrap();
+
+    let output = client
+        .list_multipart_uploads()
+        .bucket(&bucket)
+        .send()
+        .await
+        .unwrap();
+
+    assert!(output
+        .uploads
+        .unwrap_or_default()
+        .iter()
+        .find(|upload| upload.key.as_ref() == Some(&key))
+        .is_none());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_upload_tar() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let foo = (0..*PART_SIZE.start() * 3 / 2)
+        .map(|_| rng.gen())
+        .collect::<Vec<u8>>();
+    let bar = (0..1000).map(|_| rng.gen()).collect::<Vec<u8>>();
+    let archive = crate::tar::archive(&[
+        (b'5', "dir/", b""),
+        (b'0', "dir/foo", &foo),
+        (b'0', "dir/bar", &bar),
+    ]);
+
+    let mut outputs = super::upload_tar::<anyhow::Error, _>(
+        ByteStream::from(archive),
+        |path| {
+            MultipartUpload::new(&client)
+                .bucket(&bucket)
+                .key(format!("{key}/{path}"))
+        },
+        PART_SIZE,
+        std::num::NonZeroUsize::new(2),
+    )
+    .await
+    .unwrap();
+    outputs.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        outputs
+            .iter()
+            .map(|(path, _)| &path[..])
+            .collect::<Vec<_>>(),
+        ["dir/bar", "dir/foo"]
+    );
+
+    for (path, body) in [("dir/foo", foo), ("dir/bar", bar)] {
+        let output = client
+            .get_object()
+            .bucket(&bucket)
+            .key(format!("{key}/{path}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_upload_dir() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let root = env::temp_dir().join(&key);
+    let foo = (0..*PART_SIZE.start() * 3 / 2)
+        .map(|_| rng.gen())
+        .collect::<Vec<u8>>();
+    let bar = (0..1000).map(|_| rng.gen()).collect::<Vec<u8>>();
+    tokio::fs::create_dir_all(root.join("dir")).await.unwrap();
+    tokio::fs::write(root.join("foo"), &foo).await.unwrap();
+    tokio::fs::write(root.join("dir/bar"), &bar).await.unwrap();
+
+    let outputs = super::upload_dir::<anyhow::Error, _>(
+        &client,
+        &root,
+        &bucket,
+        &format!("{key}/"),
+        PART_SIZE,
+        Some(2.try_into().unwrap()),
+    )
+    .await;
+    tokio::fs::remove_dir_all(&root).await.unwrap();
+    let mut outputs = outputs.unwrap();
+    outputs.sort_by(|a, b| a.0.cmp(&b.0));
+    assert!(matches!(
+        &outputs[..],
+        [
+            (_, super::ObjectOutput::Put(_)),
+            (_, super::ObjectOutput::MultipartUpload(_)),
+        ]
+    ));
+
+    for (path, body) in [("foo", foo), ("dir/bar", bar)] {
+        let output = client
+            .get_object()
+            .bucket(&bucket)
+            .key(format!("{key}/{path}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_upload_batch() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let path = env::temp_dir().join(&key);
+    let foo = (0..*PART_SIZE.start() * 3 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let bar = (0..1000).map(|_| rng.gen()).collect::<Bytes>();
+    tokio::fs::write(&path, &foo).await.unwrap();
+
+    let mut outputs = super::upload_batch::<anyhow::Error, _, super::Source, _>(
+        [
+            (path.clone().into(), format!("{key}/foo")),
+            (ByteStream::from(bar.clone()).into(), format!("{key}/bar")),
+        ],
+        |key| MultipartUpload::new(&client).bucket(&bucket).key(key),
+        PART_SIZE,
+        Some(2.try_into().unwrap()),
+    )
+    .collect::<Vec<_>>()
+    .await;
+    tokio::fs::remove_file(&path).await.unwrap();
+    outputs.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(outputs[0].1.as_ref().unwrap().content_length, 1000);
+    assert_eq!(
+        outputs[1].1.as_ref().unwrap().content_length,
+        foo.len() as u64
+    );
+
+    for (name, body) in [("foo", foo), ("bar", bar)] {
+        let output = client
+            .get_object()
+            .bucket(&bucket)
+            .key(format!("{key}/{name}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_sync() {
+    if skip_locally("ETags of multipart uploads are not S3-style") {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let root = env::temp_dir().join(&key);
+    let prefix = format!("{key}/");
+    let foo = (0..*PART_SIZE.start() * 3 / 2)
+        .map(|_| rng.gen())
+        .collect::<Vec<u8>>();
+    let bar = (0..1000).map(|_| rng.gen()).collect::<Vec<u8>>();
+    tokio::fs::create_dir_all(&root).await.unwrap();
+    tokio::fs::write(root.join("foo"), &foo).await.unwrap();
+    tokio::fs::write(root.join("bar"), &bar).await.unwrap();
+    client
+        .put_object()
+        .bucket(&bucket)
+        .key(format!("{prefix}baz"))
+        .body(ByteStream::from_static(b"baz"))
+        .send()
+        .await
+        .unwrap();
+
+    let sync = |delete| {
+        super::sync::<anyhow::Error, _>(&client, &root, &bucket, &prefix, PART_SIZE, None, delete)
+    };
+    let output = sync(false).await.unwrap();
+    assert_eq!(output.uploaded.len(), 2);
+    assert!(output.skipped.is_empty());
+    assert!(output.deleted.is_empty());
+
+    tokio::fs::write(root.join("bar"), b"bar").await.unwrap();
+    let output = sync(true).await;
+    tokio::fs::remove_dir_all(&root).await.unwrap();
+    let output = output.unwrap();
+    assert_eq!(
+        output
+            .uploaded
+            .iter()
+            .map(|(key, _)| &key[..])
+            .collect::<Vec<_>>(),
+        [format!("{prefix}bar")]
+    );
+    assert_eq!(output.skipped, [format!("{prefix}foo")]);
+    assert_eq!(output.deleted, [format!("{prefix}baz")]);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_upload_manager() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let manager = super::UploadManager::new(&client, 2.try_into().unwrap())
+        .bytes_buffered(*PART_SIZE.start() * 2);
+    let bodies = (0..3)
+        .map(|_| {
+            (0..*PART_SIZE.start() * 5 / 2)
+                .map(|_| rng.gen())
+                .collect::<Bytes>()
+        })
+        .collect::<Vec<_>>();
+
+    let outputs = futures::future::join_all(bodies.iter().enumerate().map(|(i, body)| {
+        let upload = manager
+            .multipart_upload()
+            .bucket(&bucket)
+            .key(format!("{key}/{i}"))
+            .body(ByteStream::from(body.clone()));
+        manager.send::<anyhow::Error>(upload, PART_SIZE)
+    }))
+    .await;
+
+    for (i, (output, body)) in outputs.into_iter().zip(bodies).enumerate() {
+        assert_eq!(output.unwrap().content_length, body.len() as u64);
+        let output = client
+            .get_object()
+            .bucket(&bucket)
+            .key(format!("{key}/{i}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limiter() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    // one second of burst and one second of waiting
+    let rate_limiter = std::sync::Arc::new(super::RateLimiter::new(
+        (body.len() as u64).try_into().unwrap(),
+    ));
+
+    let start = std::time::Instant::now();
+    let outputs = futures::future::join_all((0..2).map(|i| {
+        MultipartUpload::new(&client)
+            .bucket(&bucket)
+            .key(format!("{key}/{i}"))
+            .body(ByteStream::from(body.clone()))
+            .rate_limiter(rate_limiter.clone())
+            .send::<anyhow::Error>(PART_SIZE, None)
+    }))
+    .await;
+    assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+    for output in outputs {
+        assert_eq!(output.unwrap().content_length, body.len() as u64);
+    }
+}
+
+#[cfg(feature = "copy")]
+#[tokio::test]
+async fn test_multipart_copy() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let source_key = format!("{key} source");
+    MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&source_key)
+        .body(ByteStream::from(body.clone()))
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = super::MultipartCopy::new(MultipartUpload::new(&client).bucket(&bucket).key(&key))
+        .source_bucket(&bucket)
+        .source_key(&source_key)
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+    assert_eq!(output.content_length, body.len() as u64);
+    assert_eq!(output.parts.len(), 3);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[tokio::test]
+async fn test_presign_parts() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = http_context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let mut initiated = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .initiate::<anyhow::Error>()
+        .await
+        .unwrap();
+    let presigned_parts = initiated
+        .presign_parts::<anyhow::Error>(
+            body.len() as _,
+            PART_SIZE,
+            std::time::Duration::from_secs(600),
+        )
+        .await
+        .unwrap();
+    assert_eq!(presigned_parts.len(), 3);
+    assert_eq!(presigned_parts[2].range.end, body.len() as u64);
+
+    // stands in for the client that receives the presigned requests
+    for presigned_part in &presigned_parts {
+        assert_eq!(presigned_part.request.method(), "PUT");
+        assert!(presigned_part
+            .request
+            .uri()
+            .contains(&format!("partNumber={}", presigned_part.number)));
+        let range = presigned_part.range.start as usize..presigned_part.range.end as usize;
+        client
+            .upload_part()
+            .bucket(&bucket)
+            .key(&key)
+            .upload_id(initiated.upload_id().unwrap())
+            .part_number(presigned_part.number)
+            .body(ByteStream::from(body.slice(range)))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let mut initiated = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(initiated.upload_id().unwrap())
+        .initiate::<anyhow::Error>()
+        .await
+        .unwrap();
+    initiated.list_parts::<anyhow::Error>().await.unwrap();
+    assert_eq!(initiated.parts().len(), 3);
+    let output = initiated.complete::<anyhow::Error>().await.unwrap();
+    assert_eq!(output.content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_send_blocking() {
+    let mut rng = rand::thread_rng();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (client, bucket, key) = runtime.block_on(context());
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let output = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(body.clone()))
+        .send_blocking::<anyhow::Error>(PART_SIZE, None)
+        .unwrap();
+    assert_eq!(output.content_length, body.len() as u64);
+
+    let output = runtime
+        .block_on(client.get_object().bucket(&bucket).key(&key).send())
+        .unwrap();
+    assert_eq!(
+        runtime
+            .block_on(output.body.collect())
+            .unwrap()
+            .into_bytes(),
+        body,
+    );
+}
+
+#[cfg(feature = "hyper")]
+#[tokio::test]
+async fn test_upload_presigned() {
+    use aws_sdk_s3::config::{AsyncSleep, SharedAsyncSleep, Sleep};
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+
+    #[derive(Debug)]
+    struct TokioSleep;
+
+    impl AsyncSleep for TokioSleep {
+        fn sleep(&self, duration: std::time::Duration) -> Sleep {
+            Sleep::new(tokio::time::sleep(duration))
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = http_context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    let mut initiated = MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .initiate::<anyhow::Error>()
+        .await
+        .unwrap();
+    let presigned_parts = initiated
+        .presign_parts::<anyhow::Error>(
+            body.len() as _,
+            PART_SIZE,
+            std::time::Duration::from_secs(600),
+        )
+        .await
+        .unwrap();
+
+    let uploaded_parts = super::upload_presigned::<_, anyhow::Error>(
+        &Client::builder(TokioExecutor::new()).build_http(),
+        presigned_parts.clone(),
+        ByteStream::from(body.clone()),
+        SharedAsyncSleep::new(TokioSleep),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(uploaded_parts.len(), 3);
+    assert!(uploaded_parts
+        .iter()
+        .all(|uploaded_part| uploaded_part.attempts == 1));
+
+    // the body is longer than the presigned parts
+    let err = super::upload_presigned::<_, anyhow::Error>(
+        &Client::builder(TokioExecutor::new()).build_http(),
+        presigned_parts[..2].to_vec(),
+        ByteStream::from(body.clone()),
+        SharedAsyncSleep::new(TokioSleep),
+        None,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err.error
+            .downcast_ref::<super::PartError<super::PresignedError>>()
+            .unwrap()
+            .source,
+        super::PresignedError::Range,
+    ));
+
+    initiated.list_parts::<anyhow::Error>().await.unwrap();
+    let output = initiated.complete::<anyhow::Error>().await.unwrap();
+    assert_eq!(output.content_length, body.len() as u64);
+
+    let output = client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(output.body.collect().await.unwrap().into_bytes(), body);
+}
+
+#[cfg(feature = "download")]
+#[tokio::test]
+async fn test_multipart_download() {
+    use futures::TryStreamExt;
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    client
+        .put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(body.clone()))
+        .send()
+        .await
+        .unwrap();
+
+    let output = super::MultipartDownload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .send::<anyhow::Error>((1 << 20).try_into().unwrap(), Some(4.try_into().unwrap()))
+        .await
+        .unwrap();
+    assert_eq!(output.head.content_length, Some(body.len() as i64));
+    let downloaded = output.body.try_collect::<Vec<_>>().await.unwrap().concat();
+    assert_eq!(downloaded, body);
+}
+
+#[cfg(feature = "download")]
+#[tokio::test]
+async fn test_multipart_download_verify() {
+    use futures::TryStreamExt;
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_algorithm(ChecksumAlgorithm::Crc32)
+        .body(ByteStream::from(body.clone()))
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let output = super::MultipartDownload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .verify(true)
+        .send::<anyhow::Error>((1 << 20).try_into().unwrap(), None)
+        .await
+        .unwrap();
+    let downloaded = output.body.try_collect::<Vec<_>>().await.unwrap().concat();
+    assert_eq!(downloaded, body);
+}
+
+#[cfg(all(feature = "download", feature = "tokio"))]
+#[tokio::test]
+async fn test_multipart_download_resume() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(body.clone()))
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+
+    let path = env::temp_dir().join(&key);
+    // one complete part and a partial one
+    tokio::fs::write(&path, &body[..*PART_SIZE.start() as usize + 1000])
+        .await
+        .unwrap();
+    let output = super::MultipartDownload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .verify(true)
+        .send_to_path::<anyhow::Error, _>(&path, (1 << 20).try_into().unwrap(), None)
+        .await;
+    let downloaded = tokio::fs::read(&path).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+    output.unwrap();
+    assert_eq!(downloaded, body);
+}
+
+#[cfg(all(feature = "download", feature = "tokio"))]
+#[tokio::test]
+async fn test_multipart_download_to_path() {
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Bytes>();
+    MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .body(ByteStream::from(body.clone()))
+        .send::<anyhow::Error>(PART_SIZE, None)
+        .await
+        .unwrap();
+    let download = || {
+        super::MultipartDownload::new(&client)
+            .bucket(&bucket)
+            .key(&key)
+            .verify(true)
+    };
+
+    let path = env::temp_dir().join(&key);
+    let output = download()
+        .positional(true)
+        .send_to_path::<anyhow::Error, _>(&path, (1 << 20).try_into().unwrap(), None)
+        .await;
+    let downloaded = tokio::fs::read(&path).await.unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+    output.unwrap();
+    assert_eq!(downloaded, body);
+
+    let mut downloaded = Vec::new();
+    download()
+        .to_writer::<anyhow::Error, _>(&mut downloaded, (1 << 20).try_into().unwrap(), None)
+        .await
+        .unwrap();
+    assert_eq!(downloaded, body);
+}
+
+#[tokio::test]
+async fn test_abort_incomplete_uploads() {
+    if skip_locally("ListMultipartUploads is not implemented") {
+        return;
+    }
+
+    let (client, bucket, key) = context().await;
+    let upload_id = client
+        .create_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .unwrap()
+        .upload_id
+        .unwrap();
+
+    let aborted = super::abort_incomplete_uploads(
+        &client,
+        &bucket,
+        std::time::Duration::from_secs(3600),
+        Some(&key),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(aborted.is_empty());
+
+    // initiation times have a resolution of one second
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let aborted = super::abort_incomplete_uploads(
+        &client,
+        &bucket,
+        std::time::Duration::ZERO,
+        Some(&key),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(aborted, [(key, upload_id)]);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_audit() {
+    if skip_locally("GetObjectAttributes is not implemented") {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+
+    let (client, bucket, key) = context().await;
+    let mut body = (0..*PART_SIZE.start() * 5 / 2)
+        .map(|_| rng.gen())
+        .collect::<Vec<u8>>();
+    MultipartUpload::new(&client)
+        .bucket(&bucket)
+        .key(&key)
+        .checksum_algorithm(ChecksumAlgorithm::Crc32)
+        .body(ByteStream::from(body.clone()))
+        .send::<anyhow::Error>(*PART_SIZE.start()..=*PART_SIZE.start(), None)
+        .await
+        .unwrap();
+
+    let path = env::temp_dir().join(&key);
+    tokio::fs::write(&path, &body).await.unwrap();
+    let identical = super::audit::<anyhow::Error, _>(&client, &bucket, &key, &path).await;
+    body[*PART_SIZE.start() as usize + 1] ^= 1;
+    tokio::fs::write(&path, &body).await.unwrap();
+    let differing = super::audit::<anyhow::Error, _>(&client, &bucket, &key, &path).await;
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    assert!(identical.unwrap().is_empty());
+    let part_size = *PART_SIZE.start();
+    let differing = differing.unwrap();
+    assert_eq!(differing.len(), 1);
+    assert_eq!(differing[0], part_size..part_size * 2);
 }
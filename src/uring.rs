@@ -0,0 +1,182 @@
+use crate::into_byte_stream;
+use aws_sdk_s3::primitives::{ByteStream, ByteStreamError, SdkBody};
+use aws_smithy_types::byte_stream::Length;
+use bytes::Bytes;
+use io_uring::{opcode, types, IoUring};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::OnceLock;
+use tokio::sync::mpsc;
+
+/// Reads in flight per body.
+const QUEUE_DEPTH: usize = 8;
+/// Bytes per read.
+const CHUNK_SIZE: u64 = 1 << 20;
+
+/// Reads `len` bytes of the file at `path` from `offset` with io_uring on a blocking thread,
+/// keeping up to [`QUEUE_DEPTH`] reads in flight. Each attempt of a request reads the range
+/// again. Falls back to [`ByteStream::read_from`] where io_uring is not available, e.g. when
+/// seccomp blocks it.
+pub(crate) async fn read_range(
+    path: &Path,
+    offset: u64,
+    len: u64,
+) -> Result<ByteStream, ByteStreamError> {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    if !*AVAILABLE.get_or_init(|| IoUring::new(1).is_ok()) {
+        return ByteStream::read_from()
+            .path(path)
+            .offset(offset)
+            .length(Length::Exact(len))
+            .build()
+            .await;
+    }
+    let path = path.to_owned();
+    let runtime = tokio::runtime::Handle::current();
+    Ok(ByteStream::new(SdkBody::retryable(move || {
+        let (tx, mut rx) = mpsc::channel(QUEUE_DEPTH);
+        let path = path.clone();
+        runtime.spawn_blocking(move || {
+            let result = File::open(&path)
+                .and_then(|file| Reader::new(file, offset, len))
+                .and_then(|mut reader| reader.run(&tx));
+            if let Err(err) = result {
+                let _ = tx.blocking_send(Err(err));
+            }
+        });
+        into_byte_stream::from_stream(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+            .into_inner()
+    })))
+}
+
+struct Reader {
+    ring: IoUring,
+    file: File,
+    offset: u64,
+    chunks: u64,
+    len: u64,
+    /// The buffers of the chunks read or being read, by index, and the bytes read into them.
+    buffers: BTreeMap<u64, (Vec<u8>, usize)>,
+    /// Reads submitted but not completed yet.
+    pending: usize,
+}
+
+impl Reader {
+    fn new(file: File, offset: u64, len: u64) -> io::Result<Self> {
+        Ok(Self {
+            ring: IoUring::new(QUEUE_DEPTH as _)?,
+            file,
+            offset,
+            chunks: len.div_ceil(CHUNK_SIZE),
+            len,
+            buffers: BTreeMap::new(),
+            pending: 0,
+        })
+    }
+
+    // sends the chunks to `tx` in order, until the receiver is dropped
+    fn run(&mut self, tx: &mpsc::Sender<io::Result<Bytes>>) -> io::Result<()> {
+        let (mut next_read, mut next_send) = (0, 0);
+        while next_send < self.chunks {
+            while next_read < self.chunks && self.buffers.len() < QUEUE_DEPTH {
+                let len = CHUNK_SIZE.min(self.len - next_read * CHUNK_SIZE);
+                self.buffers.insert(next_read, (vec![0; len as _], 0));
+                self.push(next_read)?;
+                next_read += 1;
+            }
+            self.ring.submit_and_wait(1)?;
+            let completed = self
+                .ring
+                .completion()
+                .map(|cqe| (cqe.user_data(), cqe.result()))
+                .collect::<Vec<_>>();
+            for (index, result) in completed {
+                self.pending -= 1;
+                let read = match result {
+                    ..0 => return Err(io::Error::from_raw_os_error(-result)),
+                    0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    read => read as usize,
+                };
+                let (buffer, filled) = self.buffers.get_mut(&index).unwrap();
+                *filled += read;
+                if *filled < buffer.len() {
+                    self.push(index)?;
+                }
+            }
+            while let Some((buffer, filled)) = self.buffers.get(&next_send) {
+                if *filled < buffer.len() {
+                    break;
+                }
+                let (buffer, _) = self.buffers.remove(&next_send).unwrap();
+                if tx.blocking_send(Ok(buffer.into())).is_err() {
+                    return Ok(());
+                }
+                next_send += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // submits a read of the rest of chunk `index`
+    fn push(&mut self, index: u64) -> io::Result<()> {
+        let (buffer, filled) = self.buffers.get_mut(&index).unwrap();
+        let rest = &mut buffer[*filled..];
+        let entry = opcode::Read::new(
+            types::Fd(self.file.as_raw_fd()),
+            rest.as_mut_ptr(),
+            rest.len() as _,
+        )
+        .offset(self.offset + index * CHUNK_SIZE + *filled as u64)
+        .build()
+        .user_data(index);
+        // SAFETY: the buffer stays allocated and the file stays open until the read completes,
+        // since `Drop` waits for the reads in flight.
+        unsafe { self.ring.submission().push(&entry) }
+            .map_err(|_| io::Error::other("the submission queue is full"))?;
+        self.pending += 1;
+        Ok(())
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        while self.pending > 0 {
+            if self.ring.submit_and_wait(1).is_err() {
+                // the kernel may still write into the buffers
+                std::mem::forget(std::mem::take(&mut self.buffers));
+                return;
+            }
+            self.pending -= self.ring.completion().count();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_range, CHUNK_SIZE};
+
+    #[tokio::test]
+    async fn test_read_range() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let body = (0..CHUNK_SIZE * 10 + 7)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        std::fs::write(&path, &body).unwrap();
+
+        let (offset, len) = (100, CHUNK_SIZE * 9 + 3);
+        let read = read_range(&path, offset, len).await.unwrap();
+        let read = read.collect().await.unwrap().into_bytes();
+        assert_eq!(read, body[offset as usize..(offset + len) as usize]);
+
+        // the file ends within the range
+        let read = match read_range(&path, CHUNK_SIZE * 10, CHUNK_SIZE).await {
+            Ok(read) => read.collect().await.map(drop),
+            Err(err) => Err(err),
+        };
+        assert!(read.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
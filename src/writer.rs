@@ -0,0 +1,103 @@
+use crate::{MultipartUploadError, MultipartUploadOutput, S3Sink};
+use bytes::{BufMut, BytesMut};
+use futures::Sink;
+use std::fmt::Display;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncWrite;
+
+const CAPACITY: usize = 1 << 20;
+
+/// An [`AsyncWrite`] that uploads everything written to it. `shutdown()` completes the upload.
+///
+/// Upload failures are reported as [`io::Error`]s; the [`MultipartUploadError`] itself is kept
+/// and returned by [`S3Writer::into_result`].
+pub struct S3Writer<E> {
+    sink: S3Sink<E>,
+    buffer: BytesMut,
+    error: Option<MultipartUploadError<E>>,
+}
+
+impl<E> Unpin for S3Writer<E> {}
+
+impl<E> S3Writer<E>
+where
+    E: Display,
+{
+    pub(crate) fn new(sink: S3Sink<E>) -> Self {
+        Self {
+            sink,
+            buffer: BytesMut::new(),
+            error: None,
+        }
+    }
+
+    /// The output of the upload once `shutdown()` has succeeded.
+    pub fn output(&self) -> Option<&MultipartUploadOutput> {
+        self.sink.output()
+    }
+
+    /// The result of the upload once it has finished.
+    pub fn into_result(self) -> Option<Result<MultipartUploadOutput, MultipartUploadError<E>>> {
+        match self.error {
+            Some(err) => Some(Err(err)),
+            None => self.sink.into_output().map(Ok),
+        }
+    }
+
+    fn check(&mut self, result: Result<(), MultipartUploadError<E>>) -> io::Result<()> {
+        result.map_err(|err| {
+            let io_err = io::Error::other(err.to_string());
+            self.error = Some(err);
+            io_err
+        })
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.error.is_some() {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if !self.buffer.is_empty() {
+            let result = ready!(Pin::new(&mut self.sink).poll_ready(cx));
+            self.check(result)?;
+            let chunk = self.buffer.split().freeze();
+            let result = Pin::new(&mut self.sink).start_send(chunk);
+            self.check(result)?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<E> AsyncWrite for S3Writer<E>
+where
+    E: Display,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.buffer.len() >= CAPACITY {
+            ready!(this.poll_send(cx))?;
+        }
+        this.buffer.put_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    /// Hands the buffered data to the upload. It does not wait for the parts to be uploaded.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        let result = ready!(Pin::new(&mut this.sink).poll_flush(cx));
+        Poll::Ready(this.check(result))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_send(cx))?;
+        let result = ready!(Pin::new(&mut this.sink).poll_close(cx));
+        Poll::Ready(this.check(result))
+    }
+}